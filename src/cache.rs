@@ -0,0 +1,185 @@
+use crate::imports::{ModuleAnalysis, ModuleIdentifier};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Directory of the parse cache, in the project root.
+pub const CACHE_DIR: &str = ".pydep-cache";
+
+const CACHE_FILE: &str = "parse-cache.json";
+
+/// The analysis of a file, valid while the file keeps its size and modification time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    size: u64,
+    modified_nanos: u64,
+    module_id: ModuleIdentifier,
+    analysis: ModuleAnalysis,
+}
+
+/// Size and modification time of a file, to tell whether it changed since it was cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    size: u64,
+    modified_nanos: u64,
+}
+
+impl FileStamp {
+    /// Reads the stamp of a file, or `None` if its metadata cannot be read.
+    pub fn of(file_path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(file_path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified_nanos: u64::try_from(modified.as_nanos()).ok()?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    fingerprint: String,
+    /// Entries keyed by file path relative to the project root
+    entries: HashMap<String, CacheEntry>,
+}
+
+/// Analyses of the files of a previous run, stored in `.pydep-cache/`.
+///
+/// Each entry is reused while its file keeps its size and modification time. Analyses are
+/// cached before their `from package import name` statements are resolved, so adding or
+/// removing other files does not invalidate them. The whole cache is dropped when its
+/// fingerprint changes: another version of the tool or another project configuration.
+#[derive(Debug)]
+pub struct ParseCache {
+    directory: PathBuf,
+    cache: CacheFile,
+    /// Entries of the current run, replacing the cache when saved
+    updated: HashMap<String, CacheEntry>,
+}
+
+impl ParseCache {
+    /// Loads the cache of a project, empty if it is missing, unreadable or stale.
+    pub fn load(project_root: &Path) -> Self {
+        let directory = project_root.join(CACHE_DIR);
+        let fingerprint = fingerprint(project_root);
+        let cache = fs::read_to_string(directory.join(CACHE_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|cache| cache.fingerprint == fingerprint)
+            .unwrap_or_else(|| CacheFile {
+                fingerprint,
+                entries: HashMap::new(),
+            });
+        Self {
+            directory,
+            cache,
+            updated: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached analysis of a file if it did not change since.
+    pub fn get(
+        &self,
+        location_path: &str,
+        stamp: FileStamp,
+    ) -> Option<(ModuleIdentifier, ModuleAnalysis)> {
+        let entry = self.cache.entries.get(location_path)?;
+        (entry.size == stamp.size && entry.modified_nanos == stamp.modified_nanos)
+            .then(|| (entry.module_id.clone(), entry.analysis.clone()))
+    }
+
+    /// Records the analysis of a file for the next run.
+    pub fn insert(
+        &mut self,
+        location_path: &str,
+        stamp: FileStamp,
+        module_id: &ModuleIdentifier,
+        analysis: &ModuleAnalysis,
+    ) {
+        self.updated.insert(
+            location_path.to_string(),
+            CacheEntry {
+                size: stamp.size,
+                modified_nanos: stamp.modified_nanos,
+                module_id: module_id.clone(),
+                analysis: analysis.clone(),
+            },
+        );
+    }
+
+    /// Writes the entries recorded in this run, dropping those of deleted files.
+    pub fn save(self) -> Result<()> {
+        fs::create_dir_all(&self.directory)?;
+        // Keep the cache out of version control, like pytest and mypy caches
+        fs::write(self.directory.join(".gitignore"), "*\n")?;
+        let cache = CacheFile {
+            fingerprint: self.cache.fingerprint,
+            entries: self.updated,
+        };
+        fs::write(
+            self.directory.join(CACHE_FILE),
+            serde_json::to_string(&cache)?,
+        )?;
+        Ok(())
+    }
+}
+
+/// Hashes what the analysis of a file depends on besides its content.
+fn fingerprint(project_root: &Path) -> String {
+    let mut hasher = DefaultHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    for file_name in ["pyproject.toml", "setup.cfg", "setup.py"] {
+        fs::read(project_root.join(file_name))
+            .unwrap_or_default()
+            .hash(&mut hasher);
+    }
+    serde_json::to_string(&crate::pyproject::get_package_info())
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let file_path = root.join("app.py");
+        fs::write(&file_path, "import os\n").unwrap();
+        let stamp = FileStamp::of(&file_path).unwrap();
+
+        let module_id = ModuleIdentifier {
+            origin: crate::imports::ModuleOrigin::Internal,
+            canonical_path: "app".to_string(),
+        };
+        let analysis = ModuleAnalysis::default();
+        let mut cache = ParseCache::load(root);
+        assert!(cache.get("app.py", stamp).is_none());
+        cache.insert("app.py", stamp, &module_id, &analysis);
+        cache.save().unwrap();
+        assert!(root.join(CACHE_DIR).join(".gitignore").is_file());
+
+        let cache = ParseCache::load(root);
+        assert_eq!(cache.get("app.py", stamp), Some((module_id, analysis)));
+
+        // A changed file misses
+        let changed = FileStamp {
+            size: stamp.size + 1,
+            ..stamp
+        };
+        assert!(cache.get("app.py", changed).is_none());
+
+        // Another configuration drops the whole cache
+        fs::write(root.join("pyproject.toml"), "[project]\nname = \"app\"\n").unwrap();
+        assert!(ParseCache::load(root).get("app.py", stamp).is_none());
+    }
+}
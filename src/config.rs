@@ -0,0 +1,205 @@
+use crate::tools::changeset::RiskThresholds;
+use crate::tools::diagnose::DiagnoseSettings;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// Configuration file read when pyproject.toml has no `[tool.pydep-mapper]` table.
+pub const CONFIG_FILE: &str = ".pydep-mapper.toml";
+
+/// Output format of the analysis commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Project settings, read from the `[tool.pydep-mapper]` table of pyproject.toml or
+/// from `.pydep-mapper.toml`. Command-line options take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Config {
+    /// Directories searched for packages when none are configured (default: `.` and `src`)
+    pub source_roots: Vec<String>,
+    /// Only analyze files matching these globs
+    pub include: Vec<String>,
+    /// Skip files and directories matching these globs
+    pub exclude: Vec<String>,
+    /// Also analyze directories named `tests`
+    pub include_tests: bool,
+    /// Analyze files ignored by .gitignore and .ignore files
+    pub no_ignore: bool,
+    /// Globs of test code, in addition to the default test paths
+    pub test_paths: Vec<String>,
+    /// Dependency groups of source paths, as `PATTERN=GROUP`
+    pub group_paths: Vec<String>,
+    /// Default output format
+    pub format: Option<OutputFormat>,
+    pub changeset: ChangesetConfig,
+    pub diagnose: DiagnoseSettings,
+}
+
+/// Settings of the `changeset` command
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ChangesetConfig {
+    pub risk_thresholds: RiskThresholds,
+}
+
+impl Config {
+    /// Reads the configuration of a project, or the defaults if it has none.
+    ///
+    /// A pyproject.toml that is not valid TOML is skipped with a warning, as other
+    /// commands may still analyze the project; an invalid `[tool.pydep-mapper]` table
+    /// or `.pydep-mapper.toml` is an error.
+    pub fn load(project_root: &Path) -> Result<Self> {
+        let pyproject_path = project_root.join("pyproject.toml");
+        if pyproject_path.is_file() {
+            match toml::from_str::<toml::Value>(&std::fs::read_to_string(&pyproject_path)?) {
+                Ok(toml) => {
+                    if let Some(table) = toml.get("tool").and_then(|t| t.get("pydep-mapper")) {
+                        let config: Self = table.clone().try_into().with_context(|| {
+                            format!(
+                                "Invalid [tool.pydep-mapper] table in {}",
+                                pyproject_path.display()
+                            )
+                        })?;
+                        return config.validated(&pyproject_path);
+                    }
+                }
+                Err(e) => eprintln!(
+                    "Warning: Ignoring the settings of {}: {}",
+                    pyproject_path.display(),
+                    e.message()
+                ),
+            }
+        }
+
+        let config_path = project_root.join(CONFIG_FILE);
+        if config_path.is_file() {
+            let config: Self = toml::from_str(&std::fs::read_to_string(&config_path)?)
+                .with_context(|| format!("Invalid configuration in {}", config_path.display()))?;
+            return config.validated(&config_path);
+        }
+        Ok(Self::default())
+    }
+
+    /// Returns the `PATTERN=GROUP` mappings of the configured test and group paths.
+    pub fn group_mappings(&self) -> Vec<String> {
+        self.group_paths
+            .iter()
+            .cloned()
+            .chain(
+                self.test_paths
+                    .iter()
+                    .map(|pattern| format!("{}=test", pattern)),
+            )
+            .collect()
+    }
+
+    fn validated(self, path: &Path) -> Result<Self> {
+        self.changeset
+            .risk_thresholds
+            .validate()
+            .and_then(|()| self.diagnose.validate())
+            .and_then(|()| crate::declared::SourceGroups::new(&self.group_mappings()).map(drop))
+            .with_context(|| format!("Invalid configuration in {}", path.display()))?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::changeset::RiskLevel;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_config() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        assert_eq!(Config::load(root).unwrap(), Config::default());
+
+        // The fallback file holds the settings at its top level
+        fs::write(
+            root.join(CONFIG_FILE),
+            "exclude = [\"migrations/**\"]\nformat = \"json\"\n",
+        )
+        .unwrap();
+        let config = Config::load(root).unwrap();
+        assert_eq!(config.exclude, vec!["migrations/**"]);
+        assert_eq!(config.format, Some(OutputFormat::Json));
+
+        // A pyproject.toml that does not parse is skipped
+        fs::write(root.join("pyproject.toml"), "[project\nname = \"demo\"\n").unwrap();
+        assert_eq!(Config::load(root).unwrap().exclude, vec!["migrations/**"]);
+
+        // pyproject.toml takes precedence
+        fs::write(
+            root.join("pyproject.toml"),
+            r#"
+[project]
+name = "demo"
+
+[tool.pydep-mapper]
+source-roots = ["lib"]
+test-paths = ["qa/**"]
+no-ignore = true
+
+[tool.pydep-mapper.changeset]
+risk-thresholds = { medium = 2, high = 5, critical = 20 }
+
+[tool.pydep-mapper.diagnose]
+pressure-levels = [5, 20, 40]
+score-weights = { cycles = 2.0, external = 0.5 }
+"#,
+        )
+        .unwrap();
+        let config = Config::load(root).unwrap();
+        assert_eq!(config.source_roots, vec!["lib"]);
+        assert!(config.exclude.is_empty());
+        assert!(config.no_ignore);
+        assert_eq!(config.format, None);
+        assert_eq!(config.group_mappings(), vec!["qa/**=test"]);
+        let thresholds = config.changeset.risk_thresholds;
+        assert_eq!(thresholds.risk_level(4), RiskLevel::Medium);
+        assert_eq!(thresholds.risk_level(20), RiskLevel::Critical);
+        assert_eq!(config.diagnose.pressure_levels, (5, 20, 40));
+        assert_eq!(config.diagnose.top_cycles, 5);
+        let weights = config.diagnose.score_weights;
+        assert_eq!((weights.cycles, weights.instability), (2.0, 1.0));
+        assert_eq!(weights.external, 0.5);
+    }
+
+    #[test]
+    fn test_load_config_errors() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let error = |config: &str| {
+            fs::write(root.join("pyproject.toml"), config).unwrap();
+            format!("{:#}", Config::load(root).unwrap_err())
+        };
+
+        let message = error("[tool.pydep-mapper]\nexlude = [\"build/**\"]\n");
+        assert!(message.contains("[tool.pydep-mapper]"), "{}", message);
+        assert!(message.contains("unknown field `exlude`"), "{}", message);
+
+        let message = error("[tool.pydep-mapper.changeset.risk-thresholds]\nlow = 1\n");
+        assert!(message.contains("unknown field `low`"), "{}", message);
+
+        let message = error("[tool.pydep-mapper]\nformat = \"yaml\"\n");
+        assert!(message.contains("unknown variant `yaml`"), "{}", message);
+
+        let message =
+            error("[tool.pydep-mapper.changeset.risk-thresholds]\nmedium = 10\nhigh = 5\n");
+        assert!(message.contains("must be increasing"), "{}", message);
+
+        let message = error("[tool.pydep-mapper]\ngroup-paths = [\"scripts/**=prod\"]\n");
+        assert!(message.contains("prod"), "{}", message);
+
+        let message = error("[tool.pydep-mapper.diagnose.score-weights]\ncycles = -1.0\n");
+        assert!(message.contains("Score weights"), "{}", message);
+    }
+}
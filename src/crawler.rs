@@ -1,14 +1,81 @@
-use crate::graph::{DependencyGraph, DependencyType, utils::add_containment_relationships};
-use crate::imports::{ModuleIdentifier, ModuleOrigin, extract_module_deps};
+use crate::cache::{CACHE_DIR, FileStamp, ParseCache};
+use crate::diagnostics::{Diagnostic, DiagnosticKind};
+use crate::encoding::{SourceDecodeError, decode_source};
+use crate::graph::utils::{add_containment_relationships, mark_namespace_packages};
+use crate::graph::{DependencyEdge, DependencyGraph};
+use crate::imports::{
+    ImportKind, ModuleAnalysis, ModuleExports, ModuleIdentifier, ModuleImport, ModuleOrigin,
+    SyntaxError, UnresolvedImport, extract_module_deps, merge_imports, parse_module_source,
+};
+use crate::lockfile::{Lockfile, find_lockfile};
+use crate::notebook::extract_code_cells;
 use anyhow::Result;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
 use indicatif::{ProgressBar, ProgressStyle};
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+
+/// Options controlling how the dependency graph is built.
+#[derive(Debug, Clone, Default)]
+pub struct GraphOptions {
+    /// Route imports of names re-exported by a package `__init__.py` to the modules
+    /// defining them instead of the package
+    pub trace_reexports: bool,
+    /// Add symbol nodes for top-level functions, classes and constants, with edges from
+    /// the modules importing them
+    pub symbols: bool,
+    /// Maximum number of files parsed in parallel (all cores when `None`)
+    pub jobs: Option<usize>,
+    /// Reuse the analyses of unchanged files from the `.pydep-cache/` directory of the
+    /// project, and update it
+    pub cache: bool,
+    /// Which files of the directory are analyzed
+    pub discovery: DiscoveryOptions,
+}
+
+/// Options controlling which files of a directory are analyzed.
+///
+/// Hidden directories, `__pycache__`, `node_modules` and virtual environments are
+/// always skipped.
+#[derive(Debug, Clone)]
+pub struct DiscoveryOptions {
+    /// Only analyze files matching one of these globs, relative to the root (all files
+    /// when empty)
+    pub include: Vec<String>,
+    /// Skip files and directories matching one of these globs, relative to the root
+    pub exclude: Vec<String>,
+    /// Skip directories named `tests`
+    pub exclude_tests: bool,
+    /// Skip what `.gitignore` and `.ignore` files (and `.git/info/exclude`) ignore
+    pub respect_ignore_files: bool,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            exclude_tests: true,
+            respect_ignore_files: true,
+        }
+    }
+}
 
 /// Builds a dependency graph from all Python files in a directory (recursive).
 pub fn build_directory_dependency_graph(dir_path: &Path) -> Result<DependencyGraph> {
-    let python_files = analyze_python_directory_recursive(dir_path)?;
+    build_directory_dependency_graph_with_options(dir_path, &GraphOptions::default())
+}
+
+/// Builds a dependency graph from all Python files in a directory (recursive) with the given options.
+pub fn build_directory_dependency_graph_with_options(
+    dir_path: &Path,
+    options: &GraphOptions,
+) -> Result<DependencyGraph> {
+    let python_files =
+        analyze_python_directory_recursive_with_options(dir_path, &options.discovery)?;
     let mut graph = DependencyGraph::new();
 
     if python_files.is_empty() {
@@ -26,40 +93,559 @@ pub fn build_directory_dependency_graph(dir_path: &Path) -> Result<DependencyGra
     );
     pb.set_message("Analyzing Python files");
 
-    for file_path in &python_files {
-        pb.set_message(format!(
-            "Processing {}",
-            file_path.file_name().unwrap_or_default().to_string_lossy()
+    // Module names of all discovered files, to resolve `from package import submodule`
+    let known_modules = known_modules(dir_path, &python_files);
+
+    let location_paths: Vec<String> = python_files
+        .iter()
+        .map(|file_path| {
+            let relative_path = file_path.strip_prefix(dir_path).unwrap_or(file_path);
+            relative_path.to_string_lossy().replace('\\', "/")
+        })
+        .collect();
+
+    // Analyses of the files unchanged since the previous run are reused
+    let mut cache = options.cache.then(|| ParseCache::load(dir_path));
+
+    // Parse every file first, in parallel: re-export tracing needs the exports of all
+    // packages. Results keep the order of the files so the graph is deterministic.
+    let parser = crate::pyproject::current();
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs.unwrap_or(0))
+        .start_handler(move |_| crate::pyproject::install(parser.clone()))
+        .build()?;
+    let parsed_files: Vec<(Option<FileStamp>, ParsedFile)> = pool.install(|| {
+        python_files
+            .par_iter()
+            .zip(&location_paths)
+            .map(|(file_path, location_path)| {
+                pb.set_message(format!(
+                    "Processing {}",
+                    file_path.file_name().unwrap_or_default().to_string_lossy()
+                ));
+                let stamp = cache.as_ref().and_then(|_| FileStamp::of(file_path));
+                let cached = cache
+                    .as_ref()
+                    .zip(stamp)
+                    .and_then(|(cache, stamp)| cache.get(location_path, stamp));
+                let parsed = match cached {
+                    Some(cached) => Ok(cached),
+                    None if is_notebook_file(file_path) => parse_notebook_file(file_path, dir_path),
+                    None => parse_python_file(file_path, dir_path),
+                };
+                pb.inc(1);
+                (stamp, parsed)
+            })
+            .collect()
+    });
+
+    let mut analyzed_files = Vec::new();
+    let mut stubs = Vec::new();
+    let mut reexports = ReexportIndex::default();
+    for ((file_path, location_path), (stamp, parsed)) in
+        python_files.iter().zip(location_paths).zip(parsed_files)
+    {
+        let (module_id, mut analysis) = match parsed {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                graph.add_diagnostic(file_diagnostic(&e, &location_path));
+                continue;
+            }
+        };
+        // The cache keeps analyses unresolved: they stay valid when other files change
+        if let Some((cache, stamp)) = cache.as_mut().zip(stamp) {
+            cache.insert(&location_path, stamp, &module_id, &analysis);
+        }
+        analysis.resolve(Some(&known_modules));
+        for unresolved in &analysis.unresolved_imports {
+            graph.add_diagnostic(unresolved_import_diagnostic(unresolved, &location_path));
+        }
+        for syntax_error in &analysis.syntax_errors {
+            graph.add_diagnostic(syntax_error_diagnostic(syntax_error, &location_path));
+        }
+
+        if is_notebook_file(file_path) {
+            analyzed_files.push((location_path, module_id, analysis.imports));
+        } else {
+            if file_path.file_stem().is_some_and(|stem| stem == "__init__") {
+                reexports.packages.insert(module_id.canonical_path.clone());
+            }
+            if is_stub_file(file_path) {
+                stubs.push((location_path, module_id, analysis.imports, analysis.exports));
+            } else {
+                reexports
+                    .exports
+                    .insert(module_id.canonical_path.clone(), analysis.exports);
+                analyzed_files.push((location_path, module_id, analysis.imports));
+            }
+        }
+    }
+    merge_stubs(&mut analyzed_files, &mut reexports, stubs);
+    if let Some(cache) = cache
+        && let Err(e) = cache.save()
+    {
+        // A cache that cannot be written only makes the next run slower
+        graph.add_diagnostic(Diagnostic::file(
+            DiagnosticKind::CacheWriteError,
+            CACHE_DIR,
+            format!("{:#}", e),
         ));
+    }
 
-        match analyze_python_file_with_package(file_path, dir_path) {
-            Ok((module_id, dependencies)) => {
-                graph.add_module(module_id.clone()); // Ignore duplicates - module might be added as dependency first
-                for dep in &dependencies {
-                    graph.add_module(dep.clone()); // Ignore duplicates
-                    graph.add_dependency(&module_id, dep, DependencyType::Imports)?;
+    for (location_path, module_id, imports) in &analyzed_files {
+        graph.add_module(module_id.clone()); // Ignore duplicates - module might be added as dependency first
+        for import in imports {
+            let edge = DependencyEdge::import(import, location_path);
+            let (traced, untraced) = if options.trace_reexports {
+                reexports.route(import)
+            } else {
+                (Vec::new(), true)
+            };
+
+            if untraced {
+                for target in import_targets(import, module_id, &known_modules) {
+                    graph.add_module(target.clone()); // Ignore duplicates
+                    graph.add_edge(module_id, &target, edge.clone())?;
                 }
             }
-            Err(e) => {
-                eprintln!(
-                    "Warning: Failed to analyze '{}': {}",
-                    file_path.display(),
-                    e
-                );
-                continue;
+            for target in traced {
+                let edge = DependencyEdge {
+                    via: Some(import.module.canonical_path.clone()),
+                    ..edge.clone()
+                };
+                graph.add_module(target.clone()); // Ignore duplicates
+                graph.add_edge(module_id, &target, edge)?;
             }
         }
-        pb.inc(1);
+    }
+
+    if options.symbols {
+        add_symbols(&mut graph, &analyzed_files, &reexports)?;
     }
 
     pb.finish_with_message("Analysis complete");
 
     // Add containment relationships based on module hierarchy
     add_containment_relationships(&mut graph)?;
+    mark_namespace_packages(&mut graph, &known_modules);
+    add_locked_packages(&mut graph, dir_path)?;
 
     Ok(graph)
 }
 
+/// Returns the module names of the Python files of a directory (notebooks are not modules).
+pub fn known_modules(dir_path: &Path, python_files: &[PathBuf]) -> HashSet<String> {
+    python_files
+        .iter()
+        .filter(|file_path| !is_notebook_file(file_path))
+        .filter_map(|file_path| crate::pyproject::compute_module_name(file_path, dir_path).ok())
+        .collect()
+}
+
+/// Replaces the imports of a changed file in a graph built from the same set of files,
+/// without parsing the other files.
+///
+/// Returns false when the change cannot be patched in place and the graph must be rebuilt:
+/// the file no longer parses, is not in the graph yet, imports a module the graph does
+/// not have (which would need containment edges), or was the last importer of a module
+/// without a file (like a third-party module). The graph may already be modified in the
+/// last case. Graphs built with re-export tracing or symbols, and files with a stub, must
+/// always be rebuilt.
+pub fn update_file_imports(
+    graph: &mut DependencyGraph,
+    dir_path: &Path,
+    file_path: &Path,
+    known_modules: &HashSet<String>,
+) -> Result<bool> {
+    let parsed = if is_notebook_file(file_path) {
+        analyze_notebook_file(file_path, dir_path, known_modules)
+    } else {
+        analyze_python_file_with_exports(file_path, dir_path, known_modules)
+    };
+    let Ok((module_id, analysis)) = parsed else {
+        return Ok(false);
+    };
+    if !graph.contains_module(&module_id) {
+        return Ok(false);
+    }
+    let targets: Vec<(&ModuleImport, Vec<ModuleIdentifier>)> = analysis
+        .imports
+        .iter()
+        .map(|import| (import, import_targets(import, &module_id, known_modules)))
+        .collect();
+    if targets
+        .iter()
+        .flat_map(|(_, targets)| targets)
+        .any(|target| !graph.contains_module(target))
+    {
+        return Ok(false);
+    }
+
+    let relative_path = file_path.strip_prefix(dir_path).unwrap_or(file_path);
+    let location_path = relative_path.to_string_lossy().replace('\\', "/");
+    let previous = graph.remove_imports(&module_id)?;
+    graph.remove_diagnostics(&location_path);
+    for unresolved in &analysis.unresolved_imports {
+        graph.add_diagnostic(unresolved_import_diagnostic(unresolved, &location_path));
+    }
+    for syntax_error in &analysis.syntax_errors {
+        graph.add_diagnostic(syntax_error_diagnostic(syntax_error, &location_path));
+    }
+    for (import, targets) in targets {
+        let edge = DependencyEdge::import(import, &location_path);
+        for target in targets {
+            graph.add_edge(&module_id, &target, edge.clone())?;
+        }
+    }
+
+    // Modules without a file that are no longer imported leave the graph on a rebuild
+    for module in previous {
+        if !known_modules.contains(&module.canonical_path) && !graph.is_imported(&module)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Adds the third-party packages pinned by the project lockfile, with `Requires` edges
+/// between them. A lockfile that cannot be read is reported as a diagnostic.
+fn add_locked_packages(graph: &mut DependencyGraph, dir_path: &Path) -> Result<()> {
+    let Some(file_name) = find_lockfile(dir_path) else {
+        return Ok(());
+    };
+    let lockfile = match Lockfile::load(dir_path, file_name) {
+        Ok(lockfile) => lockfile,
+        Err(e) => {
+            graph.add_diagnostic(file_diagnostic(&e, file_name));
+            return Ok(());
+        }
+    };
+
+    for package in &lockfile.packages {
+        let version = Some(package.version.as_str()).filter(|version| !version.is_empty());
+        graph.add_package(&package.name, version);
+    }
+    for package in &lockfile.packages {
+        for dependency in &package.dependencies {
+            // Requirements of a platform or Python version the lockfile does not cover
+            // may not be locked themselves
+            graph.add_package(dependency, None);
+            graph.add_package_requirement(&package.name, dependency)?;
+        }
+    }
+    Ok(())
+}
+
+/// Analysis of one file, before it is merged into the graph.
+type ParsedFile = Result<(ModuleIdentifier, ModuleAnalysis)>;
+
+/// Converts the error of a file that could not be analyzed into a diagnostic.
+fn file_diagnostic(error: &anyhow::Error, location_path: &str) -> Diagnostic {
+    if let Some(syntax_error) = error.downcast_ref::<SyntaxError>() {
+        syntax_error_diagnostic(syntax_error, location_path)
+    } else if let Some(decode_error) = error.downcast_ref::<SourceDecodeError>() {
+        Diagnostic::file(
+            DiagnosticKind::NonUtf8Source,
+            location_path,
+            &decode_error.message,
+        )
+    } else if let Some(io_error) = error.downcast_ref::<std::io::Error>() {
+        Diagnostic::file(
+            DiagnosticKind::UnreadableFile,
+            location_path,
+            io_error.to_string(),
+        )
+    } else {
+        Diagnostic::file(DiagnosticKind::ParseError, location_path, error.to_string())
+    }
+}
+
+/// Converts a relative import that could not be resolved into a diagnostic at its position.
+fn unresolved_import_diagnostic(unresolved: &UnresolvedImport, location_path: &str) -> Diagnostic {
+    Diagnostic {
+        cell: unresolved.position.cell,
+        ..Diagnostic::at(
+            DiagnosticKind::UnresolvedRelativeImport,
+            location_path,
+            unresolved.position.line,
+            unresolved.position.column,
+            unresolved.to_string(),
+        )
+    }
+}
+
+/// Converts a file or notebook cell that could not be parsed into a diagnostic.
+fn syntax_error_diagnostic(syntax_error: &SyntaxError, location_path: &str) -> Diagnostic {
+    Diagnostic {
+        cell: syntax_error.position.cell,
+        ..Diagnostic::at(
+            DiagnosticKind::ParseError,
+            location_path,
+            syntax_error.position.line,
+            syntax_error.position.column,
+            &syntax_error.message,
+        )
+    }
+}
+
+/// Reads a source file, decoded according to its byte-order mark or coding declaration.
+fn read_source(file_path: &Path) -> Result<String> {
+    let bytes = fs::read(file_path)?;
+    decode_source(&bytes)
+}
+
+/// Merges `.pyi` stubs into the modules of their `.py` siblings.
+///
+/// Stub imports never run, so they are type-only. Imports a stub shares with its `.py`
+/// sibling are dropped, and the sibling's exports take precedence. Stub-only modules
+/// keep the stub's exports.
+fn merge_stubs(
+    analyzed_files: &mut Vec<(String, ModuleIdentifier, Vec<ModuleImport>)>,
+    reexports: &mut ReexportIndex,
+    stubs: Vec<(String, ModuleIdentifier, Vec<ModuleImport>, ModuleExports)>,
+) {
+    for (location_path, module_id, imports, exports) in stubs {
+        let sibling_imports: HashSet<&str> = analyzed_files
+            .iter()
+            .filter(|(_, sibling_id, _)| *sibling_id == module_id)
+            .flat_map(|(_, _, imports)| imports.iter())
+            .map(|import| import.module.canonical_path.as_str())
+            .collect();
+        let imports: Vec<ModuleImport> = imports
+            .into_iter()
+            .filter(|import| !sibling_imports.contains(import.module.canonical_path.as_str()))
+            .map(|import| ModuleImport {
+                kind: ImportKind::TypeOnly,
+                ..import
+            })
+            .collect();
+
+        reexports
+            .exports
+            .entry(module_id.canonical_path.clone())
+            .or_insert(exports);
+        analyzed_files.push((location_path, module_id, imports));
+    }
+}
+
+/// Returns true if the file is a `.pyi` type stub.
+pub(crate) fn is_stub_file(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "pyi")
+}
+
+/// Returns true if the file is a Jupyter notebook.
+fn is_notebook_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "ipynb")
+}
+
+/// Returns true if the file is a Python source file, type stub or Jupyter notebook.
+pub(crate) fn is_python_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "py" || extension == "pyi" || extension == "ipynb")
+}
+
+/// Re-exported names of every analyzed module, used to trace imports through package facades.
+#[derive(Debug, Default)]
+struct ReexportIndex {
+    /// Exports of each analyzed module
+    exports: HashMap<String, ModuleExports>,
+    /// Modules defined by an `__init__.py`
+    packages: HashSet<String>,
+}
+
+impl ReexportIndex {
+    /// Traces the names of an import to the modules defining them.
+    ///
+    /// Returns the defining modules, and whether the imported module itself is still a
+    /// target (some names are defined there, or could not be traced).
+    fn route(&self, import: &ModuleImport) -> (Vec<ModuleIdentifier>, bool) {
+        let package = &import.module.canonical_path;
+        if import.module.origin != ModuleOrigin::Internal
+            || !self.packages.contains(package)
+            || import.names.is_empty()
+        {
+            return (Vec::new(), true);
+        }
+
+        let mut defining_modules = BTreeSet::new();
+        let mut untraced = false;
+        for name in &import.names {
+            let names = if name == "*" {
+                match self.exported_names(package) {
+                    Some(names) => names,
+                    None => {
+                        untraced = true;
+                        continue;
+                    }
+                }
+            } else {
+                vec![name.as_str()]
+            };
+            for name in names {
+                match self.trace(package, name) {
+                    Some(module) => {
+                        defining_modules.insert(module);
+                    }
+                    None => untraced = true,
+                }
+            }
+        }
+
+        let defining_modules = defining_modules
+            .into_iter()
+            .map(|canonical_path| ModuleIdentifier {
+                origin: ModuleOrigin::Internal,
+                canonical_path,
+            })
+            .collect();
+        (defining_modules, untraced)
+    }
+
+    /// Follows re-exports of `name` from `package` down to the module defining it.
+    ///
+    /// Returns `None` if the name is defined by the package itself, or re-exported
+    /// from a module that was not analyzed.
+    fn trace(&self, package: &str, name: &str) -> Option<String> {
+        self.resolve(package, name)
+            .map(|(module, _name)| module)
+            .filter(|module| module != package)
+    }
+
+    /// Follows re-exports of `name` from `module` through packages, down to the module
+    /// and original name it comes from.
+    ///
+    /// Returns `None` for re-export cycles and names coming from modules that were not analyzed.
+    fn resolve(&self, module: &str, name: &str) -> Option<(String, String)> {
+        let mut module = module.to_string();
+        let mut name = name.to_string();
+        let mut visited = HashSet::new();
+
+        while self.packages.contains(&module) {
+            if !visited.insert((module.clone(), name.clone())) {
+                return None; // Re-export cycle
+            }
+            match self.source_of(&module, &name) {
+                Some((source, original)) => {
+                    module = source;
+                    name = original;
+                }
+                None => break,
+            }
+        }
+
+        self.exports.contains_key(&module).then_some((module, name))
+    }
+
+    /// Returns the module and original name a module re-exports `name` from.
+    fn source_of(&self, module: &str, name: &str) -> Option<(String, String)> {
+        let exports = self.exports.get(module)?;
+        if let Some(source) = exports.reexports.get(name) {
+            return Some(source.clone());
+        }
+        exports
+            .star_sources
+            .iter()
+            .find(|source| {
+                self.exported_names(source)
+                    .is_some_and(|names| names.contains(&name))
+            })
+            .map(|source| (source.clone(), name.to_string()))
+    }
+
+    /// Returns the names `from module import *` binds: `__all__`, or the public names
+    /// the module defines or imports.
+    fn exported_names(&self, module: &str) -> Option<Vec<&str>> {
+        let exports = self.exports.get(module)?;
+        match &exports.all {
+            Some(all) => Some(all.iter().map(String::as_str).collect()),
+            None => Some(
+                exports
+                    .symbols
+                    .iter()
+                    .map(|symbol| symbol.name.as_str())
+                    .chain(exports.reexports.keys().map(String::as_str))
+                    .filter(|name| !name.starts_with('_'))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Adds symbol nodes for the top-level definitions of every analyzed module, and edges
+/// from importing modules to the symbols they import, traced through re-exports.
+fn add_symbols(
+    graph: &mut DependencyGraph,
+    analyzed_files: &[(String, ModuleIdentifier, Vec<ModuleImport>)],
+    reexports: &ReexportIndex,
+) -> Result<()> {
+    for (module, exports) in &reexports.exports {
+        let module_id = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: module.clone(),
+        };
+        for symbol in &exports.symbols {
+            graph.add_symbol(&module_id, &symbol.name, symbol.kind)?;
+        }
+    }
+
+    for (location_path, module_id, imports) in analyzed_files {
+        for import in imports {
+            if import.module.origin != ModuleOrigin::Internal {
+                continue;
+            }
+            let module = &import.module.canonical_path;
+            let mut symbol_paths = BTreeSet::new();
+            for name in &import.names {
+                let names = if name == "*" {
+                    reexports.exported_names(module).unwrap_or_default()
+                } else {
+                    vec![name.as_str()]
+                };
+                for name in names {
+                    if let Some((defining_module, original)) = reexports.resolve(module, name) {
+                        symbol_paths.insert(format!("{}.{}", defining_module, original));
+                    }
+                }
+            }
+
+            for symbol_path in symbol_paths {
+                if graph.get_symbol_kind(&symbol_path).is_some() {
+                    let edge = DependencyEdge::import(import, location_path);
+                    graph.add_symbol_import(module_id, &symbol_path, edge)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the modules reached by an import.
+///
+/// A dynamic import into an internal package (`import_module(f"myapp.plugins.{name}")`)
+/// may load any of its submodules, so all known ones are targeted along with the package.
+fn import_targets(
+    import: &ModuleImport,
+    importer: &ModuleIdentifier,
+    known_modules: &HashSet<String>,
+) -> Vec<ModuleIdentifier> {
+    let mut targets = vec![import.module.clone()];
+    if import.kind == ImportKind::DynamicPackage && import.module.origin == ModuleOrigin::Internal {
+        let prefix = format!("{}.", import.module.canonical_path);
+        let mut submodules: Vec<&String> = known_modules
+            .iter()
+            .filter(|module| module.starts_with(&prefix) && **module != importer.canonical_path)
+            .collect();
+        submodules.sort();
+        targets.extend(submodules.into_iter().map(|module| ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: module.clone(),
+        }));
+    }
+    targets
+}
+
 /// Discovers all Python files in a directory (non-recursive).
 pub fn analyze_python_directory(dir_path: &Path) -> Result<Vec<std::path::PathBuf>> {
     if !dir_path.is_dir() {
@@ -73,12 +659,8 @@ pub fn analyze_python_directory(dir_path: &Path) -> Result<Vec<std::path::PathBu
     for entry in fs::read_dir(dir_path)? {
         let entry = entry?;
         let path = entry.path();
-        if path.is_file() {
-            if let Some(extension) = path.extension() {
-                if extension == "py" {
-                    python_files.push(path);
-                }
-            }
+        if path.is_file() && is_python_file(&path) {
+            python_files.push(path);
         }
     }
 
@@ -90,6 +672,15 @@ pub fn analyze_python_directory(dir_path: &Path) -> Result<Vec<std::path::PathBu
 
 /// Discovers all Python files in a directory and its subdirectories (recursive).
 pub fn analyze_python_directory_recursive(dir_path: &Path) -> Result<Vec<PathBuf>> {
+    analyze_python_directory_recursive_with_options(dir_path, &DiscoveryOptions::default())
+}
+
+/// Discovers the Python files in a directory and its subdirectories (recursive) that the
+/// given options select.
+pub fn analyze_python_directory_recursive_with_options(
+    dir_path: &Path,
+    options: &DiscoveryOptions,
+) -> Result<Vec<PathBuf>> {
     if !dir_path.is_dir() {
         return Err(anyhow::anyhow!(
             "Path '{}' is not a directory",
@@ -97,23 +688,38 @@ pub fn analyze_python_directory_recursive(dir_path: &Path) -> Result<Vec<PathBuf
         ));
     }
 
+    let include = compile_globs(&options.include)?;
+    let exclude = compile_globs(&options.exclude)?;
+    let root = dir_path.to_path_buf();
+    let exclude_tests = options.exclude_tests;
+
     let mut python_files = Vec::new();
 
-    let walker = WalkDir::new(dir_path)
+    let walker = WalkBuilder::new(dir_path)
         .follow_links(false)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| {
-            // Skip directories starting with dot or named 'tests'
-            if e.file_type().is_dir() {
-                if let Some(name) = e.file_name().to_str() {
-                    if name.starts_with('.') || name == "tests" {
-                        return false;
-                    }
-                }
+        .hidden(false)
+        .ignore(options.respect_ignore_files)
+        .git_ignore(options.respect_ignore_files)
+        .git_exclude(options.respect_ignore_files)
+        .git_global(false)
+        .require_git(false)
+        .filter_entry(move |entry| {
+            // The root is walked whatever its name
+            if entry.depth() == 0 {
+                return true;
+            }
+            let relative_path = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            if exclude.is_match(relative_path) {
+                return false;
             }
-            e.file_type().is_file()
-        });
+            !entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_dir())
+                || !is_skipped_directory(entry.path(), exclude_tests)
+        })
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_some_and(|file_type| file_type.is_file()));
 
     let pb = ProgressBar::new_spinner();
     pb.set_style(
@@ -125,11 +731,14 @@ pub fn analyze_python_directory_recursive(dir_path: &Path) -> Result<Vec<PathBuf
 
     for entry in walker {
         let path = entry.path();
-        if let Some(extension) = path.extension() {
-            if extension == "py" {
-                python_files.push(path.to_path_buf());
-                pb.set_message(format!("Found {} Python files", python_files.len()));
-            }
+        let relative_path = path.strip_prefix(dir_path).unwrap_or(path);
+        // The setup.py of the project declares its dependencies, it is not one of its modules
+        if relative_path == Path::new("setup.py") && crate::pyproject::is_setup_declaration(path) {
+            continue;
+        }
+        if is_python_file(path) && (options.include.is_empty() || include.is_match(relative_path)) {
+            python_files.push(path.to_path_buf());
+            pb.set_message(format!("Found {} Python files", python_files.len()));
         }
         pb.tick();
     }
@@ -142,6 +751,33 @@ pub fn analyze_python_directory_recursive(dir_path: &Path) -> Result<Vec<PathBuf
     Ok(python_files)
 }
 
+/// Returns true for the directories never analyzed: hidden ones (`.git`, `.venv`),
+/// `__pycache__`, `node_modules`, virtual environments and, optionally, `tests`.
+fn is_skipped_directory(path: &Path, exclude_tests: bool) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    name.starts_with('.')
+        || name == "__pycache__"
+        || name == "node_modules"
+        || (exclude_tests && name == "tests")
+        || path.join("pyvenv.cfg").is_file()
+}
+
+/// Compiles include or exclude globs, where `*` does not cross directories and `**` does.
+fn compile_globs(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Invalid glob '{}': {}", pattern, e))?,
+        );
+    }
+    Ok(builder.build()?)
+}
+
 /// Analyzes a single Python file and returns the module identifier and its dependencies.
 pub fn analyze_python_file(file_path: &Path) -> Result<(ModuleIdentifier, Vec<ModuleIdentifier>)> {
     let python_code = fs::read_to_string(file_path)?;
@@ -161,29 +797,139 @@ pub fn analyze_python_file(file_path: &Path) -> Result<(ModuleIdentifier, Vec<Mo
     Ok((module_id, dependencies))
 }
 
-/// Analyzes a single Python file with package context and returns module info and dependencies.
+/// Analyzes a single Python file with package context and returns module info and its imports.
+///
+/// `known_modules` holds the internal module names of the project: `from package import name`
+/// targets `package.name` when it is one of them.
 pub fn analyze_python_file_with_package(
     file_path: &Path,
     project_root: &Path,
-) -> Result<(ModuleIdentifier, Vec<ModuleIdentifier>)> {
-    let python_code = fs::read_to_string(file_path)?;
+    known_modules: &HashSet<String>,
+) -> Result<(ModuleIdentifier, Vec<ModuleImport>)> {
+    let (module_id, analysis) =
+        analyze_python_file_with_exports(file_path, project_root, known_modules)?;
+    Ok((module_id, analysis.imports))
+}
+
+/// Analyzes the code cells of a Jupyter notebook and returns the notebook node and its imports.
+///
+/// Notebooks are not importable, so they are identified by their path relative to the
+/// project root (`notebooks/churn.ipynb`) and their relative imports are reported as
+/// unresolved. Each cell is parsed on its own: cells that do not parse are reported in
+/// [`ModuleAnalysis::syntax_errors`] without losing the imports of the other cells.
+/// Positions are the cell and the line in it.
+pub fn analyze_notebook_file(
+    file_path: &Path,
+    project_root: &Path,
+    known_modules: &HashSet<String>,
+) -> Result<(ModuleIdentifier, ModuleAnalysis)> {
+    let (notebook_id, mut analysis) = parse_notebook_file(file_path, project_root)?;
+    analysis.resolve(Some(known_modules));
+    Ok((notebook_id, analysis))
+}
+
+/// Analyzes a notebook like [`analyze_notebook_file`], leaving its `from package import name`
+/// statements unresolved.
+fn parse_notebook_file(file_path: &Path, project_root: &Path) -> ParsedFile {
+    let notebook_json = read_source(file_path)?;
+    let mut analysis = ModuleAnalysis::default();
+    let mut imports = Vec::new();
+    for cell in extract_code_cells(&notebook_json)? {
+        let (cell_analysis, syntax_error) = parse_notebook_cell(&cell.code)?;
+        if let Some(mut syntax_error) = syntax_error {
+            syntax_error.position.cell = Some(cell.number);
+            analysis.syntax_errors.push(syntax_error);
+        }
+        for mut import in cell_analysis.imports {
+            import.position.cell = Some(cell.number);
+            imports.push(import);
+        }
+        for mut from_import in cell_analysis.from_imports {
+            from_import.position.cell = Some(cell.number);
+            analysis.from_imports.push(from_import);
+        }
+        for mut unresolved in cell_analysis.unresolved_imports {
+            unresolved.position.cell = Some(cell.number);
+            analysis.unresolved_imports.push(unresolved);
+        }
+    }
+    analysis.imports = merge_imports(imports);
+
+    let relative_path = file_path.strip_prefix(project_root).unwrap_or(file_path);
+    let notebook_id = ModuleIdentifier {
+        origin: ModuleOrigin::Notebook,
+        canonical_path: relative_path.to_string_lossy().replace('\\', "/"),
+    };
+    Ok((notebook_id, analysis))
+}
+
+/// Parses the code of a notebook cell, replacing the lines that fail to parse by `pass`
+/// so the imports of the rest of the cell are kept. Also returns the first syntax error
+/// of the cell; a cell that still fails to parse once no line is left to replace gives
+/// an empty analysis.
+fn parse_notebook_cell(code: &str) -> Result<(ModuleAnalysis, Option<SyntaxError>)> {
+    let mut lines: Vec<String> = code.lines().map(str::to_string).collect();
+    let mut first_error = None;
+    loop {
+        let source: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        let syntax_error = match parse_module_source(&source, None) {
+            Ok(analysis) => return Ok((analysis, first_error)),
+            Err(e) => e.downcast::<SyntaxError>()?,
+        };
+        let line = syntax_error.position.line.checked_sub(1);
+        first_error.get_or_insert(syntax_error);
+
+        match line.and_then(|line| lines.get_mut(line)) {
+            Some(line) if line.trim() != "pass" => {
+                let statement = line.trim_start();
+                *line = format!("{}pass", &line[..line.len() - statement.len()]);
+            }
+            _ => return Ok((ModuleAnalysis::default(), first_error)),
+        }
+    }
+}
+
+/// Analyzes a single Python file like [`analyze_python_file_with_package`], also returning
+/// the names the module re-exports and its unresolvable relative imports.
+fn analyze_python_file_with_exports(
+    file_path: &Path,
+    project_root: &Path,
+    known_modules: &HashSet<String>,
+) -> Result<(ModuleIdentifier, ModuleAnalysis)> {
+    let (module_id, mut analysis) = parse_python_file(file_path, project_root)?;
+    analysis.resolve(Some(known_modules));
+    Ok((module_id, analysis))
+}
+
+/// Analyzes a single Python file like [`analyze_python_file_with_exports`], leaving its
+/// `from package import name` statements unresolved.
+fn parse_python_file(file_path: &Path, project_root: &Path) -> ParsedFile {
+    let python_code = read_source(file_path)?;
 
     // Create module identifier with proper package path
     let module_name = crate::pyproject::compute_module_name(file_path, project_root)?;
 
-    // Extract dependencies with current module context for relative import resolution
-    let dependencies = extract_module_deps(&python_code, Some(&module_name))?;
+    // Extract imports with current module context for relative import resolution.
+    // Relative imports in a package `__init__.py` are relative to the package itself.
+    let context = if file_path.file_stem().is_some_and(|stem| stem == "__init__") {
+        format!("{}.__init__", module_name)
+    } else {
+        module_name.clone()
+    };
+    let analysis = parse_module_source(&python_code, Some(&context))?;
 
     let module_id = ModuleIdentifier {
         origin: ModuleOrigin::Internal,
         canonical_path: module_name,
     };
-    Ok((module_id, dependencies))
+    Ok((module_id, analysis))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::graph::DependencyType;
+    use crate::imports::{ImportScope, SymbolKind};
     use std::fs;
     use std::path::PathBuf;
     use tempfile::TempDir;
@@ -395,6 +1141,794 @@ import numpy as np
         assert_eq!(graph.dependency_count(), 4);
     }
 
+    #[test]
+    fn test_build_directory_dependency_graph_function_local_imports() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+        create_temp_python_file(
+            temp_dir.path(),
+            "app.py",
+            "import json\n\ndef run():\n    import helpers\n",
+        );
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let app = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "app".to_string(),
+        };
+
+        let edges = graph.get_dependency_edges(&app).unwrap();
+        assert_eq!(edges.len(), 2);
+        let scope_of = |name: &str| {
+            edges
+                .iter()
+                .find(|(target, _)| target == name)
+                .and_then(|(_, edge)| edge.scope)
+        };
+        assert_eq!(scope_of("json"), Some(ImportScope::Module));
+        assert_eq!(scope_of("helpers"), Some(ImportScope::Function));
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_type_only_imports() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+        create_temp_python_file(
+            temp_dir.path(),
+            "app.py",
+            "from typing import TYPE_CHECKING\n\nif TYPE_CHECKING:\n    import models\n",
+        );
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let app = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "app".to_string(),
+        };
+
+        let edges = graph.get_dependency_edges(&app).unwrap();
+        let models_edge = edges
+            .iter()
+            .find(|(target, _)| target == "models")
+            .map(|(_, edge)| edge.dependency_type.clone());
+        assert_eq!(models_edge, Some(DependencyType::TypeOnlyImports));
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_stub_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+
+        create_temp_python_file(
+            temp_dir.path(),
+            "app.py",
+            "import json
+import models
+",
+        );
+        create_temp_python_file(
+            temp_dir.path(),
+            "app.pyi",
+            "import models
+from schema import Order
+",
+        );
+        create_temp_python_file(
+            temp_dir.path(),
+            "typed.pyi",
+            "import models
+",
+        );
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let module = |name: &str| ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: name.to_string(),
+        };
+
+        // The stub merges into the module of its `.py` sibling
+        let app_nodes = graph
+            .all_modules()
+            .filter(|module| module.canonical_path == "app")
+            .count();
+        assert_eq!(app_nodes, 1);
+        let edges = graph.get_dependency_edges(&module("app")).unwrap();
+        let edges_to = |name: &str| {
+            edges
+                .iter()
+                .filter(|(target, _)| target == name)
+                .map(|(_, edge)| {
+                    (
+                        edge.dependency_type.clone(),
+                        edge.location.as_ref().unwrap().to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            edges_to("models"),
+            vec![(DependencyType::Imports, "app.py:2".to_string())]
+        );
+        assert_eq!(
+            edges_to("schema"),
+            vec![(DependencyType::TypeOnlyImports, "app.pyi:2".to_string())]
+        );
+
+        // Stub-only modules are indexed with type-only imports
+        let edges = graph.get_dependency_edges(&module("typed")).unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].1.dependency_type, DependencyType::TypeOnlyImports);
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_notebooks() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::create_dir_all(temp_dir.path().join("notebooks")).unwrap();
+        create_temp_python_file(temp_dir.path(), "utils.py", "import json\n");
+        create_temp_python_file(
+            &temp_dir.path().join("notebooks"),
+            "eda.ipynb",
+            r#"{"cells": [
+                {"cell_type": "code", "source": ["%matplotlib inline\n", "import utils\n"]},
+                {"cell_type": "code", "source": ["!pip install seaborn\n", "import seaborn as sns"]},
+                {"cell_type": "code", "source": ["import requests\n", "files = !ls\n", "import httpx\n"]},
+                {"cell_type": "code", "source": ["%%time\n", "import sklearn\n"]}
+            ], "metadata": {}, "nbformat": 4, "nbformat_minor": 5}"#,
+        );
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let notebook = ModuleIdentifier {
+            origin: ModuleOrigin::Notebook,
+            canonical_path: "notebooks/eda.ipynb".to_string(),
+        };
+        assert!(graph.is_notebook("notebooks/eda.ipynb"));
+
+        // A line that does not parse is skipped, keeping the other imports of its cell
+        let edges = graph.get_dependency_edges(&notebook).unwrap();
+        let mut locations: Vec<(String, String)> = edges
+            .iter()
+            .map(|(target, edge)| (target.clone(), edge.location.as_ref().unwrap().to_string()))
+            .collect();
+        locations.sort();
+        assert_eq!(
+            locations,
+            vec![
+                (
+                    "httpx".to_string(),
+                    "notebooks/eda.ipynb:cell 3:3".to_string()
+                ),
+                (
+                    "requests".to_string(),
+                    "notebooks/eda.ipynb:cell 3:1".to_string()
+                ),
+                (
+                    "seaborn".to_string(),
+                    "notebooks/eda.ipynb:cell 2:2".to_string()
+                ),
+                (
+                    "sklearn".to_string(),
+                    "notebooks/eda.ipynb:cell 4:2".to_string()
+                ),
+                (
+                    "utils".to_string(),
+                    "notebooks/eda.ipynb:cell 1:2".to_string()
+                ),
+            ]
+        );
+        let diagnostics: Vec<String> = graph.diagnostics().iter().map(|d| d.to_string()).collect();
+        assert_eq!(diagnostics.len(), 1);
+        assert!(
+            diagnostics[0].starts_with("notebooks/eda.ipynb:cell 3:2:"),
+            "{}",
+            diagnostics[0]
+        );
+
+        // Notebooks are not modules of the package hierarchy
+        assert!(
+            !graph
+                .all_modules()
+                .any(|module| module.canonical_path == "notebooks/eda")
+        );
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_setup_py() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::create_dir_all(temp_dir.path().join("pkg")).unwrap();
+        create_temp_python_file(
+            &temp_dir.path().join("pkg"),
+            "__init__.py",
+            "import requests\n",
+        );
+        create_temp_python_file(
+            temp_dir.path(),
+            "setup.py",
+            "from setuptools import setup\nsetup(name=\"demo\", install_requires=[\"requests\"])\n",
+        );
+
+        // A setup.py read as a dependency declaration is not a module of the project
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let modules: Vec<&str> = graph
+            .all_modules()
+            .map(|module| module.canonical_path.as_str())
+            .collect();
+        assert!(modules.contains(&"pkg"));
+        assert!(!modules.contains(&"setup"));
+        assert!(!modules.contains(&"setuptools"));
+
+        // A script that happens to be named setup.py is analyzed
+        create_temp_python_file(temp_dir.path(), "setup.py", "import setuptools\n");
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        assert!(
+            graph
+                .all_modules()
+                .any(|module| module.canonical_path == "setup")
+        );
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_import_locations() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::create_dir_all(temp_dir.path().join("pkg")).unwrap();
+        create_temp_python_file(
+            &temp_dir.path().join("pkg"),
+            "app.py",
+            "import json\n\n\nfrom os import path\n",
+        );
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let app = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "pkg.app".to_string(),
+        };
+
+        let edges = graph.get_dependency_edges(&app).unwrap();
+        let location_of = |name: &str| {
+            edges
+                .iter()
+                .find(|(target, _)| target == name)
+                .and_then(|(_, edge)| edge.location.as_ref())
+                .map(|location| location.to_string())
+        };
+        assert_eq!(location_of("json"), Some("pkg/app.py:1".to_string()));
+        assert_eq!(location_of("os"), Some("pkg/app.py:4".to_string()));
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_from_import_submodules() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::write(
+            temp_dir.path().join("pyproject.toml"),
+            "[tool.poetry]\npackages = [{ include = \"myapp\" }]\n",
+        )
+        .unwrap();
+        crate::pyproject::init_for_test(temp_dir.path());
+
+        let services = temp_dir.path().join("myapp/services");
+        fs::create_dir_all(&services).unwrap();
+        create_temp_python_file(&temp_dir.path().join("myapp"), "__init__.py", "");
+        create_temp_python_file(&services, "__init__.py", "DEFAULT_CURRENCY = 'EUR'\n");
+        create_temp_python_file(&services, "billing.py", "");
+        create_temp_python_file(&services, "shipping.py", "");
+        create_temp_python_file(
+            &temp_dir.path().join("myapp"),
+            "api.py",
+            "from myapp.services import billing, DEFAULT_CURRENCY\nfrom .services import shipping\n",
+        );
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let api = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "myapp.api".to_string(),
+        };
+
+        let mut targets: Vec<String> = graph
+            .get_dependency_edges(&api)
+            .unwrap()
+            .into_iter()
+            .filter(|(_, edge)| edge.dependency_type == DependencyType::Imports)
+            .map(|(target, _)| target)
+            .collect();
+        targets.sort();
+
+        // Submodules are targeted directly, the constant keeps targeting the package
+        assert_eq!(
+            targets,
+            vec![
+                "myapp.services".to_string(),
+                "myapp.services.billing".to_string(),
+                "myapp.services.shipping".to_string(),
+            ]
+        );
+
+        crate::pyproject::reset_for_test();
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_namespace_packages() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        crate::pyproject::init_for_test(temp_dir.path());
+
+        // Two portions of the `company` namespace in different source roots
+        let team_a = temp_dir.path().join("company/team_a");
+        let team_b = temp_dir.path().join("src/company/team_b");
+        fs::create_dir_all(&team_a).unwrap();
+        fs::create_dir_all(&team_b).unwrap();
+        create_temp_python_file(&team_a, "__init__.py", "");
+        create_temp_python_file(
+            &team_a,
+            "core.py",
+            "from company.team_b import api\nfrom google.cloud import storage\n",
+        );
+        create_temp_python_file(&team_b, "__init__.py", "");
+        create_temp_python_file(&team_b, "api.py", "");
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let company = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "company".to_string(),
+        };
+        let core = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "company.team_a.core".to_string(),
+        };
+
+        let mut contained: Vec<String> = graph
+            .get_dependencies_with_types(&company)
+            .unwrap()
+            .into_iter()
+            .filter(|(_, dep_type)| *dep_type == DependencyType::Contains)
+            .map(|(module, _)| module)
+            .collect();
+        contained.sort();
+        assert_eq!(contained, vec!["company.team_a", "company.team_b"]);
+
+        let mut imported = graph.get_dependencies(&core).unwrap();
+        imported.sort();
+        assert!(imported.contains(&"company.team_b.api".to_string()));
+        assert!(imported.contains(&"google.cloud.storage".to_string()));
+
+        assert!(graph.is_namespace_package("company"));
+        assert!(graph.is_namespace_package("google.cloud"));
+        assert!(!graph.is_namespace_package("company.team_a"));
+        assert!(!graph.is_namespace_package("google.cloud.storage"));
+
+        crate::pyproject::reset_for_test();
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_dynamic_imports() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::write(
+            temp_dir.path().join("pyproject.toml"),
+            "[tool.poetry]\npackages = [{ include = \"myapp\" }]\n",
+        )
+        .unwrap();
+        crate::pyproject::init_for_test(temp_dir.path());
+
+        let plugins = temp_dir.path().join("myapp/plugins");
+        fs::create_dir_all(&plugins).unwrap();
+        create_temp_python_file(&temp_dir.path().join("myapp"), "__init__.py", "");
+        create_temp_python_file(&plugins, "__init__.py", "");
+        create_temp_python_file(&plugins, "csv_export.py", "");
+        create_temp_python_file(&plugins, "pdf_export.py", "");
+        create_temp_python_file(
+            &temp_dir.path().join("myapp"),
+            "loader.py",
+            "import importlib\n\ndef load(name):\n    return importlib.import_module(f\"myapp.plugins.{name}\")\n",
+        );
+        create_temp_python_file(
+            &temp_dir.path().join("myapp"),
+            "cli.py",
+            "import importlib\nimportlib.import_module(\"myapp.loader\")\nimportlib.import_module(\"myapp.extensions.audit\")\n",
+        );
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        let module = |name: &str| ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: name.to_string(),
+        };
+
+        let loader_edges = graph.get_dependency_edges(&module("myapp.loader")).unwrap();
+        let mut plugin_targets: Vec<String> = loader_edges
+            .iter()
+            .filter(|(_, edge)| edge.dependency_type == DependencyType::DynamicImports)
+            .map(|(target, edge)| {
+                assert_eq!(edge.dynamic_package.as_deref(), Some("myapp.plugins"));
+                target.clone()
+            })
+            .collect();
+        plugin_targets.sort();
+
+        // A prefix-only import targets the package and every module under it
+        assert_eq!(
+            plugin_targets,
+            vec![
+                "myapp.plugins".to_string(),
+                "myapp.plugins.csv_export".to_string(),
+                "myapp.plugins.pdf_export".to_string(),
+            ]
+        );
+        assert!(
+            graph
+                .get_dependents(&module("myapp.plugins.pdf_export"))
+                .unwrap()
+                .contains(&"myapp.loader".to_string())
+        );
+
+        // A literal name targets that module only
+        let cli_edges = graph.get_dependency_edges(&module("myapp.cli")).unwrap();
+        let loader_edge = cli_edges
+            .iter()
+            .find(|(target, _)| target == "myapp.loader")
+            .map(|(_, edge)| edge)
+            .unwrap();
+        assert_eq!(loader_edge.dependency_type, DependencyType::DynamicImports);
+        assert_eq!(loader_edge.dynamic_package, None);
+
+        // The parent of a module that doesn't exist has no directory
+        assert!(graph.contains_module(&module("myapp.extensions")));
+        assert!(!graph.is_namespace_package("myapp.extensions"));
+        assert!(!graph.is_namespace_package("myapp.plugins"));
+
+        crate::pyproject::reset_for_test();
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_trace_reexports() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::write(
+            temp_dir.path().join("pyproject.toml"),
+            "[tool.poetry]\npackages = [{ include = \"myapp\" }]\n",
+        )
+        .unwrap();
+        crate::pyproject::init_for_test(temp_dir.path());
+
+        let app = temp_dir.path().join("myapp");
+        let models = app.join("models");
+        fs::create_dir_all(&models).unwrap();
+        create_temp_python_file(&app, "__init__.py", "from .models import *\n");
+        create_temp_python_file(
+            &models,
+            "__init__.py",
+            "from .user import User\nfrom .billing import *\n\nVERSION = 2\n",
+        );
+        create_temp_python_file(&models, "user.py", "class User: ...\n");
+        create_temp_python_file(
+            &models,
+            "billing.py",
+            "__all__ = [\"Invoice\"]\n\nclass Invoice: ...\n",
+        );
+        create_temp_python_file(
+            &app,
+            "api.py",
+            "from myapp.models import User, VERSION\nfrom myapp.models import Invoice\n",
+        );
+        create_temp_python_file(&app, "cli.py", "from myapp import User\n");
+
+        let module = |name: &str| ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: name.to_string(),
+        };
+        let import_targets = |graph: &DependencyGraph, name: &str| {
+            let mut targets: Vec<(String, Option<String>)> = graph
+                .get_dependency_edges(&module(name))
+                .unwrap()
+                .into_iter()
+                .filter(|(_, edge)| edge.dependency_type == DependencyType::Imports)
+                .map(|(target, edge)| (target, edge.via))
+                .collect();
+            targets.sort();
+            targets
+        };
+
+        // Without the option, consumers target the facade
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+        assert_eq!(
+            import_targets(&graph, "myapp.api"),
+            vec![("myapp.models".to_string(), None)]
+        );
+
+        let options = GraphOptions {
+            trace_reexports: true,
+            ..GraphOptions::default()
+        };
+        let graph =
+            build_directory_dependency_graph_with_options(temp_dir.path(), &options).unwrap();
+
+        // VERSION is defined by the facade itself, which keeps its edge
+        assert_eq!(
+            import_targets(&graph, "myapp.api"),
+            vec![
+                ("myapp.models".to_string(), None),
+                (
+                    "myapp.models.billing".to_string(),
+                    Some("myapp.models".to_string())
+                ),
+                (
+                    "myapp.models.user".to_string(),
+                    Some("myapp.models".to_string())
+                ),
+            ]
+        );
+        // Re-exports are followed through nested facades and star imports
+        assert_eq!(
+            import_targets(&graph, "myapp.cli"),
+            vec![("myapp.models.user".to_string(), Some("myapp".to_string()))]
+        );
+
+        crate::pyproject::reset_for_test();
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_symbols() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::write(
+            temp_dir.path().join("pyproject.toml"),
+            "[tool.poetry]\npackages = [{ include = \"myapp\" }]\n",
+        )
+        .unwrap();
+        crate::pyproject::init_for_test(temp_dir.path());
+
+        let app = temp_dir.path().join("myapp");
+        let billing = app.join("billing");
+        fs::create_dir_all(&billing).unwrap();
+        create_temp_python_file(&app, "__init__.py", "");
+        create_temp_python_file(&billing, "__init__.py", "from .cards import charge_card\n");
+        create_temp_python_file(
+            &billing,
+            "cards.py",
+            "MAX_RETRIES = 3\n\ndef charge_card(card): ...\n\nclass Card: ...\n",
+        );
+        create_temp_python_file(
+            &app,
+            "api.py",
+            "from myapp.billing import charge_card\nfrom myapp.billing.cards import Card, MAX_RETRIES\n",
+        );
+
+        let options = GraphOptions {
+            symbols: true,
+            ..GraphOptions::default()
+        };
+        let graph =
+            build_directory_dependency_graph_with_options(temp_dir.path(), &options).unwrap();
+
+        assert_eq!(
+            graph.get_symbol_kind("myapp.billing.cards.charge_card"),
+            Some(SymbolKind::Function)
+        );
+        assert_eq!(
+            graph.get_symbol_kind("myapp.billing.cards.Card"),
+            Some(SymbolKind::Class)
+        );
+        assert_eq!(
+            graph.get_symbol_kind("myapp.billing.cards.MAX_RETRIES"),
+            Some(SymbolKind::Constant)
+        );
+
+        // Imports through the package facade reach the symbol that is re-exported
+        let mut users: Vec<String> = graph
+            .get_symbol_dependents("myapp.billing.cards.charge_card")
+            .unwrap()
+            .into_iter()
+            .map(|(module, _)| module)
+            .collect();
+        users.sort();
+        assert_eq!(
+            users,
+            vec!["myapp.api".to_string(), "myapp.billing".to_string()]
+        );
+        let users = graph
+            .get_symbol_dependents("myapp.billing.cards.MAX_RETRIES")
+            .unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(
+            users[0].1.location.as_ref().map(|l| l.to_string()),
+            Some("myapp/api.py:2".to_string())
+        );
+
+        // Module-level queries are unaffected by symbol nodes
+        let cards = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "myapp.billing.cards".to_string(),
+        };
+        let mut dependents = graph.get_dependents(&cards).unwrap();
+        dependents.sort();
+        dependents.dedup();
+        assert_eq!(
+            dependents,
+            vec!["myapp.api".to_string(), "myapp.billing".to_string()]
+        );
+
+        crate::pyproject::reset_for_test();
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_diagnostics() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::create_dir_all(temp_dir.path().join("pkg")).unwrap();
+        create_temp_python_file(temp_dir.path(), "good.py", "import json\n");
+        create_temp_python_file(temp_dir.path(), "broken.py", "import os\ndef f(:\n");
+        create_temp_python_file(
+            &temp_dir.path().join("pkg"),
+            "far.py",
+            "from ... import x\n",
+        );
+        fs::write(temp_dir.path().join("legacy.py"), b"# caf\xe9\nimport os\n").unwrap();
+        create_temp_python_file(temp_dir.path(), "script.py", "from . import helpers\n");
+        fs::write(
+            temp_dir.path().join("report.ipynb"),
+            r#"{"cells": [{"cell_type": "code", "source": ["import os\n", "from .helpers import load"]}]}"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("latin.py"),
+            b"# -*- coding: latin-1 -*-\n# caf\xe9\nimport csv\n",
+        )
+        .unwrap();
+
+        let graph = build_directory_dependency_graph(temp_dir.path()).unwrap();
+
+        // Files that fail are reported instead of silently skipped
+        let diagnostics: Vec<(DiagnosticKind, String, Option<usize>)> = graph
+            .diagnostics()
+            .iter()
+            .map(|d| (d.kind, d.path.clone(), d.line))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (DiagnosticKind::ParseError, "broken.py".to_string(), Some(2)),
+                (DiagnosticKind::NonUtf8Source, "legacy.py".to_string(), None),
+                (
+                    DiagnosticKind::UnresolvedRelativeImport,
+                    "pkg/far.py".to_string(),
+                    Some(1)
+                ),
+                (
+                    DiagnosticKind::UnresolvedRelativeImport,
+                    "report.ipynb".to_string(),
+                    Some(2)
+                ),
+                (
+                    DiagnosticKind::UnresolvedRelativeImport,
+                    "script.py".to_string(),
+                    Some(1)
+                ),
+            ]
+        );
+        let messages: Vec<&str> = graph
+            .diagnostics()
+            .iter()
+            .filter(|d| d.kind == DiagnosticKind::UnresolvedRelativeImport)
+            .map(|d| d.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "'...' climbs above the package root",
+                "'.helpers' is imported outside of a package",
+                "'.' is imported outside of a package",
+            ]
+        );
+        assert!(graph.all_modules().any(|m| m.canonical_path == "good"));
+        assert!(!graph.all_modules().any(|m| m.canonical_path == "broken"));
+
+        // Sources with a coding declaration are decoded before parsing
+        let latin = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "latin".to_string(),
+        };
+        assert_eq!(graph.get_dependencies(&latin).unwrap(), vec!["csv"]);
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_jobs() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        fs::write(
+            temp_dir.path().join("pyproject.toml"),
+            "[tool.poetry]\npackages = [{ include = \"myapp\", from = \"src\" }]\n",
+        )
+        .unwrap();
+        let package_dir = temp_dir.path().join("src/myapp");
+        fs::create_dir_all(&package_dir).unwrap();
+        create_temp_python_file(&package_dir, "__init__.py", "");
+        for i in 0..20 {
+            create_temp_python_file(
+                &package_dir,
+                &format!("module{}.py", i),
+                &format!("import os\nfrom myapp import module{}\n", (i + 1) % 20),
+            );
+        }
+        create_temp_python_file(&package_dir, "broken.py", "def f(:\n");
+        crate::pyproject::init_for_test(temp_dir.path());
+
+        let build = |jobs| {
+            let options = GraphOptions {
+                jobs: Some(jobs),
+                ..GraphOptions::default()
+            };
+            build_directory_dependency_graph_with_options(temp_dir.path(), &options).unwrap()
+        };
+        let sequential = build(1);
+        let parallel = build(4);
+
+        // Worker threads see the packages configured in pyproject.toml
+        let module0 = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "myapp.module0".to_string(),
+        };
+        let mut dependencies = parallel.get_dependencies(&module0).unwrap();
+        dependencies.sort();
+        assert_eq!(dependencies, vec!["myapp", "myapp.module1", "os"]);
+        assert_eq!(parallel.to_string(), sequential.to_string());
+        assert_eq!(
+            parallel.diagnostics().iter().collect::<Vec<_>>(),
+            sequential.diagnostics().iter().collect::<Vec<_>>()
+        );
+
+        crate::pyproject::reset_for_test();
+    }
+
+    #[test]
+    fn test_build_directory_dependency_graph_cache() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let file_path = create_temp_python_file(temp_dir.path(), "app.py", "import os\n");
+        let app = ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: "app".to_string(),
+        };
+        let build = |cache| {
+            let options = GraphOptions {
+                cache,
+                ..GraphOptions::default()
+            };
+            build_directory_dependency_graph_with_options(temp_dir.path(), &options).unwrap()
+        };
+
+        let graph = build(true);
+        assert_eq!(graph.get_dependencies(&app).unwrap(), vec!["os"]);
+        assert!(temp_dir.path().join(".pydep-cache").is_dir());
+
+        // Same size and modification time: the cached analysis is reused
+        let modified = fs::metadata(&file_path).unwrap().modified().unwrap();
+        fs::write(&file_path, "import re\n").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&file_path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(build(true).get_dependencies(&app).unwrap(), vec!["os"]);
+        assert_eq!(build(false).get_dependencies(&app).unwrap(), vec!["re"]);
+
+        fs::write(&file_path, "import json\n").unwrap();
+        assert_eq!(build(true).get_dependencies(&app).unwrap(), vec!["json"]);
+
+        // Adding a module keeps the other entries, whose imports resolve against it
+        let package_dir = temp_dir.path().join("pkg");
+        fs::create_dir_all(&package_dir).unwrap();
+        create_temp_python_file(&package_dir, "__init__.py", "");
+        fs::write(&file_path, "from pkg import sub\n").unwrap();
+        assert_eq!(build(true).get_dependencies(&app).unwrap(), vec!["pkg"]);
+        let modified = fs::metadata(&file_path).unwrap().modified().unwrap();
+        fs::write(&file_path, "from pkg import bus\n").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&file_path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        create_temp_python_file(&package_dir, "sub.py", "");
+        assert_eq!(build(true).get_dependencies(&app).unwrap(), vec!["pkg.sub"]);
+        assert!(build(true).diagnostics().is_empty());
+
+        // A cache that cannot be written is reported with the graph
+        fs::remove_dir_all(temp_dir.path().join(".pydep-cache")).unwrap();
+        fs::write(temp_dir.path().join(".pydep-cache"), "").unwrap();
+        let graph = build(true);
+        let kinds: Vec<DiagnosticKind> = graph.diagnostics().iter().map(|d| d.kind).collect();
+        assert_eq!(kinds, vec![DiagnosticKind::CacheWriteError]);
+    }
+
     #[test]
     fn test_build_directory_dependency_graph_nonexistent_directory() {
         let nonexistent_path = Path::new("/nonexistent/directory");
@@ -439,6 +1973,87 @@ import numpy as np
         assert!(filenames.contains(&PathBuf::from("package").join("subpackage").join("deep.py").to_string_lossy().to_string()));
     }
 
+    #[test]
+    fn test_analyze_python_directory_recursive_with_options() {
+        let temp_dir = TempDir::new().expect("Failed to create temp directory");
+        let dir_path = temp_dir.path();
+        for directory in [
+            "tests",
+            ".venv/lib",
+            "venv/lib",
+            "node_modules/pkg",
+            "build/lib",
+            "generated",
+        ] {
+            fs::create_dir_all(dir_path.join(directory)).unwrap();
+        }
+        create_temp_python_file(dir_path, "main.py", "import os");
+        create_temp_python_file(&dir_path.join("tests"), "test_main.py", "import main");
+        create_temp_python_file(&dir_path.join(".venv/lib"), "six.py", "");
+        fs::write(dir_path.join("venv/pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        create_temp_python_file(&dir_path.join("venv/lib"), "six.py", "");
+        create_temp_python_file(&dir_path.join("node_modules/pkg"), "gyp.py", "");
+        create_temp_python_file(&dir_path.join("build/lib"), "main.py", "import os");
+        create_temp_python_file(&dir_path.join("generated"), "api_pb2.py", "");
+        fs::write(dir_path.join(".gitignore"), "build/\n").unwrap();
+
+        let discover = |options: DiscoveryOptions| -> Vec<String> {
+            analyze_python_directory_recursive_with_options(dir_path, &options)
+                .unwrap()
+                .iter()
+                .map(|f| {
+                    f.strip_prefix(dir_path)
+                        .unwrap()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            discover(DiscoveryOptions::default()),
+            vec!["generated/api_pb2.py", "main.py"]
+        );
+        assert_eq!(
+            discover(DiscoveryOptions {
+                exclude: vec!["generated".to_string()],
+                ..DiscoveryOptions::default()
+            }),
+            vec!["main.py"]
+        );
+        assert_eq!(
+            discover(DiscoveryOptions {
+                exclude_tests: false,
+                respect_ignore_files: false,
+                ..DiscoveryOptions::default()
+            }),
+            vec![
+                "build/lib/main.py",
+                "generated/api_pb2.py",
+                "main.py",
+                "tests/test_main.py"
+            ]
+        );
+        assert_eq!(
+            discover(DiscoveryOptions {
+                include: vec!["**/test_*.py".to_string(), "*.py".to_string()],
+                exclude_tests: false,
+                ..DiscoveryOptions::default()
+            }),
+            vec!["main.py", "tests/test_main.py"]
+        );
+        assert!(
+            analyze_python_directory_recursive_with_options(
+                dir_path,
+                &DiscoveryOptions {
+                    exclude: vec!["[".to_string()],
+                    ..DiscoveryOptions::default()
+                }
+            )
+            .is_err()
+        );
+    }
+
     #[test]
     fn test_analyze_python_file_with_package() {
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
@@ -452,17 +2067,17 @@ import numpy as np
             "import os\nimport sys",
         );
 
-        let result = analyze_python_file_with_package(&file_path, project_root);
+        let result = analyze_python_file_with_package(&file_path, project_root, &HashSet::new());
         assert!(result.is_ok());
 
-        let (module_id, dependencies) = result.unwrap();
+        let (module_id, imports) = result.unwrap();
         assert_eq!(module_id.canonical_path, "package.module");
         assert_eq!(module_id.origin, ModuleOrigin::Internal);
 
-        assert_eq!(dependencies.len(), 2);
-        let dep_names: Vec<&str> = dependencies
+        assert_eq!(imports.len(), 2);
+        let dep_names: Vec<&str> = imports
             .iter()
-            .map(|d| d.canonical_path.as_str())
+            .map(|i| i.module.canonical_path.as_str())
             .collect();
         assert!(dep_names.contains(&"os"));
         assert!(dep_names.contains(&"sys"));
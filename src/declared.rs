@@ -0,0 +1,620 @@
+use crate::pyproject::{PyProjectParser, SETUP_FILES, normalize_dependency_name};
+use crate::requirement::Requirement;
+use anyhow::Result;
+use globset::{GlobBuilder, GlobMatcher};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Source paths of test code when no mapping says otherwise.
+pub const DEFAULT_TEST_PATHS: [&str; 5] = [
+    "**/tests/**",
+    "**/test/**",
+    "**/test_*.py",
+    "**/*_test.py",
+    "**/conftest.py",
+];
+
+/// Dependency group a declared dependency belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub enum DependencyGroup {
+    /// Needed by the code at runtime
+    Runtime,
+    /// Development tooling (linters, docs, type stubs...)
+    Dev,
+    /// Test suite dependencies
+    Test,
+}
+
+impl DependencyGroup {
+    /// Classifies a group, extras or file name (`dev`, `requirements-test.txt`).
+    ///
+    /// Returns `None` when the name is about neither development nor tests.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        let words: Vec<&str> = name
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect();
+        let has_word = |candidates: &[&str]| words.iter().any(|word| candidates.contains(word));
+
+        if has_word(&["test", "tests", "testing"]) {
+            Some(Self::Test)
+        } else if has_word(&["dev", "develop", "development", "lint", "docs", "doc"]) {
+            Some(Self::Dev)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if dependencies of this group are installed for code of the given
+    /// group: runtime dependencies everywhere, dev and test ones outside production code.
+    pub fn is_available_to(self, code_group: DependencyGroup) -> bool {
+        self == Self::Runtime || code_group != Self::Runtime
+    }
+}
+
+impl FromStr for DependencyGroup {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "runtime" => Ok(Self::Runtime),
+            "dev" => Ok(Self::Dev),
+            "test" => Ok(Self::Test),
+            _ => anyhow::bail!(
+                "Unknown dependency group '{}' (expected runtime, dev or test)",
+                name
+            ),
+        }
+    }
+}
+
+/// "runtime", "dev" or "test"
+impl fmt::Display for DependencyGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Runtime => "runtime",
+            Self::Dev => "dev",
+            Self::Test => "test",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A dependency declared by a dependency source.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct DeclaredDependency {
+    /// Normalized distribution name (e.g. "scikit-learn")
+    pub name: String,
+    pub group: DependencyGroup,
+    /// File declaring the dependency, relative to the project root
+    pub source: String,
+}
+
+/// Maps source files to the dependency group of the code they contain.
+///
+/// Files matching no pattern are production code (runtime).
+#[derive(Debug, Clone)]
+pub struct SourceGroups {
+    patterns: Vec<(GlobMatcher, DependencyGroup)>,
+}
+
+impl SourceGroups {
+    /// Creates the mapping from `PATTERN=GROUP` entries (`scripts/**=dev`), relative to
+    /// the project root. They take precedence over the default test paths.
+    pub fn new(mappings: &[String]) -> Result<Self> {
+        let defaults = DEFAULT_TEST_PATHS
+            .iter()
+            .map(|pattern| (*pattern, DependencyGroup::Test));
+        let mut entries = Vec::new();
+        for mapping in mappings {
+            let (pattern, group) = mapping.rsplit_once('=').ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid group mapping '{}' (expected PATTERN=GROUP)",
+                    mapping
+                )
+            })?;
+            entries.push((pattern.trim(), group.trim().parse()?));
+        }
+
+        let mut patterns = Vec::new();
+        for (pattern, group) in entries.into_iter().chain(defaults) {
+            let matcher = GlobBuilder::new(pattern)
+                .literal_separator(true)
+                .build()?
+                .compile_matcher();
+            patterns.push((matcher, group));
+        }
+        Ok(Self { patterns })
+    }
+
+    /// Returns the group of the code in a file, given by its path relative to the project root.
+    pub fn group_of(&self, path: &str) -> DependencyGroup {
+        self.patterns
+            .iter()
+            .find(|(matcher, _)| matcher.is_match(path))
+            .map_or(DependencyGroup::Runtime, |(_, group)| *group)
+    }
+}
+
+impl Default for SourceGroups {
+    fn default() -> Self {
+        Self::new(&[]).expect("default test paths are valid globs")
+    }
+}
+
+/// A file format declaring the dependencies of a project.
+pub trait DependencySource {
+    /// Reads the dependencies declared by the source.
+    fn load(&self) -> Result<Vec<DeclaredDependency>>;
+}
+
+/// `[tool.poetry]` and PEP 621 dependencies of pyproject.toml.
+pub struct PyProjectSource {
+    project_root: PathBuf,
+}
+
+impl DependencySource for PyProjectSource {
+    fn load(&self) -> Result<Vec<DeclaredDependency>> {
+        let parser = PyProjectParser::new(&self.project_root);
+        Ok(parser
+            .get_grouped_dependencies()?
+            .into_iter()
+            .map(|(name, group)| DeclaredDependency {
+                name,
+                group,
+                source: "pyproject.toml".to_string(),
+            })
+            .collect())
+    }
+}
+
+/// A pip requirements file, following its `-r` includes.
+///
+/// The group comes from the file name: `requirements-dev.txt` and `requirements/test.txt`
+/// declare dev and test dependencies, other files runtime ones. Included files are
+/// grouped by their own name.
+pub struct RequirementsSource {
+    project_root: PathBuf,
+    /// Path of the file relative to the project root
+    path: String,
+}
+
+impl DependencySource for RequirementsSource {
+    fn load(&self) -> Result<Vec<DeclaredDependency>> {
+        let mut dependencies = Vec::new();
+        let mut visited = HashSet::new();
+        self.load_file(&self.path, &mut visited, &mut dependencies)?;
+        Ok(dependencies)
+    }
+}
+
+impl RequirementsSource {
+    fn load_file(
+        &self,
+        path: &str,
+        visited: &mut HashSet<String>,
+        dependencies: &mut Vec<DeclaredDependency>,
+    ) -> Result<()> {
+        if !visited.insert(path.to_string()) {
+            return Ok(());
+        }
+
+        let content = std::fs::read_to_string(self.project_root.join(path))?;
+        let stem = Path::new(path)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or(path);
+        let group = DependencyGroup::from_name(stem).unwrap_or(DependencyGroup::Runtime);
+
+        for line in requirement_lines(&content) {
+            if let Some(included) = option_value(&line, &["-r", "--requirement"]) {
+                self.load_file(&include_path(path, included), visited, dependencies)?;
+            } else if let Some(editable) = option_value(&line, &["-e", "--editable"]) {
+                if let Some(name) = egg_name(editable) {
+                    dependencies.push(DeclaredDependency {
+                        name,
+                        group,
+                        source: path.to_string(),
+                    });
+                }
+            } else if line.starts_with('-') {
+                // Constraints (`-c`) only pin versions of dependencies declared elsewhere;
+                // other options configure pip
+                continue;
+            } else if let Some(name) = requirement_name(&line) {
+                dependencies.push(DeclaredDependency {
+                    name,
+                    group,
+                    source: path.to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `[packages]` and `[dev-packages]` of a Pipfile, and custom package categories.
+pub struct PipfileSource {
+    project_root: PathBuf,
+}
+
+impl DependencySource for PipfileSource {
+    fn load(&self) -> Result<Vec<DeclaredDependency>> {
+        let content = std::fs::read_to_string(self.project_root.join("Pipfile"))?;
+        let toml: toml::Value = toml::from_str(&content)?;
+        let Some(tables) = toml.as_table() else {
+            return Ok(Vec::new());
+        };
+
+        let mut dependencies = Vec::new();
+        for (category, packages) in tables {
+            let group = match category.as_str() {
+                "packages" => DependencyGroup::Runtime,
+                "dev-packages" => DependencyGroup::Dev,
+                "source" | "requires" | "scripts" | "pipenv" => continue,
+                _ => DependencyGroup::from_name(category).unwrap_or(DependencyGroup::Dev),
+            };
+            for name in packages
+                .as_table()
+                .into_iter()
+                .flat_map(|table| table.keys())
+            {
+                dependencies.push(DeclaredDependency {
+                    name: normalize_dependency_name(name),
+                    group,
+                    source: "Pipfile".to_string(),
+                });
+            }
+        }
+        Ok(dependencies)
+    }
+}
+
+/// `install_requires` and `extras_require` of a legacy `setup.cfg` or `setup.py`.
+pub struct SetupSource {
+    project_root: PathBuf,
+    file_name: &'static str,
+}
+
+impl DependencySource for SetupSource {
+    fn load(&self) -> Result<Vec<DeclaredDependency>> {
+        let parser = PyProjectParser::new(&self.project_root);
+        Ok(parser
+            .get_setup_dependencies(self.file_name)?
+            .into_iter()
+            .map(|(name, group)| DeclaredDependency {
+                name,
+                group,
+                source: self.file_name.to_string(),
+            })
+            .collect())
+    }
+}
+
+/// Finds the dependency sources of a project: pyproject.toml, setup.cfg, setup.py,
+/// Pipfile, and the requirements files in the project root (`*requirements*.txt`) and in `requirements/`.
+pub fn discover_sources(project_root: &Path) -> Vec<Box<dyn DependencySource>> {
+    let mut sources: Vec<Box<dyn DependencySource>> = Vec::new();
+    if project_root.join("pyproject.toml").is_file() {
+        sources.push(Box::new(PyProjectSource {
+            project_root: project_root.to_path_buf(),
+        }));
+    }
+    for file_name in SETUP_FILES {
+        if project_root.join(file_name).is_file() {
+            sources.push(Box::new(SetupSource {
+                project_root: project_root.to_path_buf(),
+                file_name,
+            }));
+        }
+    }
+    if project_root.join("Pipfile").is_file() {
+        sources.push(Box::new(PipfileSource {
+            project_root: project_root.to_path_buf(),
+        }));
+    }
+
+    let mut requirements_files = Vec::new();
+    for directory in [".", "requirements"] {
+        let Ok(entries) = std::fs::read_dir(project_root.join(directory)) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let Some(file_name) = entry.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let in_requirements_dir = directory == "requirements";
+            if entry.path().is_file()
+                && file_name.ends_with(".txt")
+                && (in_requirements_dir || file_name.contains("requirements"))
+            {
+                requirements_files.push(if in_requirements_dir {
+                    format!("requirements/{}", file_name)
+                } else {
+                    file_name
+                });
+            }
+        }
+    }
+    requirements_files.sort();
+    for path in requirements_files {
+        sources.push(Box::new(RequirementsSource {
+            project_root: project_root.to_path_buf(),
+            path,
+        }));
+    }
+
+    sources
+}
+
+/// Reads the dependencies declared by all the dependency sources of a project.
+pub fn load_declared_dependencies(project_root: &Path) -> Result<Vec<DeclaredDependency>> {
+    let mut dependencies = Vec::new();
+    for source in discover_sources(project_root) {
+        dependencies.extend(source.load()?);
+    }
+    // Included requirements files are also sources of their own
+    dependencies.sort();
+    dependencies.dedup();
+    Ok(dependencies)
+}
+
+/// Splits a requirements file into logical lines, joining `\` continuations and
+/// dropping comments and blank lines.
+fn requirement_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in content.lines() {
+        // `#` starts a comment at the start of a line or after whitespace (not in `#egg=`)
+        let line = match line.find(" #").or_else(|| line.find("\t#")) {
+            Some(comment) => &line[..comment],
+            None if line.trim_start().starts_with('#') => "",
+            None => line,
+        };
+        match line.trim_end().strip_suffix('\\') {
+            Some(continued) => {
+                current.push_str(continued);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(line);
+                let logical = current.trim().to_string();
+                if !logical.is_empty() {
+                    lines.push(logical);
+                }
+                current.clear();
+            }
+        }
+    }
+    if !current.trim().is_empty() {
+        lines.push(current.trim().to_string());
+    }
+    lines
+}
+
+/// Resolves the path of a file included by a requirements file, relative to the
+/// including file (`requirements/test.txt` + `../base.txt` -> `base.txt`).
+fn include_path(including: &str, included: &str) -> String {
+    let mut parts: Vec<&str> = including.split('/').collect();
+    parts.pop();
+    for part in included.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Returns the value of a requirements file option (`-r file`, `--requirement=file`).
+fn option_value<'a>(line: &'a str, names: &[&str]) -> Option<&'a str> {
+    names.iter().find_map(|name| {
+        let rest = line.strip_prefix(name)?;
+        let value = if name.starts_with("--") {
+            rest.strip_prefix('=')
+                .or_else(|| rest.starts_with(char::is_whitespace).then_some(rest))?
+        } else {
+            rest
+        };
+        let value = value.trim();
+        (!value.is_empty()).then_some(value)
+    })
+}
+
+/// Extracts the distribution name of a requirement line, or `None` for bare URLs and
+/// local paths that do not name one.
+fn requirement_name(line: &str) -> Option<String> {
+    if let Some(name) = egg_name(line) {
+        return Some(name);
+    }
+    if line.starts_with(['.', '/']) {
+        return None;
+    }
+    if let Some((scheme, _)) = line.split_once("://")
+        && !scheme.contains('@')
+    {
+        return None;
+    }
+    Requirement::parse(line).map(|requirement| normalize_dependency_name(&requirement.name))
+}
+
+/// Reads the distribution name of a URL from its `#egg=` fragment.
+fn egg_name(url: &str) -> Option<String> {
+    let (_, egg) = url.split_once("#egg=")?;
+    let name = egg.split(['&', ' ', '[']).next().unwrap_or(egg);
+    (!name.is_empty()).then(|| normalize_dependency_name(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn declared(dependencies: &[DeclaredDependency]) -> Vec<(&str, DependencyGroup, &str)> {
+        dependencies
+            .iter()
+            .map(|dep| (dep.name.as_str(), dep.group, dep.source.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_dependency_group_from_name() {
+        assert_eq!(
+            DependencyGroup::from_name("dev"),
+            Some(DependencyGroup::Dev)
+        );
+        assert_eq!(
+            DependencyGroup::from_name("requirements-test"),
+            Some(DependencyGroup::Test)
+        );
+        assert_eq!(
+            DependencyGroup::from_name("dev_requirements"),
+            Some(DependencyGroup::Dev)
+        );
+        assert_eq!(DependencyGroup::from_name("requirements"), None);
+        assert_eq!(DependencyGroup::from_name("devtools"), None);
+    }
+
+    #[test]
+    fn test_source_groups() {
+        let groups = SourceGroups::default();
+        assert_eq!(groups.group_of("myapp/db.py"), DependencyGroup::Runtime);
+        assert_eq!(
+            groups.group_of("tests/unit/test_db.py"),
+            DependencyGroup::Test
+        );
+        assert_eq!(
+            groups.group_of("myapp/tests/helpers.py"),
+            DependencyGroup::Test
+        );
+        assert_eq!(groups.group_of("myapp/db_test.py"), DependencyGroup::Test);
+        assert_eq!(groups.group_of("conftest.py"), DependencyGroup::Test);
+        assert_eq!(
+            groups.group_of("myapp/contest.py"),
+            DependencyGroup::Runtime
+        );
+
+        // User mappings take precedence over the default test paths
+        let groups = SourceGroups::new(&[
+            "scripts/**=dev".to_string(),
+            "tests/e2e/**=Runtime".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(groups.group_of("scripts/seed.py"), DependencyGroup::Dev);
+        assert_eq!(
+            groups.group_of("tests/e2e/smoke.py"),
+            DependencyGroup::Runtime
+        );
+        assert_eq!(groups.group_of("tests/test_db.py"), DependencyGroup::Test);
+
+        assert!(SourceGroups::new(&["scripts/**".to_string()]).is_err());
+        assert!(SourceGroups::new(&["scripts/**=docs".to_string()]).is_err());
+
+        assert!(DependencyGroup::Runtime.is_available_to(DependencyGroup::Runtime));
+        assert!(DependencyGroup::Dev.is_available_to(DependencyGroup::Test));
+        assert!(!DependencyGroup::Test.is_available_to(DependencyGroup::Runtime));
+    }
+
+    #[test]
+    fn test_requirements_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("requirements.txt"),
+            "# Runtime\nrequests[socks]>=2.31  # HTTP\nPyYAML==6.0 \\\n    --hash=sha256:abc\n\
+             -c constraints.txt\n--index-url https://pypi.org/simple\n\
+             git+https://github.com/org/my_lib.git#egg=my_lib\n./vendor/local_pkg\n",
+        )
+        .unwrap();
+        fs::write(root.join("constraints.txt"), "urllib3<2\n").unwrap();
+        fs::write(
+            root.join("requirements-dev.txt"),
+            "-r requirements.txt\n-e .\nblack\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("requirements")).unwrap();
+        fs::write(
+            root.join("requirements/test.txt"),
+            "--requirement=../requirements.txt\npytest; python_version >= \"3.8\"\n",
+        )
+        .unwrap();
+
+        let dependencies = load_declared_dependencies(root).unwrap();
+        assert_eq!(
+            declared(&dependencies),
+            vec![
+                ("black", DependencyGroup::Dev, "requirements-dev.txt"),
+                ("my-lib", DependencyGroup::Runtime, "requirements.txt"),
+                ("pytest", DependencyGroup::Test, "requirements/test.txt"),
+                ("pyyaml", DependencyGroup::Runtime, "requirements.txt"),
+                ("requests", DependencyGroup::Runtime, "requirements.txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pipfile_and_pyproject_sources() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(
+            root.join("Pipfile"),
+            r#"
+[[source]]
+url = "https://pypi.org/simple"
+name = "pypi"
+
+[packages]
+Flask = "*"
+sqlalchemy = { version = ">=2.0", extras = ["asyncio"] }
+
+[dev-packages]
+mypy = "*"
+
+[tests]
+pytest-cov = "*"
+
+[requires]
+python_version = "3.12"
+"#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("pyproject.toml"),
+            r#"
+[project]
+dependencies = ["httpx>=0.27"]
+
+[project.optional-dependencies]
+fast = ["orjson"]
+test = ["hypothesis"]
+
+[tool.poetry.group.lint.dependencies]
+ruff = "*"
+"#,
+        )
+        .unwrap();
+
+        let dependencies = load_declared_dependencies(root).unwrap();
+        assert_eq!(
+            declared(&dependencies),
+            vec![
+                ("flask", DependencyGroup::Runtime, "Pipfile"),
+                ("httpx", DependencyGroup::Runtime, "pyproject.toml"),
+                ("hypothesis", DependencyGroup::Test, "pyproject.toml"),
+                ("mypy", DependencyGroup::Dev, "Pipfile"),
+                ("orjson", DependencyGroup::Runtime, "pyproject.toml"),
+                ("pytest-cov", DependencyGroup::Test, "Pipfile"),
+                ("ruff", DependencyGroup::Dev, "pyproject.toml"),
+                ("sqlalchemy", DependencyGroup::Runtime, "Pipfile"),
+            ]
+        );
+    }
+}
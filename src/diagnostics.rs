@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Kind of problem found while analyzing the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DiagnosticKind {
+    ParseError,               // Invalid Python source or notebook JSON, or unreadable lockfile
+    UnreadableFile,           // File that could not be read
+    NonUtf8Source,            // Source file that does not decode as UTF-8 or its declared encoding
+    UnresolvedRelativeImport, // Relative import that does not resolve to a module
+    CacheWriteError,          // Parse cache that could not be written (the graph is complete)
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            DiagnosticKind::ParseError => "parse error",
+            DiagnosticKind::UnreadableFile => "unreadable file",
+            DiagnosticKind::NonUtf8Source => "non-UTF-8 source",
+            DiagnosticKind::UnresolvedRelativeImport => "unresolved relative import",
+            DiagnosticKind::CacheWriteError => "cache write error",
+        };
+        f.write_str(label)
+    }
+}
+
+/// A file or import that could not be analyzed, so the graph may be missing dependencies.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// File path relative to the project root
+    pub path: String,
+    /// 1-based notebook cell the line and column are in (notebooks only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell: Option<usize>,
+    /// 1-based line of the problem, if it is known
+    pub line: Option<usize>,
+    /// 1-based column of the problem, if it is known
+    pub column: Option<usize>,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic about a whole file.
+    pub fn file(kind: DiagnosticKind, path: &str, message: impl Into<String>) -> Self {
+        Self {
+            path: path.to_string(),
+            cell: None,
+            line: None,
+            column: None,
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Creates a diagnostic about a position in a file.
+    pub fn at(
+        kind: DiagnosticKind,
+        path: &str,
+        line: usize,
+        column: usize,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            line: Some(line),
+            column: Some(column),
+            ..Self::file(kind, path, message)
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// "pkg/module.py:3:5: parse error: unexpected token", with the cell of notebooks
+    /// ("analysis.ipynb:cell 4:3:5: ...")
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(cell) = self.cell {
+            write!(f, ":cell {}", cell)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        write!(f, ": {}: {}", self.kind, self.message)
+    }
+}
+
+/// Problems found while building the dependency graph, sorted by file and position.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a diagnostic, keeping the collection sorted.
+    pub fn push(&mut self, diagnostic: Diagnostic) {
+        let index = self.entries.partition_point(|entry| *entry <= diagnostic);
+        self.entries.insert(index, diagnostic);
+    }
+
+    /// Removes the diagnostics of a file.
+    pub fn remove_file(&mut self, path: &str) {
+        self.entries.retain(|entry| entry.path != path);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.entries.iter()
+    }
+
+    /// Returns the number of diagnostics of the given kind.
+    pub fn count(&self, kind: DiagnosticKind) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.kind == kind)
+            .count()
+    }
+}
+
+/// Formats diagnostics for display
+pub mod formatters {
+    use super::Diagnostics;
+
+    /// Formats diagnostics as a summary section (empty when there are none)
+    pub fn format_text(diagnostics: &Diagnostics) -> String {
+        if diagnostics.is_empty() {
+            return String::new();
+        }
+
+        let mut output = String::from("\n=== Diagnostics ===\n");
+        output.push_str("(Files and imports that could not be analyzed)\n");
+        for diagnostic in diagnostics.iter() {
+            output.push_str(&format!("  {}\n", diagnostic));
+        }
+        output.push_str(&format!("Total: {} diagnostics\n", diagnostics.len()));
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostics() {
+        let mut diagnostics = Diagnostics::new();
+        assert_eq!(formatters::format_text(&diagnostics), "");
+
+        diagnostics.push(Diagnostic::at(
+            DiagnosticKind::ParseError,
+            "pkg/b.py",
+            3,
+            5,
+            "unexpected token ')'",
+        ));
+        diagnostics.push(Diagnostic::file(
+            DiagnosticKind::NonUtf8Source,
+            "pkg/a.py",
+            "invalid UTF-8 at byte 12",
+        ));
+        diagnostics.push(Diagnostic::at(
+            DiagnosticKind::UnresolvedRelativeImport,
+            "pkg/b.py",
+            1,
+            1,
+            "'...models' climbs above the package root",
+        ));
+
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics.count(DiagnosticKind::ParseError), 1);
+        let lines: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "pkg/a.py: non-UTF-8 source: invalid UTF-8 at byte 12",
+                "pkg/b.py:1:1: unresolved relative import: '...models' climbs above the package root",
+                "pkg/b.py:3:5: parse error: unexpected token ')'",
+            ]
+        );
+
+        let formatted = formatters::format_text(&diagnostics);
+        assert!(formatted.contains("=== Diagnostics ===\n"));
+        assert!(formatted.contains("  pkg/b.py:3:5: parse error: unexpected token ')'\n"));
+        assert!(formatted.contains("Total: 3 diagnostics\n"));
+
+        let json = serde_json::to_value(&diagnostics).unwrap();
+        assert_eq!(json[0]["kind"], "NonUtf8Source");
+        assert_eq!(json[0]["line"], serde_json::Value::Null);
+        assert_eq!(json[2]["line"], 3);
+    }
+}
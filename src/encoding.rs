@@ -0,0 +1,192 @@
+use anyhow::Result;
+use encoding_rs::{Encoding, UTF_8};
+
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Error returned when Python source bytes cannot be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDecodeError {
+    pub message: String,
+}
+
+impl std::fmt::Display for SourceDecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SourceDecodeError {}
+
+impl SourceDecodeError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+/// Decodes Python source bytes the way the interpreter does (PEP 263).
+///
+/// A UTF-8 byte-order mark is stripped and forces UTF-8. Otherwise a coding declaration
+/// (`# -*- coding: latin-1 -*-`) on the first or second line selects the encoding, and
+/// sources without one are UTF-8.
+pub fn decode_source(bytes: &[u8]) -> Result<String> {
+    if let Some(bytes) = bytes.strip_prefix(UTF8_BOM) {
+        if let Some(name) = coding_declaration(bytes)
+            && normalize_encoding_name(&name) != "utf-8"
+        {
+            return Err(SourceDecodeError::new(format!(
+                "encoding '{}' declared after a UTF-8 byte-order mark",
+                name
+            ))
+            .into());
+        }
+        return decode_with(UTF_8, bytes);
+    }
+
+    let encoding = match coding_declaration(bytes) {
+        Some(name) => {
+            Encoding::for_label(normalize_encoding_name(&name).as_bytes()).ok_or_else(|| {
+                SourceDecodeError::new(format!("unknown encoding '{}' in coding declaration", name))
+            })?
+        }
+        None => UTF_8,
+    };
+    decode_with(encoding, bytes)
+}
+
+/// Decodes bytes strictly, reporting the first malformed byte sequence.
+fn decode_with(encoding: &'static Encoding, bytes: &[u8]) -> Result<String> {
+    if encoding == UTF_8 {
+        return match std::str::from_utf8(bytes) {
+            Ok(source) => Ok(source.to_string()),
+            Err(e) => Err(SourceDecodeError::new(format!(
+                "invalid UTF-8 at byte {}",
+                e.valid_up_to()
+            ))
+            .into()),
+        };
+    }
+
+    encoding
+        .decode_without_bom_handling_and_without_replacement(bytes)
+        .map(|source| source.into_owned())
+        .ok_or_else(|| SourceDecodeError::new(format!("invalid {} source", encoding.name())).into())
+}
+
+/// Returns the encoding named by a coding declaration on the first two lines.
+///
+/// The second line only counts if the first one is blank or a comment.
+fn coding_declaration(bytes: &[u8]) -> Option<String> {
+    let mut lines = bytes.split(|&byte| byte == b'\n');
+    let first = lines.next()?;
+    if let Some(name) = coding_cookie(first) {
+        return Some(name);
+    }
+    let first = trim_start(first);
+    if !first.is_empty() && !first.starts_with(b"#") && first != b"\r" {
+        return None;
+    }
+    coding_cookie(lines.next()?)
+}
+
+/// Matches `^[ \t\f]*#.*?coding[:=][ \t]*([-\w.]+)` against a line.
+fn coding_cookie(line: &[u8]) -> Option<String> {
+    let comment = trim_start(line).strip_prefix(b"#")?;
+    let comment = std::str::from_utf8(comment).ok()?;
+
+    let mut rest = comment;
+    while let Some(index) = rest.find("coding") {
+        rest = &rest[index + "coding".len()..];
+        let Some(value) = rest.strip_prefix(':').or_else(|| rest.strip_prefix('=')) else {
+            continue;
+        };
+        let value = value.trim_start_matches([' ', '\t']);
+        let name: String = value
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            .collect();
+        if !name.is_empty() {
+            return Some(name);
+        }
+    }
+    None
+}
+
+fn trim_start(line: &[u8]) -> &[u8] {
+    let start = line
+        .iter()
+        .position(|byte| !matches!(byte, b' ' | b'\t' | b'\x0c'))
+        .unwrap_or(line.len());
+    &line[start..]
+}
+
+/// Normalizes an encoding name like the interpreter's tokenizer, so Python spellings
+/// (`latin-1`, `utf8`, `iso_latin_1`) map to labels known to `encoding_rs`.
+fn normalize_encoding_name(name: &str) -> String {
+    let name = name.to_lowercase().replace('_', "-");
+    if name == "utf-8" || name == "utf8" || name.starts_with("utf-8-") {
+        return "utf-8".to_string();
+    }
+    for latin1 in ["latin-1", "iso-8859-1", "iso-latin-1"] {
+        if name == latin1 || name.starts_with(&format!("{}-", latin1)) {
+            return "iso-8859-1".to_string();
+        }
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_source() {
+        // UTF-8 by default, with or without a byte-order mark
+        assert_eq!(decode_source(b"import os\n").unwrap(), "import os\n");
+        assert_eq!(
+            decode_source(b"\xef\xbb\xbfimport os\n").unwrap(),
+            "import os\n"
+        );
+
+        // Coding declarations on the first or second line
+        let latin1 = b"# -*- coding: latin-1 -*-\n# caf\xe9\nimport os\n";
+        assert_eq!(
+            decode_source(latin1).unwrap(),
+            "# -*- coding: latin-1 -*-\n# caf\u{e9}\nimport os\n"
+        );
+        let second_line = b"#!/usr/bin/env python\n# vim: set fileencoding=cp1252 :\nx = '\x80'\n";
+        assert!(
+            decode_source(second_line)
+                .unwrap()
+                .contains("x = '\u{20ac}'")
+        );
+
+        // A declaration after code does not count
+        let too_late = b"import os\n# coding: latin-1\n# caf\xe9\n";
+        let error = decode_source(too_late).unwrap_err();
+        assert_eq!(error.to_string(), "invalid UTF-8 at byte 33");
+        assert!(error.downcast_ref::<SourceDecodeError>().is_some());
+
+        assert!(
+            decode_source(b"# coding: klingon\n")
+                .unwrap_err()
+                .to_string()
+                .contains("unknown encoding 'klingon'")
+        );
+        assert!(decode_source(b"\xef\xbb\xbf# coding: latin-1\n").is_err());
+    }
+
+    #[test]
+    fn test_coding_cookie() {
+        assert_eq!(coding_cookie(b"# coding=utf8"), Some("utf8".to_string()));
+        assert_eq!(
+            coding_cookie(b"  # -*- coding: iso_latin_1 -*-"),
+            Some("iso_latin_1".to_string())
+        );
+        assert_eq!(coding_cookie(b"x = 1  # coding: latin-1"), None);
+        assert_eq!(coding_cookie(b"# no declaration here"), None);
+        assert_eq!(normalize_encoding_name("Latin_1"), "iso-8859-1");
+        assert_eq!(normalize_encoding_name("UTF-8-unix"), "utf-8");
+    }
+}
@@ -1,35 +1,146 @@
-use crate::imports::{ModuleIdentifier, ModuleOrigin};
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::imports::{
+    EXTERNAL_NAMESPACE_PACKAGES, ImportKind, ImportPosition, ImportScope, ModuleIdentifier,
+    ModuleImport, ModuleOrigin, SymbolKind,
+};
 use anyhow::Result;
-use petgraph::graph::NodeIndex;
+use petgraph::graph::EdgeReference;
+use petgraph::graph::{EdgeIndex, NodeIndex};
 use petgraph::visit::EdgeRef;
 use petgraph::{Directed, Graph};
+use serde::Serialize;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 
 /// Represents the type of dependency relationship between modules.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum DependencyType {
     /// X imports Y (import/from import statement)
     Imports,
+    /// X imports Y at runtime through `importlib.import_module` / `__import__`
+    DynamicImports,
+    /// X imports Y for type checking only (`if TYPE_CHECKING:` block or string annotation)
+    TypeOnlyImports,
     /// X is included in Y (e.g., function/class defined in module)
     IncludedIn,
     /// X contains Y (e.g., module contains function/class)
     Contains,
     /// X is the module
     Is,
+    /// Third-party package X requires package Y (from the project lockfile)
+    Requires,
+}
+
+/// Location of an import statement: the importing file and the position in it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct SourceLocation {
+    /// Path of the importing file, relative to the analyzed root
+    pub path: String,
+    /// 1-based notebook cell the line and column are in (notebooks only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell: Option<usize>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SourceLocation {
+    pub fn new(path: &str, position: ImportPosition) -> Self {
+        Self {
+            path: path.to_string(),
+            cell: position.cell,
+            line: position.line,
+            column: position.column,
+        }
+    }
+}
+
+/// "path/to/file.py:42", or "notebooks/churn.ipynb:cell 3:2" in notebooks
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if let Some(cell) = self.cell {
+            write!(f, ":cell {}", cell)?;
+        }
+        write!(f, ":{}", self.line)
+    }
+}
+
+/// Attributes carried by an edge of the dependency graph.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DependencyEdge {
+    /// The type of dependency relationship
+    pub dependency_type: DependencyType,
+    /// Scope of the import statement that created the edge (import edges only)
+    pub scope: Option<ImportScope>,
+    /// True if the import is guarded by `try/except ImportError`
+    pub optional: bool,
+    /// Where the import statement is (import edges only)
+    pub location: Option<SourceLocation>,
+    /// Package a dynamic import points into when only a prefix of the module name is known
+    pub dynamic_package: Option<String>,
+    /// Package facade the import was routed through by re-export tracing
+    pub via: Option<String>,
+}
+
+impl DependencyEdge {
+    /// Creates an edge without import metadata.
+    pub fn new(dependency_type: DependencyType) -> Self {
+        Self {
+            dependency_type,
+            scope: None,
+            optional: false,
+            location: None,
+            dynamic_package: None,
+            via: None,
+        }
+    }
+
+    /// Creates an import edge (`Imports`, `DynamicImports` or `TypeOnlyImports`) for an
+    /// import extracted from the file at `path`.
+    pub fn import(import: &ModuleImport, path: &str) -> Self {
+        let dependency_type = match import.kind {
+            ImportKind::Runtime => DependencyType::Imports,
+            ImportKind::Dynamic | ImportKind::DynamicPackage => DependencyType::DynamicImports,
+            ImportKind::TypeOnly => DependencyType::TypeOnlyImports,
+        };
+        let dynamic_package = (import.kind == ImportKind::DynamicPackage)
+            .then(|| import.module.canonical_path.clone());
+        Self {
+            dependency_type,
+            scope: Some(import.scope),
+            optional: import.optional,
+            location: Some(SourceLocation::new(path, import.position)),
+            dynamic_package,
+            via: None,
+        }
+    }
 }
 
 /// A directed graph representing dependencies between Python modules.
 ///
 /// Each node represents a module, and each edge represents a dependency
 /// relationship (import, containment, etc.) from one module to another.
+/// The graph can optionally hold symbol nodes (top-level functions, classes and
+/// constants), which module-level queries ignore.
 #[derive(Debug)]
 pub struct DependencyGraph {
     /// The underlying directed graph structure where each node contains a module path string
-    /// and each edge contains the type of dependency relationship
-    graph: Graph<String, DependencyType, Directed>,
+    /// and each edge contains the type of dependency relationship and its attributes
+    graph: Graph<String, DependencyEdge, Directed>,
     /// Fast lookup from module identifier to graph node index
     module_index: HashMap<ModuleIdentifier, NodeIndex>,
+    /// Fast lookup from symbol path (`myapp.billing.charge_card`) to graph node index
+    symbol_index: HashMap<String, NodeIndex>,
+    /// Kind of each symbol node
+    symbol_kinds: HashMap<NodeIndex, SymbolKind>,
+    /// Fast lookup from locked third-party package name to graph node index
+    package_index: HashMap<String, NodeIndex>,
+    /// Resolved version of each package node
+    package_versions: HashMap<NodeIndex, String>,
+    /// Paths of the PEP 420 namespace packages (no `__init__.py` of their own)
+    namespace_packages: HashSet<String>,
+    /// Files and imports that could not be analyzed while building the graph
+    diagnostics: Diagnostics,
 }
 
 impl DependencyGraph {
@@ -38,6 +149,12 @@ impl DependencyGraph {
         Self {
             graph: Graph::new(),
             module_index: HashMap::new(),
+            symbol_index: HashMap::new(),
+            symbol_kinds: HashMap::new(),
+            package_index: HashMap::new(),
+            package_versions: HashMap::new(),
+            namespace_packages: HashSet::new(),
+            diagnostics: Diagnostics::new(),
         }
     }
 
@@ -68,6 +185,19 @@ impl DependencyGraph {
         from_module: &ModuleIdentifier,
         to_module: &ModuleIdentifier,
         dependency_type: DependencyType,
+    ) -> Result<()> {
+        self.add_edge(from_module, to_module, DependencyEdge::new(dependency_type))
+    }
+
+    /// Adds a dependency edge with its full set of attributes between two modules.
+    ///
+    /// # Errors
+    /// Returns an error if either module is not found in the graph.
+    pub fn add_edge(
+        &mut self,
+        from_module: &ModuleIdentifier,
+        to_module: &ModuleIdentifier,
+        edge: DependencyEdge,
     ) -> Result<()> {
         let from_idx = self
             .module_index
@@ -78,10 +208,222 @@ impl DependencyGraph {
             .get(to_module)
             .ok_or_else(|| anyhow::anyhow!("Module '{}' not found", to_module.canonical_path))?;
 
-        self.graph.add_edge(*from_idx, *to_idx, dependency_type);
+        self.graph.add_edge(*from_idx, *to_idx, edge);
+        Ok(())
+    }
+
+    /// Adds a symbol defined by a module if not already known, linked to the module
+    /// by `Contains` / `IncludedIn` edges.
+    ///
+    /// Returns the node index of the symbol.
+    ///
+    /// # Errors
+    /// Returns an error if the module is not found in the graph.
+    pub fn add_symbol(
+        &mut self,
+        module_id: &ModuleIdentifier,
+        name: &str,
+        kind: SymbolKind,
+    ) -> Result<NodeIndex> {
+        let module_idx = self.get_node_index(module_id)?;
+        let symbol_path = format!("{}.{}", module_id.canonical_path, name);
+        if let Some(&existing_idx) = self.symbol_index.get(&symbol_path) {
+            return Ok(existing_idx);
+        }
+
+        let symbol_idx = self.graph.add_node(symbol_path.clone());
+        self.symbol_index.insert(symbol_path, symbol_idx);
+        self.symbol_kinds.insert(symbol_idx, kind);
+        self.graph.add_edge(
+            module_idx,
+            symbol_idx,
+            DependencyEdge::new(DependencyType::Contains),
+        );
+        self.graph.add_edge(
+            symbol_idx,
+            module_idx,
+            DependencyEdge::new(DependencyType::IncludedIn),
+        );
+        Ok(symbol_idx)
+    }
+
+    /// Adds an import edge from a module to a symbol it imports.
+    ///
+    /// # Errors
+    /// Returns an error if the module or the symbol is not found in the graph.
+    pub fn add_symbol_import(
+        &mut self,
+        from_module: &ModuleIdentifier,
+        symbol_path: &str,
+        edge: DependencyEdge,
+    ) -> Result<()> {
+        let from_idx = self.get_node_index(from_module)?;
+        let symbol_idx = self
+            .symbol_index
+            .get(symbol_path)
+            .ok_or_else(|| anyhow::anyhow!("Symbol '{}' not found", symbol_path))?;
+
+        self.graph.add_edge(from_idx, *symbol_idx, edge);
+        Ok(())
+    }
+
+    /// Returns the kind of a symbol, or `None` if the graph does not hold it.
+    pub fn get_symbol_kind(&self, symbol_path: &str) -> Option<SymbolKind> {
+        self.symbol_index
+            .get(symbol_path)
+            .and_then(|idx| self.symbol_kinds.get(idx))
+            .copied()
+    }
+
+    /// Gets all modules that import the specified symbol, with the import edges.
+    ///
+    /// # Errors
+    /// Returns an error if the symbol is not found in the graph.
+    pub fn get_symbol_dependents(
+        &self,
+        symbol_path: &str,
+    ) -> Result<Vec<(String, DependencyEdge)>> {
+        let symbol_idx = self
+            .symbol_index
+            .get(symbol_path)
+            .ok_or_else(|| anyhow::anyhow!("Symbol '{}' not found in graph", symbol_path))?;
+
+        Ok(self
+            .graph
+            .edges_directed(*symbol_idx, petgraph::Incoming)
+            .filter(|edge| edge.weight().dependency_type != DependencyType::Contains)
+            .filter_map(|edge| {
+                self.graph
+                    .node_weight(edge.source())
+                    .map(|module| (module.clone(), edge.weight().clone()))
+            })
+            .collect())
+    }
+
+    /// Returns an iterator over all symbol paths in the graph.
+    pub fn all_symbols(&self) -> impl Iterator<Item = &String> {
+        self.symbol_index.keys()
+    }
+
+    /// Adds a third-party package pinned by the lockfile if not already known.
+    ///
+    /// Package nodes are distributions (`scikit-learn`), apart from the module nodes
+    /// (`sklearn`), and are only linked to each other by `Requires` edges.
+    pub fn add_package(&mut self, name: &str, version: Option<&str>) -> NodeIndex {
+        if let Some(&existing_idx) = self.package_index.get(name) {
+            return existing_idx;
+        }
+        let package_idx = self.graph.add_node(name.to_string());
+        self.package_index.insert(name.to_string(), package_idx);
+        if let Some(version) = version {
+            self.package_versions
+                .insert(package_idx, version.to_string());
+        }
+        package_idx
+    }
+
+    /// Adds a `Requires` edge between two packages.
+    ///
+    /// # Errors
+    /// Returns an error if either package is not found in the graph.
+    pub fn add_package_requirement(&mut self, package: &str, requirement: &str) -> Result<()> {
+        let package_idx = self.get_package_index(package)?;
+        let requirement_idx = self.get_package_index(requirement)?;
+        self.graph.add_edge(
+            package_idx,
+            requirement_idx,
+            DependencyEdge::new(DependencyType::Requires),
+        );
         Ok(())
     }
 
+    fn get_package_index(&self, package: &str) -> Result<NodeIndex> {
+        self.package_index
+            .get(package)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Package '{}' not found in graph", package))
+    }
+
+    /// Returns the resolved version of a package, or `None` if the lockfile does not pin it.
+    pub fn get_package_version(&self, package: &str) -> Option<&str> {
+        self.package_index
+            .get(package)
+            .and_then(|idx| self.package_versions.get(idx))
+            .map(String::as_str)
+    }
+
+    /// Gets the packages directly required by a package.
+    ///
+    /// # Errors
+    /// Returns an error if the package is not found in the graph.
+    pub fn get_package_requirements(&self, package: &str) -> Result<Vec<String>> {
+        let package_idx = self.get_package_index(package)?;
+        Ok(self
+            .graph
+            .edges_directed(package_idx, petgraph::Outgoing)
+            .filter(|edge| edge.weight().dependency_type == DependencyType::Requires)
+            .filter_map(|edge| self.graph.node_weight(edge.target()).cloned())
+            .collect())
+    }
+
+    /// Returns the shortest chain of requirements from one package to another, both
+    /// included (`["pandas", "python-dateutil", "six"]`), or `None` if `from` does not
+    /// require `to`, even transitively.
+    pub fn get_requirement_chain(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let from_idx = self.get_package_index(from).ok()?;
+        let to_idx = self.get_package_index(to).ok()?;
+
+        let mut parents: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+        let mut queue = VecDeque::from([from_idx]);
+        let mut visited = HashSet::from([from_idx]);
+        while let Some(idx) = queue.pop_front() {
+            if idx == to_idx {
+                let mut chain = vec![idx];
+                while let Some(&parent) = parents.get(chain.last()?) {
+                    chain.push(parent);
+                }
+                chain.reverse();
+                return Some(
+                    chain
+                        .into_iter()
+                        .map(|idx| self.graph[idx].clone())
+                        .collect(),
+                );
+            }
+            for edge in self.graph.edges_directed(idx, petgraph::Outgoing) {
+                if edge.weight().dependency_type == DependencyType::Requires
+                    && visited.insert(edge.target())
+                {
+                    parents.insert(edge.target(), idx);
+                    queue.push_back(edge.target());
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns an iterator over all locked package names in the graph.
+    pub fn all_packages(&self) -> impl Iterator<Item = &String> {
+        self.package_index.keys()
+    }
+
+    /// Returns the edges of a node in one direction, skipping edges to or from symbol nodes.
+    fn module_edges(
+        &self,
+        node: NodeIndex,
+        direction: petgraph::Direction,
+    ) -> impl Iterator<Item = EdgeReference<'_, DependencyEdge>> {
+        self.graph
+            .edges_directed(node, direction)
+            .filter(move |edge| {
+                let other = match direction {
+                    petgraph::Outgoing => edge.target(),
+                    petgraph::Incoming => edge.source(),
+                };
+                !self.symbol_kinds.contains_key(&other)
+            })
+    }
+
     /// Gets all modules that the specified module depends on.
     ///
     /// Returns a vector of module identifiers that this module imports.
@@ -92,8 +434,7 @@ impl DependencyGraph {
         let node_idx = self.get_node_index(module_id)?;
 
         Ok(self
-            .graph
-            .edges(node_idx)
+            .module_edges(node_idx, petgraph::Outgoing)
             .filter_map(|edge| self.graph.node_weight(edge.target()))
             .cloned()
             .collect())
@@ -126,8 +467,8 @@ impl DependencyGraph {
         queue.push_back(start);
 
         while let Some(current) = queue.pop_front() {
-            for edge in self.graph.edges(current) {
-                if matches!(edge.weight(), DependencyType::Contains) {
+            for edge in self.module_edges(current, petgraph::Outgoing) {
+                if edge.weight().dependency_type == DependencyType::Contains {
                     let child = edge.target();
                     if visited.insert(child) {
                         result.push(child);
@@ -150,8 +491,7 @@ impl DependencyGraph {
         let node_idx = self.get_node_index(module_id)?;
 
         Ok(self
-            .graph
-            .edges_directed(node_idx, petgraph::Incoming)
+            .module_edges(node_idx, petgraph::Incoming)
             .filter_map(|edge| self.graph.node_weight(edge.source()))
             .cloned()
             .collect())
@@ -170,8 +510,29 @@ impl DependencyGraph {
         let node_idx = self.get_node_index(module_id)?;
 
         Ok(self
-            .graph
-            .edges(node_idx)
+            .module_edges(node_idx, petgraph::Outgoing)
+            .filter_map(|edge| {
+                self.graph
+                    .node_weight(edge.target())
+                    .map(|module| (module.clone(), edge.weight().dependency_type.clone()))
+            })
+            .collect())
+    }
+
+    /// Gets all outgoing edges of the specified module with their full attributes.
+    ///
+    /// Returns a vector of tuples containing (target_module, edge).
+    ///
+    /// # Errors
+    /// Returns an error if the module is not found in the graph.
+    pub fn get_dependency_edges(
+        &self,
+        module_id: &ModuleIdentifier,
+    ) -> Result<Vec<(String, DependencyEdge)>> {
+        let node_idx = self.get_node_index(module_id)?;
+
+        Ok(self
+            .module_edges(node_idx, petgraph::Outgoing)
             .filter_map(|edge| {
                 self.graph
                     .node_weight(edge.target())
@@ -183,26 +544,31 @@ impl DependencyGraph {
     /// Gets all modules that depend on the specified module **or any of its descendants**.
     ///
     /// Traverses `Contains` edges downward, then collects incoming edges to each visited node.
-    /// Returns (dependent_module, dependency_type_on_that_child). De-duplicates by dependent module name.
+    /// Returns (dependent_module, dependency_type_on_that_child). De-duplicates by dependent module name,
+    /// preferring runtime relationships over `TypeOnlyImports`.
     pub fn get_transitive_dependents_with_types(
         &self,
         module_id: &ModuleIdentifier,
     ) -> Result<Vec<(String, DependencyType)>> {
         let descendant_nodes = self.descendants_via_contains(module_id, true)?;
-        let mut seen_dependents = HashSet::new();
+        let mut seen_dependents = HashMap::new();
         let mut result = Vec::new();
 
         result.push((module_id.canonical_path.clone(), DependencyType::Is));
 
         for node in descendant_nodes {
-            for edge in self.graph.edges_directed(node, petgraph::Incoming) {
-                if *edge.weight() == DependencyType::Contains {
+            for edge in self.module_edges(node, petgraph::Incoming) {
+                let dependency_type = &edge.weight().dependency_type;
+                if *dependency_type == DependencyType::Contains {
                     continue;
                 }
                 if let Some(dependent_module) = self.graph.node_weight(edge.source()) {
-                    if seen_dependents.insert(dependent_module.clone()) {
-                        result.push((dependent_module.clone(), edge.weight().clone()));
-                    }
+                    utils::push_preferring_runtime(
+                        &mut result,
+                        &mut seen_dependents,
+                        dependent_module,
+                        dependency_type,
+                    );
                 }
             }
         }
@@ -213,19 +579,21 @@ impl DependencyGraph {
     /// Gets all modules that the specified module **or any of its descendants** depend on.
     ///
     /// Traverses `Contains` edges downward, then collects outgoing edges from each visited node.
-    /// Returns (dependency_module, dependency_type_from_that_child). De-duplicates by dependency module name.
+    /// Returns (dependency_module, dependency_type_from_that_child). De-duplicates by dependency module name,
+    /// preferring runtime relationships over `TypeOnlyImports`.
     /// Excludes dependencies that point back to ancestor modules to avoid artificial cycles.
     pub fn get_transitive_dependencies_with_types(
         &self,
         module_id: &ModuleIdentifier,
     ) -> Result<Vec<(String, DependencyType)>> {
         let descendant_nodes = self.descendants_via_contains(module_id, true)?;
-        let mut seen_dependencies = HashSet::new();
+        let mut seen_dependencies = HashMap::new();
         let mut result = Vec::new();
 
         for node in descendant_nodes {
-            for edge in self.graph.edges(node) {
-                if *edge.weight() == DependencyType::Contains {
+            for edge in self.module_edges(node, petgraph::Outgoing) {
+                let dependency_type = &edge.weight().dependency_type;
+                if *dependency_type == DependencyType::Contains {
                     continue;
                 }
                 if let Some(dependency_module) = self.graph.node_weight(edge.target()) {
@@ -233,9 +601,12 @@ impl DependencyGraph {
                     if utils::is_ancestor_module(&module_id.canonical_path, dependency_module) {
                         continue;
                     }
-                    if seen_dependencies.insert(dependency_module.clone()) {
-                        result.push((dependency_module.clone(), edge.weight().clone()));
-                    }
+                    utils::push_preferring_runtime(
+                        &mut result,
+                        &mut seen_dependencies,
+                        dependency_module,
+                        dependency_type,
+                    );
                 }
             }
         }
@@ -243,9 +614,116 @@ impl DependencyGraph {
         Ok(result)
     }
 
+    /// Gets the locations of the import statements of the specified module **or any of its
+    /// descendants**, grouped by imported module.
+    ///
+    /// Locations are sorted for each imported module.
+    pub fn get_transitive_dependency_locations(
+        &self,
+        module_id: &ModuleIdentifier,
+    ) -> Result<HashMap<String, Vec<SourceLocation>>> {
+        self.import_locations(module_id, petgraph::Outgoing)
+    }
+
+    /// Gets the locations of the import statements that import the specified module
+    /// **or any of its descendants**, grouped by importing module.
+    ///
+    /// Locations are sorted for each importing module.
+    pub fn get_transitive_dependent_locations(
+        &self,
+        module_id: &ModuleIdentifier,
+    ) -> Result<HashMap<String, Vec<SourceLocation>>> {
+        self.import_locations(module_id, petgraph::Incoming)
+    }
+
+    /// Collects import locations on the edges of a module and its descendants in one direction,
+    /// keyed by the module at the other end of each edge.
+    fn import_locations(
+        &self,
+        module_id: &ModuleIdentifier,
+        direction: petgraph::Direction,
+    ) -> Result<HashMap<String, Vec<SourceLocation>>> {
+        let mut locations: HashMap<String, Vec<SourceLocation>> = HashMap::new();
+
+        for node in self.descendants_via_contains(module_id, true)? {
+            for edge in self.module_edges(node, direction) {
+                let Some(location) = &edge.weight().location else {
+                    continue;
+                };
+                let other = match direction {
+                    petgraph::Outgoing => edge.target(),
+                    petgraph::Incoming => edge.source(),
+                };
+                if let Some(other_module) = self.graph.node_weight(other) {
+                    locations
+                        .entry(other_module.clone())
+                        .or_default()
+                        .push(location.clone());
+                }
+            }
+        }
+
+        for module_locations in locations.values_mut() {
+            module_locations.sort();
+            module_locations.dedup();
+        }
+        Ok(locations)
+    }
+
+    /// Removes the import edges (`Imports`, `DynamicImports`, `TypeOnlyImports`) leaving a
+    /// module, so that the imports of a changed file can be added again.
+    ///
+    /// Returns the modules that were imported.
+    ///
+    /// # Errors
+    /// Returns an error if the module is not found in the graph.
+    pub fn remove_imports(
+        &mut self,
+        module_id: &ModuleIdentifier,
+    ) -> Result<Vec<ModuleIdentifier>> {
+        let node_idx = self.get_node_index(module_id)?;
+        let mut edges: Vec<(EdgeIndex, NodeIndex)> = self
+            .module_edges(node_idx, petgraph::Outgoing)
+            .filter(|edge| is_import(&edge.weight().dependency_type))
+            .map(|edge| (edge.id(), edge.target()))
+            .collect();
+
+        // Removing an edge moves the last edge into its index, so remove from the end
+        edges.sort_by_key(|(edge, _)| std::cmp::Reverse(*edge));
+        let targets: HashSet<NodeIndex> = edges.iter().map(|(_, target)| *target).collect();
+        for (edge, _) in edges {
+            self.graph.remove_edge(edge);
+        }
+
+        let mut imported: Vec<ModuleIdentifier> = self
+            .module_index
+            .iter()
+            .filter(|(_, node)| targets.contains(node))
+            .map(|(module, _)| module.clone())
+            .collect();
+        imported.sort();
+        Ok(imported)
+    }
+
+    /// Returns true if any module imports the specified module.
+    ///
+    /// # Errors
+    /// Returns an error if the module is not found in the graph.
+    pub fn is_imported(&self, module_id: &ModuleIdentifier) -> Result<bool> {
+        let node_idx = self.get_node_index(module_id)?;
+        Ok(self
+            .module_edges(node_idx, petgraph::Incoming)
+            .any(|edge| is_import(&edge.weight().dependency_type)))
+    }
+
+    /// Returns true if the graph has a node for the module.
+    pub fn contains_module(&self, module_id: &ModuleIdentifier) -> bool {
+        self.module_index.contains_key(module_id)
+    }
+
     /// Returns the total number of modules in the graph.
     pub fn module_count(&self) -> usize {
-        self.graph.node_count()
+        self.module_index.len()
     }
 
     /// Returns the total number of dependency relationships in the graph.
@@ -257,6 +735,47 @@ impl DependencyGraph {
     pub fn all_modules(&self) -> impl Iterator<Item = &ModuleIdentifier> {
         self.module_index.keys()
     }
+
+    /// Records a file or import that could not be analyzed.
+    pub fn add_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Drops the diagnostics of a file, before it is analyzed again.
+    pub fn remove_diagnostics(&mut self, path: &str) {
+        self.diagnostics.remove_file(path);
+    }
+
+    /// Returns the files and imports that could not be analyzed while building the graph.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Marks the module with this path as a namespace package.
+    pub fn mark_namespace_package(&mut self, module_path: &str) {
+        self.namespace_packages.insert(module_path.to_string());
+    }
+
+    /// Returns true if the module with this path is a namespace package.
+    pub fn is_namespace_package(&self, module_path: &str) -> bool {
+        self.namespace_packages.contains(module_path)
+    }
+
+    /// Returns true if the node with this path is a Jupyter notebook.
+    pub fn is_notebook(&self, module_path: &str) -> bool {
+        self.module_index.contains_key(&ModuleIdentifier {
+            origin: ModuleOrigin::Notebook,
+            canonical_path: module_path.to_string(),
+        })
+    }
+}
+
+/// Returns true for the relationships created by import statements.
+fn is_import(dependency_type: &DependencyType) -> bool {
+    matches!(
+        dependency_type,
+        DependencyType::Imports | DependencyType::DynamicImports | DependencyType::TypeOnlyImports
+    )
 }
 
 /// Utility functions for working with dependency graphs
@@ -266,9 +785,15 @@ pub mod utils {
     /// Adds Contains/IncludedIn relationships based on module path hierarchy.
     ///
     /// For each module with dots in its path, creates bidirectional relationships
-    /// with its direct parent module.
+    /// with its direct parent module. Notebooks are not part of the module hierarchy.
     pub fn add_containment_relationships(graph: &mut DependencyGraph) -> Result<()> {
-        let modules: Vec<ModuleIdentifier> = graph.all_modules().cloned().collect();
+        let mut modules: Vec<ModuleIdentifier> = graph
+            .all_modules()
+            .filter(|module| module.origin != ModuleOrigin::Notebook)
+            .cloned()
+            .collect();
+        // Sorted so that edges are added in the same order on every run
+        modules.sort();
 
         for module in &modules {
             if let Some(parent_path) = get_direct_parent_module(&module.canonical_path) {
@@ -286,6 +811,68 @@ pub mod utils {
         Ok(())
     }
 
+    /// Marks the namespace packages of the graph: internal packages without a file of
+    /// their own (`known_modules` holds the modules backed by a file) whose directory
+    /// holds other modules, and well-known third-party namespace packages.
+    ///
+    /// Parents added for imports of modules that don't exist (e.g. a dynamic import of
+    /// `myapp.plugins.x` without a `myapp/plugins/` directory) are not marked.
+    ///
+    /// Must run after [`add_containment_relationships`], which adds the namespace nodes.
+    pub fn mark_namespace_packages(graph: &mut DependencyGraph, known_modules: &HashSet<String>) {
+        // Packages with a directory on disk: the parents of the modules backed by a file
+        let directories: HashSet<&str> = known_modules
+            .iter()
+            .flat_map(|module| module.match_indices('.').map(|(end, _)| &module[..end]))
+            .collect();
+
+        let namespaces: Vec<String> = graph
+            .all_modules()
+            .filter(|module| match module.origin {
+                ModuleOrigin::Internal => {
+                    !known_modules.contains(&module.canonical_path)
+                        && directories.contains(module.canonical_path.as_str())
+                        && graph.get_dependencies_with_types(module).is_ok_and(|deps| {
+                            deps.iter()
+                                .any(|(_, dep_type)| *dep_type == DependencyType::Contains)
+                        })
+                }
+                ModuleOrigin::External => {
+                    EXTERNAL_NAMESPACE_PACKAGES.contains(&module.canonical_path.as_str())
+                }
+                ModuleOrigin::Notebook => false,
+            })
+            .map(|module| module.canonical_path.clone())
+            .collect();
+
+        for namespace in namespaces {
+            graph.mark_namespace_package(&namespace);
+        }
+    }
+
+    /// Appends a (module, dependency_type) pair unless the module was already seen.
+    ///
+    /// A module first seen through `TypeOnlyImports` is upgraded if a runtime
+    /// relationship to it shows up later.
+    pub(crate) fn push_preferring_runtime(
+        result: &mut Vec<(String, DependencyType)>,
+        seen: &mut HashMap<String, usize>,
+        module: &str,
+        dependency_type: &DependencyType,
+    ) {
+        match seen.get(module) {
+            Some(&index) => {
+                if result[index].1 == DependencyType::TypeOnlyImports {
+                    result[index].1 = dependency_type.clone();
+                }
+            }
+            None => {
+                seen.insert(module.to_string(), result.len());
+                result.push((module.to_string(), dependency_type.clone()));
+            }
+        }
+    }
+
     /// Extracts the direct parent module from a module path.
     ///
     /// Returns the immediate parent module path, or None if the module is top-level.
@@ -327,26 +914,63 @@ fn format_dependency_graph(graph: &DependencyGraph, f: &mut fmt::Formatter<'_>)
 
     let mut internal_modules: Vec<_> = graph
         .all_modules()
-        .filter(|m| m.origin == ModuleOrigin::Internal)
+        .filter(|m| m.origin != ModuleOrigin::External)
         .collect();
     internal_modules.sort_by(|a, b| a.canonical_path.cmp(&b.canonical_path));
 
     for module in internal_modules {
-        let dependencies = graph
-            .get_dependencies_with_types(module)
-            .unwrap_or_default();
+        let dependencies = graph.get_dependency_edges(module).unwrap_or_default();
 
+        let namespace = if graph.is_namespace_package(&module.canonical_path) {
+            " [namespace package]"
+        } else {
+            ""
+        };
         if dependencies.is_empty() {
-            writeln!(f, "{} -> (no dependencies)", module.canonical_path)?;
+            writeln!(
+                f,
+                "{}{} -> (no dependencies)",
+                module.canonical_path, namespace
+            )?;
         } else {
             writeln!(
                 f,
-                "{} -> ({} deps)",
+                "{}{} -> ({} deps)",
                 module.canonical_path,
+                namespace,
                 dependencies.len()
             )?;
-            for (dep_module, dep_type) in dependencies {
-                writeln!(f, "  -> {} ({:?})", dep_module, dep_type)?;
+            for (dep_module, edge) in dependencies {
+                let optional = if edge.optional { ", optional" } else { "" };
+                let dynamic_package = edge
+                    .dynamic_package
+                    .as_ref()
+                    .map(|package| format!(", dynamic import into package {}", package))
+                    .unwrap_or_default();
+                let via = edge
+                    .via
+                    .as_ref()
+                    .map(|package| format!(", via {}", package))
+                    .unwrap_or_default();
+                let location = edge
+                    .location
+                    .as_ref()
+                    .map(|location| format!(" at {}", location))
+                    .unwrap_or_default();
+                match edge.scope {
+                    Some(scope) => writeln!(
+                        f,
+                        "  -> {} ({:?}, {:?} scope{}{}{}){}",
+                        dep_module,
+                        edge.dependency_type,
+                        scope,
+                        optional,
+                        dynamic_package,
+                        via,
+                        location
+                    )?,
+                    None => writeln!(f, "  -> {} ({:?})", dep_module, edge.dependency_type)?,
+                }
             }
         }
     }
@@ -692,4 +1316,45 @@ mod tests {
         assert!(deps.contains(&("module2".to_string(), DependencyType::Imports)));
         assert!(deps.contains(&("module3".to_string(), DependencyType::Contains)));
     }
+
+    #[test]
+    fn test_package_requirements() {
+        let mut graph = DependencyGraph::new();
+        let six = create_test_module_id("six", ModuleOrigin::External);
+        graph.add_module(six.clone());
+
+        graph.add_package("pandas", Some("2.2.2"));
+        graph.add_package("python-dateutil", Some("2.9.0"));
+        graph.add_package("six", Some("1.16.0"));
+        graph.add_package("numpy", None);
+        graph.add_package_requirement("pandas", "numpy").unwrap();
+        graph
+            .add_package_requirement("pandas", "python-dateutil")
+            .unwrap();
+        graph
+            .add_package_requirement("python-dateutil", "six")
+            .unwrap();
+        assert!(graph.add_package_requirement("pandas", "missing").is_err());
+
+        // Package nodes are kept apart from the module with the same name
+        assert_eq!(graph.module_count(), 1);
+        assert!(graph.get_dependents(&six).unwrap().is_empty());
+
+        assert_eq!(graph.get_package_version("six"), Some("1.16.0"));
+        assert_eq!(graph.get_package_version("numpy"), None);
+        let mut requirements = graph.get_package_requirements("pandas").unwrap();
+        requirements.sort();
+        assert_eq!(requirements, vec!["numpy", "python-dateutil"]);
+
+        assert_eq!(
+            graph.get_requirement_chain("pandas", "six"),
+            Some(vec![
+                "pandas".to_string(),
+                "python-dateutil".to_string(),
+                "six".to_string()
+            ])
+        );
+        assert_eq!(graph.get_requirement_chain("six", "pandas"), None);
+        assert_eq!(graph.get_requirement_chain("missing", "six"), None);
+    }
 }
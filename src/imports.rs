@@ -1,29 +1,383 @@
 use anyhow::Result;
-use rustpython_parser::ast::{Mod, Stmt};
+use rustpython_parser::ast::{
+    Alias, Arguments, Comprehension, Constant, ExceptHandler, Expr, ExprCall, Mod, Operator, Stmt,
+};
+use rustpython_parser::source_code::RandomLocator;
+use rustpython_parser::text_size::TextSize;
 use rustpython_parser::{Mode, parse};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// Represents the origin type of a Python module.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ModuleOrigin {
     External, // Standard library and third-party packages
     Internal, // Project modules within the same codebase
+    Notebook, // Jupyter notebooks of the project (not importable)
 }
 
 /// Unique identifier for a Python module.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct ModuleIdentifier {
     pub origin: ModuleOrigin,
     pub canonical_path: String,
 }
 
+/// Represents the scope in which an import statement is executed.
+///
+/// Variants are ordered from eager to deferred: nesting takes the most deferred
+/// enclosing scope, while repeated imports of one module keep the most eager one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ImportScope {
+    Module,      // Top-level statement, executed at import time
+    Class,       // Class body, executed when the class is defined
+    Conditional, // Inside an if/try/with/match or loop block
+    Function,    // Function body, deferred until the function is called
+}
+
+impl ImportScope {
+    /// Returns true if the import runs when the importing module is loaded.
+    pub fn is_import_time(&self) -> bool {
+        *self != ImportScope::Function
+    }
+
+    /// Returns the scope of a block nested inside this one.
+    fn nested(self, inner: ImportScope) -> ImportScope {
+        self.max(inner)
+    }
+}
+
+/// Represents the kind of dependency created by an import.
+///
+/// Ordered by precedence: a static runtime import of a module outweighs dynamic ones,
+/// which outweigh type-only ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ImportKind {
+    Runtime,        // Regular import executed by the interpreter
+    Dynamic,        // `importlib.import_module("a.b")` / `__import__("a.b")` with a literal name
+    DynamicPackage, // Dynamic import of a name built from a constant prefix: some module of the package
+    TypeOnly,       // Only seen by type checkers (`if TYPE_CHECKING:` block or string annotation)
+}
+
+/// Position of an import in its source file (1-based line and column).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ImportPosition {
+    /// 1-based notebook cell the line and column are in (notebooks only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cell: Option<usize>,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A module imported by a Python source file, along with the scope and kind of the import.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleImport {
+    pub module: ModuleIdentifier,
+    pub scope: ImportScope,
+    pub kind: ImportKind,
+    /// True if the import is guarded by `try/except ImportError` (the module may be absent)
+    pub optional: bool,
+    /// Where the module is imported (the occurrence that determined scope and kind)
+    pub position: ImportPosition,
+    /// Names imported from the module by `from module import ...` (`*` for star imports)
+    pub names: Vec<String>,
+    /// True if the module is only named by a string annotation, not by an import statement
+    #[serde(default)]
+    pub annotation: bool,
+}
+
+impl ModuleImport {
+    /// Merges another occurrence of the same import into this one.
+    ///
+    /// Runtime occurrences take precedence over type-only ones, then import statements
+    /// over string annotations, then the most eager scope wins, then the earliest position.
+    /// The import stays optional only if every occurrence is guarded.
+    fn merge(&mut self, other: &ModuleImport) {
+        if (other.kind, other.annotation, other.scope, other.position)
+            < (self.kind, self.annotation, self.scope, self.position)
+        {
+            self.kind = other.kind;
+            self.annotation = other.annotation;
+            self.scope = other.scope;
+            self.position = other.position;
+        }
+        self.optional &= other.optional;
+        self.names.extend(other.names.iter().cloned());
+        self.names.sort();
+        self.names.dedup();
+    }
+}
+
+/// Kind of a symbol defined at the top level of a module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SymbolKind {
+    Function, // `def` / `async def`
+    Class,    // `class`
+    Constant, // Assignment to an UPPER_CASE name
+}
+
+/// A function, class or constant defined at the top level of a module.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleSymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Where the symbol is defined
+    pub position: ImportPosition,
+}
+
+/// Names a module makes available to importers: the symbols it defines and the names
+/// it imports from other modules.
+///
+/// Used to trace `from myapp.models import User` through the `myapp/models/__init__.py`
+/// facade to the module that defines `User`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleExports {
+    /// Top-level functions, classes and constants defined by the module
+    pub symbols: Vec<ModuleSymbol>,
+    /// Names listed in `__all__`, if the module defines it
+    pub all: Option<Vec<String>>,
+    /// Names bound by top-level `from module import name [as alias]`: alias -> (module, name)
+    pub reexports: BTreeMap<String, (String, String)>,
+    /// Modules re-exported wholesale with a top-level `from module import *`
+    pub star_sources: Vec<String>,
+}
+
+/// Why a relative import could not be resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnresolvedReason {
+    BeyondPackageRoot, // More leading dots than parent packages (`from ... import x` in `pkg/a.py`)
+    NoPackage,         // Importing module is not part of a package (top-level script, notebook)
+}
+
+/// A relative import that could not be resolved to a module.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnresolvedImport {
+    /// Module as written in the import, with its leading dots (`...models`)
+    pub module: String,
+    /// Where the module is imported
+    pub position: ImportPosition,
+    pub reason: UnresolvedReason,
+}
+
+impl std::fmt::Display for UnresolvedImport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.reason {
+            UnresolvedReason::BeyondPackageRoot => {
+                write!(f, "'{}' climbs above the package root", self.module)
+            }
+            UnresolvedReason::NoPackage => {
+                write!(f, "'{}' is imported outside of a package", self.module)
+            }
+        }
+    }
+}
+
+/// Everything extracted from the source of one module.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleAnalysis {
+    /// Imported modules, sorted by module path
+    pub imports: Vec<ModuleImport>,
+    /// Symbols, re-exported names and `__all__` of the module
+    pub exports: ModuleExports,
+    /// Relative imports that could not be resolved
+    pub unresolved_imports: Vec<UnresolvedImport>,
+    /// Notebook cells that could not be parsed, and were skipped
+    #[serde(default)]
+    pub syntax_errors: Vec<SyntaxError>,
+    /// `from package import name` statements not resolved yet (see [`ModuleAnalysis::resolve`])
+    #[serde(default)]
+    pub from_imports: Vec<FromImport>,
+}
+
+impl ModuleAnalysis {
+    /// Resolves the `from package import name` statements against the internal modules
+    /// of the project, adding their imports and re-exports to the analysis.
+    ///
+    /// Parsing does not depend on the other files of the project, so an unresolved
+    /// analysis stays valid while its file does not change.
+    pub fn resolve(&mut self, known_modules: Option<&HashSet<String>>) {
+        let mut resolved = Vec::new();
+        for from_import in std::mem::take(&mut self.from_imports) {
+            resolved.extend(from_import.resolve(known_modules, &mut self.exports));
+        }
+        self.imports = merge_imports(
+            std::mem::take(&mut self.imports)
+                .into_iter()
+                .chain(resolved),
+        );
+    }
+}
+
+/// A `from <package> import <names>` statement, whose names may be submodules of the package.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FromImport {
+    /// Imported package, with relative imports already made absolute
+    pub package: String,
+    /// Imported names and their alias (`*` for star imports)
+    pub names: Vec<(String, Option<String>)>,
+    pub scope: ImportScope,
+    pub kind: ImportKind,
+    pub optional: bool,
+    pub position: ImportPosition,
+}
+
+impl FromImport {
+    /// Returns the imports of the statement.
+    ///
+    /// Names that are known internal submodules of the package become imports of those
+    /// submodules; other names (functions, classes, constants) keep targeting the package
+    /// and are recorded on its import. Top-level statements are also recorded as re-exports.
+    fn resolve(
+        self,
+        known_modules: Option<&HashSet<String>>,
+        exports: &mut ModuleExports,
+    ) -> Vec<ModuleImport> {
+        let package_path = crate::pyproject::normalize_module_name(&self.package)
+            .unwrap_or_else(|_| self.package.clone());
+        let import_of = |module: ModuleIdentifier| ModuleImport {
+            module,
+            scope: self.scope,
+            kind: self.kind,
+            optional: self.optional,
+            position: self.position,
+            names: Vec::new(),
+            annotation: false,
+        };
+        let mut imports = Vec::new();
+        let mut symbols = Vec::new();
+
+        for (name, alias) in &self.names {
+            let candidate = if package_path.is_empty() {
+                name.clone()
+            } else {
+                format!("{}.{}", package_path, name)
+            };
+            if known_modules.is_some_and(|known_modules| known_modules.contains(&candidate)) {
+                imports.push(import_of(ModuleIdentifier {
+                    origin: ModuleOrigin::Internal,
+                    canonical_path: candidate,
+                }));
+                continue;
+            }
+            // A third-party namespace package only holds subpackages (`from google.cloud import storage`)
+            if name != "*"
+                && EXTERNAL_NAMESPACE_PACKAGES.contains(&self.package.as_str())
+                && !crate::pyproject::is_internal_module(&self.package)
+            {
+                let module_id = resolve_module_identifier(&format!("{}.{}", self.package, name));
+                imports.push(import_of(module_id));
+                continue;
+            }
+
+            symbols.push(name.clone());
+            if self.scope == ImportScope::Module {
+                if name == "*" {
+                    exports.star_sources.push(package_path.clone());
+                } else {
+                    let bound = alias.as_deref().unwrap_or(name);
+                    exports
+                        .reexports
+                        .insert(bound.to_string(), (package_path.clone(), name.clone()));
+                }
+            }
+        }
+
+        if self.names.is_empty() || !symbols.is_empty() {
+            symbols.sort();
+            symbols.dedup();
+            imports.push(ModuleImport {
+                names: symbols,
+                ..import_of(resolve_module_identifier(&self.package))
+            });
+        }
+        imports
+    }
+}
+
+/// Merges the imports of the same module, sorted by module path.
+///
+/// Dynamic imports of a package are kept apart from its static imports.
+pub(crate) fn merge_imports(imports: impl IntoIterator<Item = ModuleImport>) -> Vec<ModuleImport> {
+    let mut merged: HashMap<(ModuleIdentifier, bool), ModuleImport> = HashMap::new();
+    for import in imports {
+        let key = (
+            import.module.clone(),
+            import.kind == ImportKind::DynamicPackage,
+        );
+        merged
+            .entry(key)
+            .and_modify(|existing| existing.merge(&import))
+            .or_insert(import);
+    }
+    let mut imports: Vec<ModuleImport> = merged.into_values().collect();
+    imports.sort_by(|a, b| {
+        (&a.module.canonical_path, a.kind).cmp(&(&b.module.canonical_path, b.kind))
+    });
+    imports
+}
+
+/// Error returned when Python source code cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyntaxError {
+    /// Where parsing failed
+    pub position: ImportPosition,
+    pub message: String,
+}
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at ", self.message)?;
+        if let Some(cell) = self.position.cell {
+            write!(f, "cell {}, ", cell)?;
+        }
+        write!(
+            f,
+            "line {}, column {}",
+            self.position.line, self.position.column
+        )
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
 /// Extracts the root module name from a dotted module path.
 fn extract_root_module(module_name: &str) -> &str {
     module_name.split('.').next().unwrap_or(module_name)
 }
 
+/// Well-known PEP 420 namespace packages shared by several third-party distributions.
+pub const EXTERNAL_NAMESPACE_PACKAGES: &[&str] = &[
+    "azure",
+    "azure.mgmt",
+    "azure.storage",
+    "backports",
+    "google",
+    "google.cloud",
+    "jaraco",
+    "opentelemetry",
+    "opentelemetry.exporter",
+    "opentelemetry.instrumentation",
+    "ruamel",
+    "sphinxcontrib",
+    "zope",
+];
+
+/// Extracts the package of a third-party module: its root module, or the first module
+/// below a namespace package (`google.cloud.storage.blob` -> `google.cloud.storage`).
+pub fn external_package_root(module_name: &str) -> &str {
+    let mut end = extract_root_module(module_name).len();
+    while end < module_name.len() && EXTERNAL_NAMESPACE_PACKAGES.contains(&&module_name[..end]) {
+        end = module_name[end + 1..]
+            .find('.')
+            .map_or(module_name.len(), |next| end + 1 + next);
+    }
+    &module_name[..end]
+}
+
 /// Resolves relative imports to absolute module paths.
+///
+/// Returns `None` when the import climbs above the top-level package, like the interpreter's
+/// "attempted relative import beyond top-level package".
 fn resolve_relative_import(module_name: &str, level: u32, current_module: &str) -> Option<String> {
     if level == 0 {
         return Some(module_name.to_string());
@@ -31,9 +385,9 @@ fn resolve_relative_import(module_name: &str, level: u32, current_module: &str)
 
     let current_parts: Vec<&str> = current_module.split('.').collect();
 
-    // Check if we have enough parent levels
-    if level as usize > current_parts.len() {
-        return None; // Invalid relative import - goes beyond package root
+    // Each dot drops one trailing part; at least the top-level package must remain
+    if level as usize >= current_parts.len() {
+        return None;
     }
 
     // Calculate the target parent level
@@ -45,11 +399,7 @@ fn resolve_relative_import(module_name: &str, level: u32, current_module: &str)
         Some(parent_path)
     } else {
         // from .module import something or from ..parent.module import something
-        if parent_path.is_empty() {
-            Some(module_name.to_string())
-        } else {
-            Some(format!("{}.{}", parent_path, module_name))
-        }
+        Some(format!("{}.{}", parent_path, module_name))
     }
 }
 
@@ -64,7 +414,7 @@ fn resolve_module_identifier(module_name: &str) -> ModuleIdentifier {
     let canonical_path = match origin {
         ModuleOrigin::Internal => crate::pyproject::normalize_module_name(module_name)
             .unwrap_or_else(|_| module_name.to_string()),
-        _ => extract_root_module(module_name).to_string(),
+        _ => external_package_root(module_name).to_string(),
     };
 
     ModuleIdentifier {
@@ -73,94 +423,913 @@ fn resolve_module_identifier(module_name: &str) -> ModuleIdentifier {
     }
 }
 
-/// Processes a Python AST statement and extracts module dependencies.
-fn process_stmt(
-    stmt: &Stmt,
-    modules: &mut HashSet<ModuleIdentifier>,
-    current_module: Option<&str>,
-) {
-    match stmt {
-        Stmt::Import(import_stmt) => {
-            for alias in &import_stmt.names {
-                let module_id = resolve_module_identifier(&alias.name);
-                modules.insert(module_id);
-            }
-        }
-        Stmt::ImportFrom(import_from_stmt) => {
-            // Handle relative imports by resolving them to absolute paths
-            let level = import_from_stmt
-                .level
-                .as_ref()
-                .map(|_level_int| {
-                    // For now, we'll extract the level by parsing the debug representation
-                    // This is a limitation of the current rustpython-parser API
-                    let debug_str = format!("{:?}", _level_int);
-
-                    // Look for a numeric value in the debug string
-                    for char in debug_str.chars() {
-                        if char.is_ascii_digit() {
-                            if let Some(digit) = char.to_digit(10) {
-                                return digit;
-                            }
-                        }
-                    }
+/// Context of the block currently being walked.
+#[derive(Debug, Clone, Copy)]
+struct BlockContext {
+    scope: ImportScope,
+    kind: ImportKind,
+    optional: bool,
+    /// True while recording the modules named by string annotations
+    annotation: bool,
+}
+
+impl BlockContext {
+    /// Returns the context of a block of the given scope nested inside this one.
+    fn nested(self, scope: ImportScope) -> Self {
+        Self {
+            scope: self.scope.nested(scope),
+            ..self
+        }
+    }
+
+    /// Returns the same context with imports marked as type-only.
+    fn type_only(self) -> Self {
+        Self {
+            kind: ImportKind::TypeOnly,
+            ..self
+        }
+    }
 
-                    // If no digit found, assume level 1 for relative imports
-                    1
+    /// Returns the same context for a module named by a string annotation, which is
+    /// type-only.
+    fn annotation(self) -> Self {
+        Self {
+            annotation: true,
+            ..self.type_only()
+        }
+    }
+
+    /// Returns the same context for a dynamic import of the given kind.
+    fn dynamic(self, kind: ImportKind) -> Self {
+        Self {
+            kind: self.kind.max(kind),
+            ..self
+        }
+    }
+
+    /// Returns the same context with imports marked as optional.
+    fn optional(self) -> Self {
+        Self {
+            optional: true,
+            ..self
+        }
+    }
+}
+
+/// Walks a module AST and collects the modules it imports.
+struct ImportCollector<'a> {
+    current_module: Option<&'a str>,
+    locator: RandomLocator<'a>,
+    imports: HashMap<ModuleIdentifier, ModuleImport>,
+    /// Re-exported names and `__all__` of the module
+    exports: ModuleExports,
+    /// Packages targeted by dynamic imports with a constant prefix, kept apart from
+    /// `imports` so that a static import of the package does not absorb them
+    dynamic_packages: HashMap<ModuleIdentifier, ModuleImport>,
+    /// Names bound locally by imports and definitions
+    bound_names: HashSet<String>,
+    /// Dotted names referenced from string annotations, resolved once all bindings are known
+    annotation_refs: Vec<(String, BlockContext, TextSize)>,
+    /// Relative imports that could not be resolved
+    unresolved_imports: Vec<UnresolvedImport>,
+    /// `from package import name` statements, resolved after parsing
+    from_imports: Vec<FromImport>,
+}
+
+impl<'a> ImportCollector<'a> {
+    fn new(python_code: &'a str, current_module: Option<&'a str>) -> Self {
+        Self {
+            current_module,
+            locator: RandomLocator::new(python_code),
+            imports: HashMap::new(),
+            exports: ModuleExports::default(),
+            dynamic_packages: HashMap::new(),
+            bound_names: HashSet::new(),
+            annotation_refs: Vec::new(),
+            unresolved_imports: Vec::new(),
+            from_imports: Vec::new(),
+        }
+    }
+
+    /// Returns the 1-based line and column of an offset in the source.
+    fn position(&mut self, offset: TextSize) -> ImportPosition {
+        let location = self.locator.locate(offset);
+        ImportPosition {
+            cell: None,
+            line: location.row.to_usize(),
+            column: location.column.to_usize(),
+        }
+    }
+
+    /// Records an imported module, merging it with earlier imports of the same module.
+    fn record_import(
+        &mut self,
+        module_id: ModuleIdentifier,
+        ctx: BlockContext,
+        offset: TextSize,
+    ) -> &mut ModuleImport {
+        let import = ModuleImport {
+            module: module_id.clone(),
+            scope: ctx.scope,
+            kind: ctx.kind,
+            optional: ctx.optional,
+            position: self.position(offset),
+            names: Vec::new(),
+            annotation: ctx.annotation,
+        };
+        let imports = if ctx.kind == ImportKind::DynamicPackage {
+            &mut self.dynamic_packages
+        } else {
+            &mut self.imports
+        };
+        imports
+            .entry(module_id)
+            .and_modify(|existing| existing.merge(&import))
+            .or_insert(import)
+    }
+
+    /// Records a `from <package> import <names>` statement, resolved once the modules of
+    /// the project are known.
+    fn record_from_import(
+        &mut self,
+        package: &str,
+        names: &[Alias],
+        ctx: BlockContext,
+        offset: TextSize,
+    ) {
+        let position = self.position(offset);
+        self.from_imports.push(FromImport {
+            package: package.to_string(),
+            names: names
+                .iter()
+                .map(|alias| {
+                    (
+                        alias.name.to_string(),
+                        alias.asname.as_ref().map(|asname| asname.to_string()),
+                    )
                 })
-                .unwrap_or(0);
+                .collect(),
+            scope: ctx.scope,
+            kind: ctx.kind,
+            optional: ctx.optional,
+            position,
+        });
+    }
+
+    /// Records a symbol defined by a top-level statement (the first definition of a name wins).
+    fn record_symbol(&mut self, name: &str, kind: SymbolKind, offset: TextSize, ctx: BlockContext) {
+        if ctx.scope != ImportScope::Module
+            || self
+                .exports
+                .symbols
+                .iter()
+                .any(|symbol| symbol.name == name)
+        {
+            return;
+        }
+        let location = self.locator.locate(offset);
+        self.exports.symbols.push(ModuleSymbol {
+            name: name.to_string(),
+            kind,
+            position: ImportPosition {
+                cell: None,
+                line: location.row.to_usize(),
+                column: location.column.to_usize(),
+            },
+        });
+    }
+
+    /// Records a top-level assignment to an UPPER_CASE name as a constant.
+    fn record_constant(&mut self, target: &Expr, ctx: BlockContext) {
+        if let Expr::Name(name) = target
+            && is_constant_name(&name.id)
+        {
+            self.record_symbol(&name.id, SymbolKind::Constant, name.range.start(), ctx);
+        }
+    }
+
+    /// Records the names of a top-level `__all__ = [...]` or `__all__ += [...]` statement.
+    fn record_dunder_all(&mut self, target: &Expr, value: &Expr, extend: bool, ctx: BlockContext) {
+        let is_dunder_all = matches!(target, Expr::Name(name) if name.id.as_str() == "__all__");
+        if !is_dunder_all || ctx.scope != ImportScope::Module {
+            return;
+        }
+        let elts = match value {
+            Expr::List(list) => &list.elts,
+            Expr::Tuple(tuple) => &tuple.elts,
+            _ => return,
+        };
+        let names = elts.iter().filter_map(|elt| match elt {
+            Expr::Constant(constant) => match &constant.value {
+                Constant::Str(name) => Some(name.clone()),
+                _ => None,
+            },
+            _ => None,
+        });
+
+        let all = self.exports.all.get_or_insert_with(Vec::new);
+        if !extend {
+            all.clear();
+        }
+        all.extend(names);
+    }
 
-            if level > 0 {
-                // This is a relative import
-                if let Some(current_mod) = current_module {
+    /// Processes a collection of Python AST statements.
+    fn process_body(&mut self, body: &[Stmt], ctx: BlockContext) {
+        for stmt in body {
+            self.process_stmt(stmt, ctx);
+        }
+    }
+
+    /// Processes a Python AST statement and extracts module dependencies.
+    ///
+    /// Compound statements are walked recursively so that imports nested in
+    /// functions, classes and control-flow blocks are collected as well.
+    fn process_stmt(&mut self, stmt: &Stmt, ctx: BlockContext) {
+        match stmt {
+            Stmt::Import(import_stmt) => {
+                for alias in &import_stmt.names {
+                    let bound = alias.asname.as_deref().unwrap_or(&alias.name);
+                    self.bound_names
+                        .insert(extract_root_module(bound).to_string());
+
+                    let module_id = resolve_module_identifier(&alias.name);
+                    self.record_import(module_id, ctx, import_stmt.range.start());
+                }
+            }
+            Stmt::ImportFrom(import_from_stmt) => {
+                for alias in &import_from_stmt.names {
+                    let bound = alias.asname.as_deref().unwrap_or(&alias.name);
+                    self.bound_names.insert(bound.to_string());
+                }
+
+                // Handle relative imports by resolving them to absolute paths
+                let level = import_from_stmt.level.map_or(0, |level| level.to_u32());
+
+                let offset = import_from_stmt.range.start();
+                if level > 0 {
+                    // This is a relative import
                     let module_name = import_from_stmt.module.as_deref().unwrap_or("");
-                    if let Some(resolved_module) =
+                    let resolved = self.current_module.map(|current_mod| {
                         resolve_relative_import(module_name, level, current_mod)
-                    {
-                        let module_id = resolve_module_identifier(&resolved_module);
-                        modules.insert(module_id);
+                    });
+                    if let Some(Some(resolved_module)) = resolved {
+                        self.record_from_import(
+                            &resolved_module,
+                            &import_from_stmt.names,
+                            ctx,
+                            offset,
+                        );
+                    } else {
+                        // A top-level module has no package to be relative to
+                        let reason = match self.current_module {
+                            Some(current_mod) if current_mod.contains('.') => {
+                                UnresolvedReason::BeyondPackageRoot
+                            }
+                            _ => UnresolvedReason::NoPackage,
+                        };
+                        let position = self.position(offset);
+                        self.unresolved_imports.push(UnresolvedImport {
+                            module: format!("{}{}", ".".repeat(level as usize), module_name),
+                            position,
+                            reason,
+                        });
                     }
+                } else if let Some(module) = &import_from_stmt.module {
+                    // Regular absolute import
+                    self.record_from_import(module, &import_from_stmt.names, ctx, offset);
+                }
+            }
+            Stmt::FunctionDef(def) => {
+                self.bound_names.insert(def.name.to_string());
+                self.record_symbol(&def.name, SymbolKind::Function, def.range.start(), ctx);
+                self.process_signature(&def.args, def.returns.as_deref(), ctx);
+                self.process_body(&def.body, ctx.nested(ImportScope::Function));
+            }
+            Stmt::AsyncFunctionDef(def) => {
+                self.bound_names.insert(def.name.to_string());
+                self.record_symbol(&def.name, SymbolKind::Function, def.range.start(), ctx);
+                self.process_signature(&def.args, def.returns.as_deref(), ctx);
+                self.process_body(&def.body, ctx.nested(ImportScope::Function));
+            }
+            Stmt::ClassDef(def) => {
+                self.bound_names.insert(def.name.to_string());
+                self.record_symbol(&def.name, SymbolKind::Class, def.range.start(), ctx);
+                self.process_body(&def.body, ctx.nested(ImportScope::Class));
+            }
+            Stmt::Expr(expr_stmt) => self.process_expr(&expr_stmt.value, ctx),
+            Stmt::Assign(assign) => {
+                if let [target] = assign.targets.as_slice() {
+                    self.record_dunder_all(target, &assign.value, false, ctx);
+                }
+                for target in &assign.targets {
+                    self.record_constant(target, ctx);
+                }
+                self.process_expr(&assign.value, ctx);
+            }
+            Stmt::AugAssign(aug_assign) => {
+                if aug_assign.op == Operator::Add {
+                    self.record_dunder_all(&aug_assign.target, &aug_assign.value, true, ctx);
+                }
+                self.process_expr(&aug_assign.value, ctx);
+            }
+            Stmt::AnnAssign(ann_assign) => {
+                self.process_annotation(&ann_assign.annotation, ctx, None);
+                self.record_constant(&ann_assign.target, ctx);
+                if let Some(value) = &ann_assign.value {
+                    self.record_dunder_all(&ann_assign.target, value, false, ctx);
+                    self.process_expr(value, ctx);
+                }
+            }
+            Stmt::Return(return_stmt) => {
+                if let Some(value) = &return_stmt.value {
+                    self.process_expr(value, ctx);
+                }
+            }
+            Stmt::If(if_stmt) => {
+                self.process_expr(&if_stmt.test, ctx);
+                let inner = ctx.nested(ImportScope::Conditional);
+                if is_type_checking_guard(&if_stmt.test) {
+                    self.process_body(&if_stmt.body, inner.type_only());
+                } else {
+                    self.process_body(&if_stmt.body, inner);
+                }
+                self.process_body(&if_stmt.orelse, inner);
+            }
+            Stmt::For(for_stmt) => {
+                self.process_expr(&for_stmt.iter, ctx);
+                let inner = ctx.nested(ImportScope::Conditional);
+                self.process_body(&for_stmt.body, inner);
+                self.process_body(&for_stmt.orelse, inner);
+            }
+            Stmt::AsyncFor(for_stmt) => {
+                self.process_expr(&for_stmt.iter, ctx);
+                let inner = ctx.nested(ImportScope::Conditional);
+                self.process_body(&for_stmt.body, inner);
+                self.process_body(&for_stmt.orelse, inner);
+            }
+            Stmt::While(while_stmt) => {
+                self.process_expr(&while_stmt.test, ctx);
+                let inner = ctx.nested(ImportScope::Conditional);
+                self.process_body(&while_stmt.body, inner);
+                self.process_body(&while_stmt.orelse, inner);
+            }
+            Stmt::With(with_stmt) => {
+                for item in &with_stmt.items {
+                    self.process_expr(&item.context_expr, ctx);
+                }
+                self.process_body(&with_stmt.body, ctx.nested(ImportScope::Conditional));
+            }
+            Stmt::AsyncWith(with_stmt) => {
+                for item in &with_stmt.items {
+                    self.process_expr(&item.context_expr, ctx);
+                }
+                self.process_body(&with_stmt.body, ctx.nested(ImportScope::Conditional));
+            }
+            Stmt::Match(match_stmt) => {
+                self.process_expr(&match_stmt.subject, ctx);
+                let inner = ctx.nested(ImportScope::Conditional);
+                for case in &match_stmt.cases {
+                    self.process_body(&case.body, inner);
+                }
+            }
+            Stmt::Try(try_stmt) => {
+                self.process_try(&try_stmt.body, &try_stmt.handlers, ctx);
+                let inner = ctx.nested(ImportScope::Conditional);
+                self.process_body(&try_stmt.orelse, inner);
+                self.process_body(&try_stmt.finalbody, inner);
+            }
+            Stmt::TryStar(try_stmt) => {
+                self.process_try(&try_stmt.body, &try_stmt.handlers, ctx);
+                let inner = ctx.nested(ImportScope::Conditional);
+                self.process_body(&try_stmt.orelse, inner);
+                self.process_body(&try_stmt.finalbody, inner);
+            }
+            _ => {}
+        }
+    }
+
+    /// Walks an expression looking for dynamic import calls.
+    fn process_expr(&mut self, expr: &Expr, ctx: BlockContext) {
+        match expr {
+            Expr::Call(call) => {
+                self.process_dynamic_import(call, ctx);
+                self.process_expr(&call.func, ctx);
+                self.process_exprs(&call.args, ctx);
+                for keyword in &call.keywords {
+                    self.process_expr(&keyword.value, ctx);
+                }
+            }
+            Expr::BoolOp(bool_op) => self.process_exprs(&bool_op.values, ctx),
+            Expr::NamedExpr(named) => self.process_expr(&named.value, ctx),
+            Expr::BinOp(bin_op) => {
+                self.process_expr(&bin_op.left, ctx);
+                self.process_expr(&bin_op.right, ctx);
+            }
+            Expr::UnaryOp(unary_op) => self.process_expr(&unary_op.operand, ctx),
+            Expr::Lambda(lambda) => {
+                self.process_expr(&lambda.body, ctx.nested(ImportScope::Function))
+            }
+            Expr::IfExp(if_exp) => {
+                self.process_expr(&if_exp.test, ctx);
+                self.process_expr(&if_exp.body, ctx);
+                self.process_expr(&if_exp.orelse, ctx);
+            }
+            Expr::Dict(dict) => {
+                for key in dict.keys.iter().flatten() {
+                    self.process_expr(key, ctx);
+                }
+                self.process_exprs(&dict.values, ctx);
+            }
+            Expr::Set(set) => self.process_exprs(&set.elts, ctx),
+            Expr::ListComp(comp) => {
+                self.process_expr(&comp.elt, ctx);
+                self.process_comprehensions(&comp.generators, ctx);
+            }
+            Expr::SetComp(comp) => {
+                self.process_expr(&comp.elt, ctx);
+                self.process_comprehensions(&comp.generators, ctx);
+            }
+            Expr::GeneratorExp(comp) => {
+                self.process_expr(&comp.elt, ctx);
+                self.process_comprehensions(&comp.generators, ctx);
+            }
+            Expr::DictComp(comp) => {
+                self.process_expr(&comp.key, ctx);
+                self.process_expr(&comp.value, ctx);
+                self.process_comprehensions(&comp.generators, ctx);
+            }
+            Expr::Await(await_expr) => self.process_expr(&await_expr.value, ctx),
+            Expr::Yield(yield_expr) => {
+                if let Some(value) = &yield_expr.value {
+                    self.process_expr(value, ctx);
+                }
+            }
+            Expr::YieldFrom(yield_from) => self.process_expr(&yield_from.value, ctx),
+            Expr::Compare(compare) => {
+                self.process_expr(&compare.left, ctx);
+                self.process_exprs(&compare.comparators, ctx);
+            }
+            Expr::FormattedValue(formatted) => self.process_expr(&formatted.value, ctx),
+            Expr::JoinedStr(joined) => self.process_exprs(&joined.values, ctx),
+            Expr::Attribute(attribute) => self.process_expr(&attribute.value, ctx),
+            Expr::Subscript(subscript) => {
+                self.process_expr(&subscript.value, ctx);
+                self.process_expr(&subscript.slice, ctx);
+            }
+            Expr::Starred(starred) => self.process_expr(&starred.value, ctx),
+            Expr::List(list) => self.process_exprs(&list.elts, ctx),
+            Expr::Tuple(tuple) => self.process_exprs(&tuple.elts, ctx),
+            Expr::Slice(slice) => {
+                let bounds = [&slice.lower, &slice.upper, &slice.step];
+                for bound in bounds.into_iter().flatten() {
+                    self.process_expr(bound, ctx);
+                }
+            }
+            Expr::Constant(_) | Expr::Name(_) => {}
+        }
+    }
+
+    fn process_exprs(&mut self, exprs: &[Expr], ctx: BlockContext) {
+        for expr in exprs {
+            self.process_expr(expr, ctx);
+        }
+    }
+
+    fn process_comprehensions(&mut self, generators: &[Comprehension], ctx: BlockContext) {
+        for generator in generators {
+            self.process_expr(&generator.iter, ctx);
+            self.process_exprs(&generator.ifs, ctx);
+        }
+    }
+
+    /// Records the module loaded by an `importlib.import_module(...)` or `__import__(...)` call.
+    ///
+    /// Literal names become `Dynamic` imports of that module. Names built from a constant
+    /// prefix (`f"myapp.plugins.{name}"`, `"myapp.plugins." + name`) become `DynamicPackage`
+    /// imports of the package the prefix points into.
+    fn process_dynamic_import(&mut self, call: &ExprCall, ctx: BlockContext) {
+        let Some(function) = dotted_name(&call.func) else {
+            return;
+        };
+        let (name, package) = match function.as_str() {
+            "importlib.import_module" | "import_module" => (
+                call_argument(call, 0, "name"),
+                call_argument(call, 1, "package"),
+            ),
+            "__import__" | "builtins.__import__" => (call_argument(call, 0, "name"), None),
+            _ => return,
+        };
+
+        let (name, kind) = match name.and_then(dynamic_module_name) {
+            Some(DynamicModuleName::Literal(name)) => (name, ImportKind::Dynamic),
+            Some(DynamicModuleName::Prefix(prefix)) => match prefix_package(&prefix) {
+                Some(package) => (package, ImportKind::DynamicPackage),
+                None => return,
+            },
+            None => return,
+        };
+
+        let name = if name.starts_with('.') {
+            let anchor = package.and_then(|expr| self.anchor_package(expr));
+            match anchor.and_then(|anchor| resolve_dynamic_relative(&name, &anchor)) {
+                Some(name) => name,
+                None => return, // Relative name without a resolvable anchor package
+            }
+        } else {
+            name
+        };
+        if name.is_empty() {
+            return;
+        }
+
+        let module_id = resolve_module_identifier(&name);
+        self.record_import(module_id, ctx.dynamic(kind), call.range.start());
+    }
+
+    /// Evaluates the `package` argument of `import_module`: a literal or `__name__`.
+    fn anchor_package(&self, expr: &Expr) -> Option<String> {
+        match expr {
+            Expr::Constant(constant) => match &constant.value {
+                Constant::Str(package) => Some(package.clone()),
+                _ => None,
+            },
+            Expr::Name(name) if name.id.as_str() == "__name__" => {
+                self.current_module.map(|module| {
+                    module
+                        .strip_suffix(".__init__")
+                        .unwrap_or(module)
+                        .to_string()
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Processes the body and handlers of a `try` statement.
+    ///
+    /// When a handler swallows `ImportError`, the imports of the `try` body and
+    /// the fallback imports of that handler are optional alternatives
+    /// (`try: import ujson as json` / `except ImportError: import json`).
+    fn process_try(&mut self, body: &[Stmt], handlers: &[ExceptHandler], ctx: BlockContext) {
+        let inner = ctx.nested(ImportScope::Conditional);
+        let guarded = handlers.iter().any(is_import_error_fallback);
+
+        self.process_body(body, if guarded { inner.optional() } else { inner });
+        for handler in handlers {
+            let ExceptHandler::ExceptHandler(h) = handler;
+            if is_import_error_fallback(handler) {
+                self.process_body(&h.body, inner.optional());
+            } else {
+                self.process_body(&h.body, inner);
+            }
+        }
+    }
+
+    /// Processes the parameter and return annotations of a function definition.
+    fn process_signature(&mut self, args: &Arguments, returns: Option<&Expr>, ctx: BlockContext) {
+        let params = args
+            .posonlyargs
+            .iter()
+            .chain(&args.args)
+            .chain(&args.kwonlyargs)
+            .map(|arg| &arg.def)
+            .chain(args.vararg.as_deref())
+            .chain(args.kwarg.as_deref());
+
+        for param in params {
+            if let Some(annotation) = &param.annotation {
+                self.process_annotation(annotation, ctx, None);
+            }
+        }
+        if let Some(returns) = returns {
+            self.process_annotation(returns, ctx, None);
+        }
+    }
+
+    /// Collects dotted names referenced from string (forward-reference) annotations.
+    ///
+    /// `string_start` is the offset of the enclosing string while walking the parsed
+    /// contents of a string annotation, and `None` otherwise.
+    fn process_annotation(
+        &mut self,
+        annotation: &Expr,
+        ctx: BlockContext,
+        string_start: Option<TextSize>,
+    ) {
+        match annotation {
+            Expr::Constant(constant) => {
+                if let Constant::Str(text) = &constant.value
+                    && let Ok(Mod::Expression(parsed)) =
+                        parse(text.trim(), Mode::Expression, "<annotation>")
+                {
+                    self.process_annotation(&parsed.body, ctx, Some(constant.range.start()));
+                }
+            }
+            Expr::Name(_) | Expr::Attribute(_) if string_start.is_some() => {
+                if let Some(name) = dotted_name(annotation)
+                    && let Some(offset) = string_start
+                {
+                    self.annotation_refs.push((name, ctx, offset));
+                }
+            }
+            Expr::Subscript(subscript) => {
+                self.process_annotation(&subscript.value, ctx, string_start);
+                self.process_annotation(&subscript.slice, ctx, string_start);
+            }
+            Expr::BinOp(bin_op) => {
+                self.process_annotation(&bin_op.left, ctx, string_start);
+                self.process_annotation(&bin_op.right, ctx, string_start);
+            }
+            Expr::Tuple(tuple) => {
+                for elt in &tuple.elts {
+                    self.process_annotation(elt, ctx, string_start);
+                }
+            }
+            Expr::List(list) => {
+                for elt in &list.elts {
+                    self.process_annotation(elt, ctx, string_start);
                 }
-                // If no current_module context, we can't resolve relative imports, so skip
-            } else if let Some(module) = &import_from_stmt.module {
-                // Regular absolute import
-                let module_id = resolve_module_identifier(module);
-                modules.insert(module_id);
             }
+            _ => {}
+        }
+    }
+
+    /// Records type-only imports for fully qualified names used in string annotations.
+    ///
+    /// Names rooted in a local binding are skipped: the import that binds them
+    /// is already recorded.
+    fn resolve_annotation_refs(&mut self) {
+        for (name, ctx, offset) in std::mem::take(&mut self.annotation_refs) {
+            if self.bound_names.contains(extract_root_module(&name)) {
+                continue;
+            }
+            if let Some((module_name, _symbol)) = name.rsplit_once('.') {
+                let module_id = resolve_module_identifier(module_name);
+                self.record_import(module_id, ctx.annotation(), offset);
+            }
+        }
+    }
+
+    /// Returns the collected imports sorted by module path and the module's exports, with
+    /// the `from package import name` statements left to resolve.
+    fn finish(mut self) -> ModuleAnalysis {
+        self.resolve_annotation_refs();
+
+        let mut imports: Vec<ModuleImport> = self
+            .imports
+            .into_values()
+            .chain(self.dynamic_packages.into_values())
+            .collect();
+        imports.sort_by(|a, b| a.module.canonical_path.cmp(&b.module.canonical_path));
+        ModuleAnalysis {
+            imports,
+            exports: self.exports,
+            unresolved_imports: self.unresolved_imports,
+            syntax_errors: Vec::new(),
+            from_imports: self.from_imports,
         }
-        _ => {}
     }
 }
 
-/// Processes a collection of Python AST statements.
-fn process_body(
-    body: &[Stmt],
-    modules: &mut HashSet<ModuleIdentifier>,
-    current_module: Option<&str>,
-) {
-    for stmt in body {
-        process_stmt(stmt, modules, current_module);
+/// Returns true if an `if` test is a `TYPE_CHECKING` / `typing.TYPE_CHECKING` guard.
+fn is_type_checking_guard(test: &Expr) -> bool {
+    match test {
+        Expr::Name(name) => name.id.as_str() == "TYPE_CHECKING",
+        Expr::Attribute(attribute) => attribute.attr.as_str() == "TYPE_CHECKING",
+        _ => false,
     }
 }
 
-/// Extracts module dependencies from Python source code with context for resolution.
-pub fn extract_module_deps(
+/// Returns true if an `except` handler catches `ImportError` / `ModuleNotFoundError`
+/// without re-raising, i.e. the code keeps working when the import fails.
+fn is_import_error_fallback(handler: &ExceptHandler) -> bool {
+    let ExceptHandler::ExceptHandler(handler) = handler;
+    let catches_import_error = |expr: &Expr| {
+        dotted_name(expr).is_some_and(|name| {
+            let name = name.rsplit('.').next().unwrap_or(&name);
+            name == "ImportError" || name == "ModuleNotFoundError"
+        })
+    };
+    let catches = match handler.type_.as_deref() {
+        Some(Expr::Tuple(tuple)) => tuple.elts.iter().any(catches_import_error),
+        Some(expr) => catches_import_error(expr),
+        None => false,
+    };
+    let reraises = handler
+        .body
+        .iter()
+        .any(|stmt| matches!(stmt, Stmt::Raise(_)));
+
+    catches && !reraises
+}
+
+/// Module name passed to a dynamic import call, as far as it can be known statically.
+enum DynamicModuleName {
+    /// The full name is a literal (`"myapp.plugins.csv"`)
+    Literal(String),
+    /// Only a constant prefix is known (`f"myapp.plugins.{name}"`)
+    Prefix(String),
+}
+
+/// Returns the positional argument at `index`, or the keyword argument named `keyword`.
+pub(crate) fn call_argument<'e>(
+    call: &'e ExprCall,
+    index: usize,
+    keyword: &str,
+) -> Option<&'e Expr> {
+    call.args.get(index).or_else(|| {
+        call.keywords
+            .iter()
+            .find(|kw| kw.arg.as_deref() == Some(keyword))
+            .map(|kw| &kw.value)
+    })
+}
+
+/// Evaluates the module name of a dynamic import: string literals, f-strings and
+/// `+` concatenations starting with a string literal.
+fn dynamic_module_name(expr: &Expr) -> Option<DynamicModuleName> {
+    match expr {
+        Expr::Constant(constant) => match &constant.value {
+            Constant::Str(name) => Some(DynamicModuleName::Literal(name.clone())),
+            _ => None,
+        },
+        Expr::JoinedStr(joined) => {
+            let mut prefix = String::new();
+            for value in &joined.values {
+                match value {
+                    Expr::Constant(constant) => match &constant.value {
+                        Constant::Str(part) => prefix.push_str(part),
+                        _ => return None,
+                    },
+                    _ => return Some(DynamicModuleName::Prefix(prefix)),
+                }
+            }
+            Some(DynamicModuleName::Literal(prefix))
+        }
+        Expr::BinOp(bin_op) if bin_op.op == Operator::Add => {
+            match dynamic_module_name(&bin_op.left)? {
+                DynamicModuleName::Literal(left) => match dynamic_module_name(&bin_op.right) {
+                    Some(DynamicModuleName::Literal(right)) => {
+                        Some(DynamicModuleName::Literal(left + &right))
+                    }
+                    Some(DynamicModuleName::Prefix(right)) => {
+                        Some(DynamicModuleName::Prefix(left + &right))
+                    }
+                    None => Some(DynamicModuleName::Prefix(left)),
+                },
+                prefix => Some(prefix),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Returns the package a module name prefix points into (`"myapp.plugins."` and
+/// `"myapp.plugins.plugin_"` both give `myapp.plugins`), keeping leading dots.
+fn prefix_package(prefix: &str) -> Option<String> {
+    let relative = prefix.trim_start_matches('.');
+    let dots = &prefix[..prefix.len() - relative.len()];
+    let package = relative.rfind('.').map_or("", |end| &relative[..end]);
+    if dots.is_empty() && package.is_empty() {
+        return None; // No package to point into, e.g. `f"{name}"`
+    }
+    Some(format!("{}{}", dots, package))
+}
+
+/// Resolves a relative module name against the anchor package, as `importlib.import_module` does.
+fn resolve_dynamic_relative(name: &str, anchor: &str) -> Option<String> {
+    let relative = name.trim_start_matches('.');
+    let level = name.len() - relative.len();
+    let mut base = anchor;
+    for _ in 1..level {
+        base = base.rsplit_once('.')?.0;
+    }
+    if relative.is_empty() {
+        Some(base.to_string())
+    } else {
+        Some(format!("{}.{}", base, relative))
+    }
+}
+
+/// Returns true for UPPER_CASE names, which PEP 8 reserves for constants.
+fn is_constant_name(name: &str) -> bool {
+    name.chars().any(|c| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Converts a `Name` or `Attribute` chain to its dotted form (e.g. `a.b.c`).
+fn dotted_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Name(name) => Some(name.id.to_string()),
+        Expr::Attribute(attribute) => {
+            dotted_name(&attribute.value).map(|prefix| format!("{}.{}", prefix, attribute.attr))
+        }
+        _ => None,
+    }
+}
+
+/// Extracts imported modules from Python source code, with the scope and kind of each import.
+///
+/// Each module appears once; if it is imported in several places, runtime imports
+/// win over type-only ones and the most eager scope is kept. Results are sorted
+/// by module path for deterministic output.
+pub fn extract_module_imports(
     python_code: &str,
     current_module: Option<&str>,
-) -> Result<Vec<ModuleIdentifier>> {
-    let ast = parse(python_code, Mode::Module, "<string>")?;
-    let mut modules = HashSet::new();
+) -> Result<Vec<ModuleImport>> {
+    Ok(collect_imports(python_code, current_module, None)?.imports)
+}
 
-    match ast {
-        Mod::Module(module) => process_body(&module.body, &mut modules, current_module),
-        Mod::Interactive(interactive) => {
-            process_body(&interactive.body, &mut modules, current_module)
+/// Extracts imported modules like [`extract_module_imports`], resolving
+/// `from package import name` to `package.name` when it is one of `known_modules`.
+pub fn extract_module_imports_with_submodules(
+    python_code: &str,
+    current_module: Option<&str>,
+    known_modules: &HashSet<String>,
+) -> Result<Vec<ModuleImport>> {
+    Ok(collect_imports(python_code, current_module, Some(known_modules))?.imports)
+}
+
+/// Extracts imported modules like [`extract_module_imports_with_submodules`], along with
+/// the names the module re-exports from other modules.
+pub fn extract_module_imports_and_exports(
+    python_code: &str,
+    current_module: Option<&str>,
+    known_modules: &HashSet<String>,
+) -> Result<(Vec<ModuleImport>, ModuleExports)> {
+    let analysis = collect_imports(python_code, current_module, Some(known_modules))?;
+    Ok((analysis.imports, analysis.exports))
+}
+
+/// Analyzes a module like [`extract_module_imports_and_exports`], also returning the
+/// relative imports that could not be resolved.
+///
+/// Parse failures are returned as a [`SyntaxError`] with the position of the error.
+pub fn analyze_module_source(
+    python_code: &str,
+    current_module: Option<&str>,
+    known_modules: &HashSet<String>,
+) -> Result<ModuleAnalysis> {
+    collect_imports(python_code, current_module, Some(known_modules))
+}
+
+/// Analyzes a module like [`analyze_module_source`] without the modules of the project:
+/// its `from package import name` statements are left for [`ModuleAnalysis::resolve`].
+pub fn parse_module_source(
+    python_code: &str,
+    current_module: Option<&str>,
+) -> Result<ModuleAnalysis> {
+    let ast = parse(python_code, Mode::Module, "<string>").map_err(|e| {
+        let location = RandomLocator::new(python_code).locate(e.offset);
+        SyntaxError {
+            position: ImportPosition {
+                cell: None,
+                line: location.row.to_usize(),
+                column: location.column.to_usize(),
+            },
+            message: e.error.to_string(),
         }
+    })?;
+    let mut collector = ImportCollector::new(python_code, current_module);
+    let ctx = BlockContext {
+        scope: ImportScope::Module,
+        kind: ImportKind::Runtime,
+        optional: false,
+        annotation: false,
+    };
+
+    match ast {
+        Mod::Module(module) => collector.process_body(&module.body, ctx),
+        Mod::Interactive(interactive) => collector.process_body(&interactive.body, ctx),
         Mod::Expression(_) => {} // No statements to visit in expression mode
         Mod::FunctionType(_) => {} // No statements to visit in function type mode
     }
 
-    Ok(modules.into_iter().collect())
+    Ok(collector.finish())
+}
+
+fn collect_imports(
+    python_code: &str,
+    current_module: Option<&str>,
+    known_modules: Option<&HashSet<String>>,
+) -> Result<ModuleAnalysis> {
+    let mut analysis = parse_module_source(python_code, current_module)?;
+    analysis.resolve(known_modules);
+    Ok(analysis)
+}
+
+/// Extracts module dependencies from Python source code with context for resolution.
+pub fn extract_module_deps(
+    python_code: &str,
+    current_module: Option<&str>,
+) -> Result<Vec<ModuleIdentifier>> {
+    Ok(extract_module_imports(python_code, current_module)?
+        .into_iter()
+        .map(|import| import.module)
+        .collect())
 }
 
 #[cfg(test)]
@@ -191,6 +1360,77 @@ import os
         assert!(module_names.contains("os")); // import os
     }
 
+    #[test]
+    fn test_analyze_module_source_problems() {
+        let python_code =
+            "from . import sibling\nfrom .. import too_far\nfrom ....models import x\n";
+        let analysis =
+            analyze_module_source(python_code, Some("myapp.views"), &HashSet::new()).unwrap();
+        assert_eq!(
+            analysis.unresolved_imports,
+            vec![
+                UnresolvedImport {
+                    module: "..".to_string(),
+                    position: ImportPosition {
+                        cell: None,
+                        line: 2,
+                        column: 1
+                    },
+                    reason: UnresolvedReason::BeyondPackageRoot,
+                },
+                UnresolvedImport {
+                    module: "....models".to_string(),
+                    position: ImportPosition {
+                        cell: None,
+                        line: 3,
+                        column: 1
+                    },
+                    reason: UnresolvedReason::BeyondPackageRoot,
+                },
+            ]
+        );
+        assert_eq!(
+            analysis.unresolved_imports[1].to_string(),
+            "'....models' climbs above the package root"
+        );
+
+        // Relative imports in modules outside of any package cannot be resolved either
+        for context in [None, Some("script")] {
+            let analysis = analyze_module_source(
+                "import os\nfrom .helpers import x\n",
+                context,
+                &HashSet::new(),
+            )
+            .unwrap();
+            assert_eq!(analysis.unresolved_imports.len(), 1);
+            assert_eq!(
+                analysis.unresolved_imports[0].to_string(),
+                "'.helpers' is imported outside of a package"
+            );
+            assert_eq!(analysis.unresolved_imports[0].position.line, 2);
+        }
+
+        // Levels of ten or more dots
+        let deep = "a.b.c.d.e.f.g.h.i.j.k.module";
+        let analysis = analyze_module_source(
+            "from ..........target import x\n",
+            Some(deep),
+            &HashSet::new(),
+        )
+        .unwrap();
+        assert!(analysis.unresolved_imports.is_empty());
+        assert_eq!(analysis.imports[0].module.canonical_path, "a");
+        assert_eq!(
+            resolve_relative_import("target", 10, deep),
+            Some("a.b.target".to_string())
+        );
+
+        let error =
+            analyze_module_source("import os\ndef broken(:\n", None, &HashSet::new()).unwrap_err();
+        let syntax_error = error.downcast_ref::<SyntaxError>().unwrap();
+        assert_eq!(syntax_error.position.line, 2);
+    }
+
     #[test]
     fn test_relative_imports_without_context() {
         // Test that relative imports are ignored when no current module context is provided
@@ -225,6 +1465,33 @@ from .sub.module import func
         assert!(module_names.contains("package")); // All relative imports resolve to "package" root
     }
 
+    #[test]
+    fn test_external_namespace_packages() {
+        assert_eq!(external_package_root("numpy.testing.utils"), "numpy");
+        assert_eq!(
+            external_package_root("google.cloud.storage.blob"),
+            "google.cloud.storage"
+        );
+        assert_eq!(external_package_root("google.protobuf"), "google.protobuf");
+        assert_eq!(external_package_root("google"), "google");
+        assert_eq!(
+            external_package_root("azure.storage.blob"),
+            "azure.storage.blob"
+        );
+
+        let python_code = "from google.cloud import storage\nimport google.cloud.bigquery\nimport zope.interface\n";
+        let modules = extract_module_deps(python_code, None).unwrap();
+        let names: Vec<&str> = modules.iter().map(|m| m.canonical_path.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "google.cloud.bigquery",
+                "google.cloud.storage",
+                "zope.interface"
+            ]
+        );
+    }
+
     #[test]
     fn test_simple_import() {
         let python_code = "import os";
@@ -388,6 +1655,436 @@ import custom_module
         assert_eq!(custom_module.origin, ModuleOrigin::External);
     }
 
+    #[test]
+    fn test_nested_imports_collected() {
+        let python_code = r#"
+import os
+
+def load():
+    import json
+    from collections import OrderedDict
+
+class Config:
+    import yaml
+
+if os.name == "nt":
+    import winreg
+
+try:
+    import ujson
+except ImportError:
+    pass
+
+with open("f") as f:
+    import csv
+
+match os.name:
+    case "posix":
+        import posix
+"#;
+        let imports = extract_module_imports(python_code, None).unwrap();
+        let scopes: HashMap<String, ImportScope> = imports
+            .iter()
+            .map(|i| (i.module.canonical_path.clone(), i.scope))
+            .collect();
+
+        assert_eq!(scopes.len(), 8);
+        assert_eq!(scopes["os"], ImportScope::Module);
+        assert_eq!(scopes["json"], ImportScope::Function);
+        assert_eq!(scopes["collections"], ImportScope::Function);
+        assert_eq!(scopes["yaml"], ImportScope::Class);
+        assert_eq!(scopes["winreg"], ImportScope::Conditional);
+        assert_eq!(scopes["ujson"], ImportScope::Conditional);
+        assert_eq!(scopes["csv"], ImportScope::Conditional);
+        assert_eq!(scopes["posix"], ImportScope::Conditional);
+    }
+
+    #[test]
+    fn test_nested_scope_precedence() {
+        let python_code = r#"
+class Service:
+    def run(self):
+        import requests
+
+if True:
+    def helper():
+        import numpy
+
+def lazy():
+    import os
+
+import os
+"#;
+        let imports = extract_module_imports(python_code, None).unwrap();
+        let scopes: HashMap<String, ImportScope> = imports
+            .iter()
+            .map(|i| (i.module.canonical_path.clone(), i.scope))
+            .collect();
+
+        // The most deferred enclosing scope wins when nesting
+        assert_eq!(scopes["requests"], ImportScope::Function);
+        assert_eq!(scopes["numpy"], ImportScope::Function);
+        // The most eager occurrence wins for repeated imports
+        assert_eq!(scopes["os"], ImportScope::Module);
+        assert!(scopes["os"].is_import_time());
+        assert!(!scopes["numpy"].is_import_time());
+    }
+
+    #[test]
+    fn test_type_checking_imports_are_type_only() {
+        let python_code = r#"
+from typing import TYPE_CHECKING
+import typing
+
+if TYPE_CHECKING:
+    from decimal import Decimal
+    import json
+else:
+    import pickle
+
+if typing.TYPE_CHECKING:
+    import fractions
+
+import json
+"#;
+        let imports = extract_module_imports(python_code, None).unwrap();
+        let kinds: HashMap<String, ImportKind> = imports
+            .iter()
+            .map(|i| (i.module.canonical_path.clone(), i.kind))
+            .collect();
+
+        assert_eq!(kinds["decimal"], ImportKind::TypeOnly);
+        assert_eq!(kinds["fractions"], ImportKind::TypeOnly);
+        assert_eq!(kinds["pickle"], ImportKind::Runtime);
+        assert_eq!(kinds["typing"], ImportKind::Runtime);
+        // A runtime import elsewhere in the module wins over the type-only one
+        assert_eq!(kinds["json"], ImportKind::Runtime);
+    }
+
+    #[test]
+    fn test_string_annotations_are_type_only() {
+        let python_code = r#"
+from typing import Optional
+import numpy as np
+
+def handle(order: "billing.models.Order", arr: "np.ndarray") -> Optional["shipping.Parcel"]:
+    pass
+
+class Invoice:
+    total: "decimal.Decimal"
+    parent: "Invoice"
+"#;
+        let imports = extract_module_imports(python_code, None).unwrap();
+        let kinds: HashMap<String, ImportKind> = imports
+            .iter()
+            .map(|i| (i.module.canonical_path.clone(), i.kind))
+            .collect();
+
+        assert_eq!(kinds.len(), 5);
+        assert_eq!(kinds["billing"], ImportKind::TypeOnly);
+        assert_eq!(kinds["shipping"], ImportKind::TypeOnly);
+        assert_eq!(kinds["decimal"], ImportKind::TypeOnly);
+        assert_eq!(kinds["numpy"], ImportKind::Runtime);
+        assert_eq!(kinds["typing"], ImportKind::Runtime);
+        // Names rooted in local bindings (aliases, classes) are not modules
+        assert!(!kinds.contains_key("np"));
+        assert!(!kinds.contains_key("Invoice"));
+    }
+
+    #[test]
+    fn test_string_annotations_keep_import_location() {
+        let python_code = r#"
+from typing import TYPE_CHECKING
+
+if TYPE_CHECKING:
+    from myapp.models import User
+
+class Service:
+    def get(self) -> "myapp.models.User":
+        pass
+"#;
+        let imports = extract_module_imports(python_code, None).unwrap();
+        let models = imports
+            .iter()
+            .find(|i| i.module.canonical_path == "myapp")
+            .unwrap();
+
+        // The import statement is reported rather than the annotation in the class
+        assert_eq!(models.kind, ImportKind::TypeOnly);
+        assert_eq!(models.scope, ImportScope::Conditional);
+        assert_eq!(models.position.line, 5);
+        assert!(!models.annotation);
+    }
+
+    #[test]
+    fn test_optional_imports() {
+        let python_code = r#"
+try:
+    import ujson as json
+except ImportError:
+    import simplejson as json
+
+try:
+    import yaml
+except (ValueError, ModuleNotFoundError):
+    yaml = None
+
+try:
+    import requests
+except ImportError:
+    raise RuntimeError("requests is required")
+
+try:
+    import toml
+except Exception:
+    toml = None
+
+try:
+    import numpy
+except ImportError:
+    numpy = None
+
+import numpy.linalg
+"#;
+        let imports = extract_module_imports(python_code, None).unwrap();
+        let optional: HashMap<String, bool> = imports
+            .iter()
+            .map(|i| (i.module.canonical_path.clone(), i.optional))
+            .collect();
+
+        assert!(optional["ujson"]);
+        assert!(optional["simplejson"]);
+        assert!(optional["yaml"]);
+        // Re-raising handlers and handlers for other exceptions do not make imports optional
+        assert!(!optional["requests"]);
+        assert!(!optional["toml"]);
+        // An unguarded import elsewhere makes the module required
+        assert!(!optional["numpy"]);
+    }
+
+    #[test]
+    fn test_import_positions() {
+        let python_code = r#"import os
+
+def handler(order: "billing.models.Order"):
+    from collections import OrderedDict
+    import os
+"#;
+        let imports = extract_module_imports(python_code, None).unwrap();
+        let positions: HashMap<String, ImportPosition> = imports
+            .iter()
+            .map(|i| (i.module.canonical_path.clone(), i.position))
+            .collect();
+
+        assert_eq!(
+            positions["os"],
+            ImportPosition {
+                cell: None,
+                line: 1,
+                column: 1
+            }
+        );
+        assert_eq!(
+            positions["collections"],
+            ImportPosition {
+                cell: None,
+                line: 4,
+                column: 5
+            }
+        );
+        // String annotations point at the annotation itself
+        assert_eq!(
+            positions["billing"],
+            ImportPosition {
+                cell: None,
+                line: 3,
+                column: 20
+            }
+        );
+    }
+
+    #[test]
+    fn test_dynamic_imports() {
+        let python_code = r#"
+import importlib
+from importlib import import_module
+
+def load(name):
+    importlib.import_module("myapp.plugins.csv_export")
+    __import__("yaml")
+    import_module(".jobs", package="myapp.tasks")
+    return import_module(f"myapp.plugins.{name}")
+
+handlers = [import_module("backends." + name) for name in ("a", "b")]
+import_module(f"{name}")
+"#;
+        let imports = extract_module_imports(python_code, Some("myapp.loader")).unwrap();
+        let kinds: Vec<(String, ImportKind)> = imports
+            .iter()
+            .map(|i| (i.module.canonical_path.clone(), i.kind))
+            .collect();
+
+        // Without pyproject.toml every module is external and reduced to its root
+        assert!(kinds.contains(&("myapp".to_string(), ImportKind::Dynamic)));
+        assert!(kinds.contains(&("yaml".to_string(), ImportKind::Dynamic)));
+        assert!(kinds.contains(&("myapp".to_string(), ImportKind::DynamicPackage)));
+        assert!(kinds.contains(&("backends".to_string(), ImportKind::DynamicPackage)));
+        assert!(kinds.contains(&("importlib".to_string(), ImportKind::Runtime)));
+        // A name without a constant package prefix cannot be resolved
+        assert_eq!(kinds.len(), 5);
+        let yaml = imports
+            .iter()
+            .find(|i| i.module.canonical_path == "yaml")
+            .unwrap();
+        assert_eq!(yaml.scope, ImportScope::Function);
+    }
+
+    #[test]
+    fn test_dynamic_module_names() {
+        let name = |code: &str| {
+            let Ok(Mod::Expression(parsed)) = parse(code, Mode::Expression, "<test>") else {
+                panic!("invalid expression");
+            };
+            match dynamic_module_name(&parsed.body) {
+                Some(DynamicModuleName::Literal(name)) => format!("literal {}", name),
+                Some(DynamicModuleName::Prefix(prefix)) => {
+                    format!("package {:?}", prefix_package(&prefix))
+                }
+                None => "none".to_string(),
+            }
+        };
+
+        assert_eq!(name(r#""myapp.plugins.csv""#), "literal myapp.plugins.csv");
+        assert_eq!(name(r#""myapp." + "plugins""#), "literal myapp.plugins");
+        assert_eq!(
+            name(r#"f"myapp.plugins.{name}""#),
+            r#"package Some("myapp.plugins")"#
+        );
+        assert_eq!(
+            name(r#"f"myapp.plugins.plugin_{name}""#),
+            r#"package Some("myapp.plugins")"#
+        );
+        assert_eq!(
+            name(r#""myapp.plugins." + name"#),
+            r#"package Some("myapp.plugins")"#
+        );
+        assert_eq!(name(r#"f".{name}""#), r#"package Some(".")"#);
+        assert_eq!(name(r#"f"{name}""#), "package None");
+        assert_eq!(name("name"), "none");
+
+        assert_eq!(
+            resolve_dynamic_relative(".jobs", "myapp.tasks"),
+            Some("myapp.tasks.jobs".to_string())
+        );
+        assert_eq!(
+            resolve_dynamic_relative("..jobs", "myapp.tasks"),
+            Some("myapp.jobs".to_string())
+        );
+        assert_eq!(
+            resolve_dynamic_relative(".", "myapp"),
+            Some("myapp".to_string())
+        );
+        assert_eq!(resolve_dynamic_relative("...jobs", "myapp.tasks"), None);
+    }
+
+    #[test]
+    fn test_module_exports() {
+        let python_code = r#"
+from .user import User, Role as UserRole
+from .billing import *
+from myapp.core import settings
+
+__all__ = ["User", "UserRole"]
+__all__ += ("Invoice",)
+
+def helper():
+    from .internal import Hidden
+"#;
+        let known_modules: HashSet<String> = ["myapp.core.settings".to_string()].into();
+        let (imports, exports) = extract_module_imports_and_exports(
+            python_code,
+            Some("myapp.models.__init__"),
+            &known_modules,
+        )
+        .unwrap();
+
+        assert_eq!(
+            exports.all,
+            Some(vec![
+                "User".to_string(),
+                "UserRole".to_string(),
+                "Invoice".to_string()
+            ])
+        );
+        assert_eq!(
+            exports.reexports["User"],
+            ("myapp.models.user".to_string(), "User".to_string())
+        );
+        assert_eq!(
+            exports.reexports["UserRole"],
+            ("myapp.models.user".to_string(), "Role".to_string())
+        );
+        assert_eq!(
+            exports.star_sources,
+            vec!["myapp.models.billing".to_string()]
+        );
+        // Submodules and nested imports are not re-exports
+        assert_eq!(exports.reexports.len(), 2);
+
+        // Imported names are recorded on the import of the module they come from
+        let names: HashMap<String, Vec<String>> = imports
+            .iter()
+            .map(|i| (i.module.canonical_path.clone(), i.names.clone()))
+            .collect();
+        assert_eq!(names["myapp"], vec!["*", "Hidden", "Role", "User"]);
+    }
+
+    #[test]
+    fn test_module_symbols() {
+        let python_code = r#"
+import os
+
+MAX_RETRIES = 3
+TIMEOUT: float = 2.5
+logger = None
+
+def charge_card(card):
+    LOCAL = 1
+
+async def refund(payment):
+    pass
+
+class Card:
+    BRAND = "visa"
+
+def charge_card(card, amount):
+    pass
+
+if os.name == "nt":
+    def windows_only():
+        pass
+"#;
+        let (_, exports) =
+            extract_module_imports_and_exports(python_code, None, &HashSet::new()).unwrap();
+        let symbols: Vec<(&str, SymbolKind, usize)> = exports
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind, s.position.line))
+            .collect();
+
+        // Only top-level definitions count, the first definition of a name wins
+        assert_eq!(
+            symbols,
+            vec![
+                ("MAX_RETRIES", SymbolKind::Constant, 4),
+                ("TIMEOUT", SymbolKind::Constant, 5),
+                ("charge_card", SymbolKind::Function, 8),
+                ("refund", SymbolKind::Function, 11),
+                ("Card", SymbolKind::Class, 14),
+            ]
+        );
+    }
+
     #[test]
     fn test_root_module_extraction() {
         let python_code = r#"
@@ -1,5 +1,14 @@
+pub mod cache;
+pub mod config;
 pub mod crawler;
+pub mod declared;
+pub mod diagnostics;
+pub mod encoding;
 pub mod graph;
 pub mod imports;
+pub mod lockfile;
+pub mod notebook;
 pub mod pyproject;
+pub mod requirement;
 pub mod tools;
+pub mod watch;
@@ -0,0 +1,249 @@
+use crate::pyproject::normalize_dependency_name;
+use crate::requirement::Requirement;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Supported lockfiles, in order of precedence when a project has several.
+pub const LOCKFILES: [&str; 3] = ["poetry.lock", "uv.lock", "pdm.lock"];
+
+/// A third-party package pinned by a lockfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    /// Normalized distribution name (e.g. "python-dateutil")
+    pub name: String,
+    /// Resolved version
+    pub version: String,
+    /// Normalized names of the packages it requires
+    pub dependencies: Vec<String>,
+}
+
+/// The resolved third-party dependency tree of a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    /// File name of the lockfile (e.g. "poetry.lock")
+    pub path: String,
+    /// Locked packages, sorted by name
+    pub packages: Vec<LockedPackage>,
+}
+
+/// Returns the file name of the lockfile of a project, if it has one.
+pub fn find_lockfile(project_root: &Path) -> Option<&'static str> {
+    LOCKFILES
+        .into_iter()
+        .find(|name| project_root.join(name).is_file())
+}
+
+impl Lockfile {
+    /// Reads a `poetry.lock`, `uv.lock` or `pdm.lock` of the project.
+    ///
+    /// Requirements of extras are skipped (poetry's `[package.extras]`, uv's optional
+    /// dependencies) unless the lockfile resolves them, as pdm does with one entry per set
+    /// of extras. The project itself (uv's editable or virtual root) is skipped.
+    pub fn load(project_root: &Path, file_name: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(project_root.join(file_name))?;
+        let toml: toml::Value = toml::from_str(&content)?;
+
+        // Entries of the same package (pdm locks one per set of extras) are merged
+        let mut packages: BTreeMap<String, LockedPackage> = BTreeMap::new();
+        for package in toml
+            .get("package")
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+        {
+            let Some(name) = package.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            let source = package.get("source");
+            if source.is_some_and(|s| s.get("editable").is_some() || s.get("virtual").is_some()) {
+                continue;
+            }
+
+            let entry = packages
+                .entry(normalize_dependency_name(name))
+                .or_insert_with(|| LockedPackage {
+                    name: normalize_dependency_name(name),
+                    version: String::new(),
+                    dependencies: Vec::new(),
+                });
+            if let Some(version) = package.get("version").and_then(|v| v.as_str()) {
+                entry.version = version.to_string();
+            }
+            entry.dependencies.extend(locked_dependencies(package));
+        }
+
+        let mut packages: Vec<LockedPackage> = packages.into_values().collect();
+        for package in &mut packages {
+            // pdm entries with extras require the package itself
+            let name = package.name.clone();
+            package
+                .dependencies
+                .retain(|dependency| *dependency != name);
+            package.dependencies.sort();
+            package.dependencies.dedup();
+        }
+        Ok(Self {
+            path: file_name.to_string(),
+            packages,
+        })
+    }
+}
+
+/// Reads the requirements of a locked package: a `[package.dependencies]` table of
+/// names (poetry), an array of `{ name = ... }` tables (uv) or of PEP 508 strings (pdm).
+fn locked_dependencies(package: &toml::Value) -> Vec<String> {
+    match package.get("dependencies") {
+        Some(toml::Value::Table(table)) => table
+            .keys()
+            .map(|name| normalize_dependency_name(name))
+            .collect(),
+        Some(toml::Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                toml::Value::String(requirement) => {
+                    Requirement::parse(requirement).map(|requirement| requirement.name)
+                }
+                toml::Value::Table(table) => table
+                    .get("name")
+                    .and_then(|n| n.as_str())
+                    .map(str::to_string),
+                _ => None,
+            })
+            .map(|name| normalize_dependency_name(&name))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn tree(lockfile: &Lockfile) -> Vec<(&str, &str, Vec<&str>)> {
+        lockfile
+            .packages
+            .iter()
+            .map(|package| {
+                (
+                    package.name.as_str(),
+                    package.version.as_str(),
+                    package.dependencies.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_load_lockfiles() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        assert_eq!(find_lockfile(root), None);
+
+        fs::write(
+            root.join("poetry.lock"),
+            r#"
+[[package]]
+name = "pandas"
+version = "2.2.2"
+
+[package.dependencies]
+numpy = ">=1.26.0"
+python-dateutil = ">=2.8.2"
+
+[package.extras]
+excel = ["openpyxl (>=3.1.0)"]
+
+[[package]]
+name = "python-dateutil"
+version = "2.9.0"
+
+[package.dependencies]
+six = ">=1.5"
+
+[[package]]
+name = "six"
+version = "1.16.0"
+"#,
+        )
+        .unwrap();
+        assert_eq!(find_lockfile(root), Some("poetry.lock"));
+        let lockfile = Lockfile::load(root, "poetry.lock").unwrap();
+        assert_eq!(
+            tree(&lockfile),
+            vec![
+                ("pandas", "2.2.2", vec!["numpy", "python-dateutil"]),
+                ("python-dateutil", "2.9.0", vec!["six"]),
+                ("six", "1.16.0", vec![]),
+            ]
+        );
+
+        fs::write(
+            root.join("uv.lock"),
+            r#"
+version = 1
+
+[[package]]
+name = "demo"
+version = "0.1.0"
+source = { editable = "." }
+dependencies = [{ name = "requests" }]
+
+[[package]]
+name = "requests"
+version = "2.32.3"
+source = { registry = "https://pypi.org/simple" }
+dependencies = [
+    { name = "certifi" },
+    { name = "urllib3" },
+]
+
+[package.optional-dependencies]
+socks = [{ name = "pysocks" }]
+"#,
+        )
+        .unwrap();
+        let lockfile = Lockfile::load(root, "uv.lock").unwrap();
+        assert_eq!(
+            tree(&lockfile),
+            vec![("requests", "2.32.3", vec!["certifi", "urllib3"])]
+        );
+
+        fs::write(
+            root.join("pdm.lock"),
+            r#"
+[[package]]
+name = "Flask"
+version = "3.0.3"
+dependencies = [
+    "Werkzeug>=3.0.0",
+    "itsdangerous>=2.1.2",
+]
+
+[[package]]
+name = "flask"
+version = "3.0.3"
+extras = ["async"]
+dependencies = [
+    "asgiref>=3.2; extra == \"async\"",
+    "flask==3.0.3",
+]
+"#,
+        )
+        .unwrap();
+        let lockfile = Lockfile::load(root, "pdm.lock").unwrap();
+        assert_eq!(
+            tree(&lockfile),
+            vec![(
+                "flask",
+                "3.0.3",
+                vec!["asgiref", "itsdangerous", "werkzeug"]
+            )]
+        );
+
+        fs::write(root.join("poetry.lock"), "[[package]\n").unwrap();
+        assert!(Lockfile::load(root, "poetry.lock").is_err());
+    }
+}
@@ -1,18 +1,34 @@
-use clap::{Parser, Subcommand};
-use pydep_mapper::crawler::build_directory_dependency_graph;
+use anyhow::Context;
+use clap::{Parser, Subcommand, ValueEnum};
+use pydep_mapper::config::{self, Config};
+use pydep_mapper::crawler::{
+    DiscoveryOptions, GraphOptions, build_directory_dependency_graph_with_options,
+};
+use pydep_mapper::declared::SourceGroups;
+use pydep_mapper::diagnostics::DiagnosticKind;
+use pydep_mapper::diagnostics::formatters as diagnostics_formatters;
+use pydep_mapper::graph::{DependencyEdge, DependencyGraph};
+use pydep_mapper::imports::ModuleOrigin;
+use pydep_mapper::pyproject::{self, PackageInfo};
 use pydep_mapper::tools::agent::print_agent_documentation;
 use pydep_mapper::tools::changeset::{
-    ChangesetScope, analyze_changeset, formatters as changeset_formatters,
+    ChangesetScope, RiskThresholds, analyze_changeset_with_thresholds,
+    formatters as changeset_formatters,
 };
-use pydep_mapper::tools::cycles::{detect_cycles, formatters as cycle_formatters};
+use pydep_mapper::tools::cycles::{Cycle, detect_cycles, formatters as cycle_formatters};
 use pydep_mapper::tools::dependencies::{analyze_dependencies, formatters as dep_formatters};
-use pydep_mapper::tools::diagnose::{analyze_diagnose, formatters as diagnose_formatters};
+use pydep_mapper::tools::diagnose::{
+    DiagnoseSettings, analyze_diagnose_with_settings, formatters as diagnose_formatters,
+};
 use pydep_mapper::tools::external::{
-    analyze_external_dependencies, formatters as external_formatters,
+    analyze_external_dependencies_with_groups, formatters as external_formatters,
 };
 use pydep_mapper::tools::impact::{analyze_impact, formatters};
 use pydep_mapper::tools::instability::{analyze_instability, formatters as instability_formatters};
 use pydep_mapper::tools::pressure::{analyze_pressure, formatters as pressure_formatters};
+use pydep_mapper::tools::usages::{analyze_usages, formatters as usages_formatters};
+use pydep_mapper::watch::{self, CycleChanges, LiveGraph, Update};
+use serde::Serialize;
 use std::path::Path;
 
 #[derive(Parser)]
@@ -23,10 +39,58 @@ struct Args {
     #[arg(long, default_value = ".")]
     root: String,
 
+    /// Route imports of names re-exported by a package `__init__.py` to the modules defining them
+    #[arg(long, global = true)]
+    trace_reexports: bool,
+
+    /// Output format: human-readable text (default), or JSON with the result and diagnostics
+    #[arg(long, global = true, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Fail if any file or import could not be analyzed
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Maximum number of files parsed in parallel (defaults to the number of cores)
+    #[arg(long, global = true, value_parser = clap::value_parser!(u16).range(1..))]
+    jobs: Option<u16>,
+
+    /// Parse every file instead of reusing the analyses cached in .pydep-cache/
+    #[arg(long, global = true)]
+    no_cache: bool,
+
+    /// Only analyze files matching this glob, relative to the root (repeatable)
+    #[arg(long, global = true, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files and directories matching this glob, relative to the root (repeatable)
+    #[arg(long, global = true, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Also analyze directories named `tests`
+    #[arg(long, global = true)]
+    include_tests: bool,
+
+    /// Analyze files ignored by .gitignore and .ignore files
+    #[arg(long, global = true)]
+    no_ignore: bool,
+
+    /// Keep running: update the graph when files under the root change and print what
+    /// changed in the output of the command
+    #[arg(long, global = true)]
+    watch: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Output format of the analysis commands
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Analyze the entire codebase and display dependency graph
@@ -36,6 +100,9 @@ enum Commands {
     Impact {
         /// Module name to analyze for impact
         module_name: String,
+        /// Include modules that only import the target for type checking
+        #[arg(long)]
+        include_type_only: bool,
     },
 
     /// Show all dependencies of the specified module
@@ -44,6 +111,15 @@ enum Commands {
         module_name: String,
     },
 
+    /// Show all modules importing the specified top-level function, class or constant
+    Usages {
+        /// Symbol to analyze, as module path and name (e.g. myapp.billing.charge_card)
+        symbol_name: String,
+        /// Include modules that only import the symbol for type checking
+        #[arg(long)]
+        include_type_only: bool,
+    },
+
     /// Analyze changeset impact and dependencies for safe refactoring
     Changeset {
         /// Module name to analyze for changeset
@@ -51,10 +127,17 @@ enum Commands {
         /// Scope of analysis: affected, dependencies, or both (default: both)
         #[arg(long, default_value = "both")]
         scope: String,
+        /// Include type-only imports (`if TYPE_CHECKING:` blocks and string annotations)
+        #[arg(long)]
+        include_type_only: bool,
     },
 
     /// Detect and report circular dependencies in the codebase
-    Cycles,
+    Cycles {
+        /// Also report cycles that only exist through type-only imports
+        #[arg(long)]
+        include_type_only: bool,
+    },
 
     /// Comprehensive health report of the codebase from a dependency perspective
     Diagnose,
@@ -66,7 +149,12 @@ enum Commands {
     Instability,
 
     /// Analyze external dependencies across the codebase with frequency analysis
-    External,
+    External {
+        /// Map source paths to the dependency group of their code, as PATTERN=GROUP
+        /// (e.g. 'scripts/**=dev'); test paths like tests/** default to the test group
+        #[arg(long = "group-path", value_name = "PATTERN=GROUP")]
+        group_paths: Vec<String>,
+    },
 
     /// Display command documentation optimized for agentic coding workflows
     Agent,
@@ -76,180 +164,477 @@ fn main() {
     let args = Args::parse();
     let dir_path = Path::new(&args.root);
 
+    if let Commands::Agent = args.command {
+        print_agent_documentation();
+        return;
+    }
+
+    // Command-line options take precedence over the project configuration
+    let config = match Config::load(dir_path) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
+    };
+
     // Initialize the pyproject parser once
-    pydep_mapper::pyproject::init(dir_path);
+    init_parser(dir_path, &config);
 
-    match args.command {
-        Commands::Analyze => match build_directory_dependency_graph(dir_path) {
-            Ok(graph) => {
-                println!("Analyzed directory: {}", args.root);
-                println!("{}", graph);
-            }
-            Err(e) => {
-                eprintln!("Error processing directory '{}': {}", args.root, e);
-            }
-        },
-        Commands::Impact { module_name } => match run_impact_analysis(dir_path, &module_name) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Error running impact analysis: {}", e);
-            }
+    if args.watch {
+        if let Err(e) = run_watch(&args, config) {
+            eprintln!("Error watching directory '{}': {:#}", args.root, e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let options = graph_options(&args, &config);
+    let graph = match build_directory_dependency_graph_with_options(dir_path, &options) {
+        Ok(graph) => graph,
+        Err(e) => {
+            eprintln!("Error processing directory '{}': {}", args.root, e);
+            return;
+        }
+    };
+    let format = output_format(&args, &config);
+
+    match run_command(&graph, &args, &config, format) {
+        Ok(output) => print!("{}", output),
+        Err(e) => eprintln!("{:#}", e),
+    }
+
+    // JSON output carries the diagnostics with the result
+    let diagnostics = graph.diagnostics();
+    if format == OutputFormat::Text {
+        print!("{}", diagnostics_formatters::format_text(diagnostics));
+    }
+    // The graph is complete when only the parse cache could not be written
+    let analysis_errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.kind != DiagnosticKind::CacheWriteError)
+        .count();
+    if args.strict && analysis_errors > 0 {
+        eprintln!(
+            "Error: {} files or imports could not be analyzed (--strict)",
+            analysis_errors
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Initializes the pyproject parser, searching the configured source roots for packages.
+fn init_parser(dir_path: &Path, config: &Config) {
+    if config.source_roots.is_empty() {
+        pyproject::init(dir_path);
+    } else {
+        pyproject::init_with_source_roots(dir_path, &config.source_roots);
+    }
+}
+
+/// Options of the graph, from the command line and the configuration.
+fn graph_options(args: &Args, config: &Config) -> GraphOptions {
+    GraphOptions {
+        trace_reexports: args.trace_reexports,
+        // Symbol nodes are only needed to find the users of a symbol
+        symbols: matches!(args.command, Commands::Usages { .. }),
+        jobs: args.jobs.map(usize::from),
+        cache: !args.no_cache,
+        discovery: DiscoveryOptions {
+            include: [config.include.as_slice(), &args.include].concat(),
+            exclude: [config.exclude.as_slice(), &args.exclude].concat(),
+            exclude_tests: !(args.include_tests || config.include_tests),
+            respect_ignore_files: !(args.no_ignore || config.no_ignore),
         },
+    }
+}
+
+fn output_format(args: &Args, config: &Config) -> OutputFormat {
+    args.format.unwrap_or(match config.format {
+        Some(config::OutputFormat::Json) => OutputFormat::Json,
+        Some(config::OutputFormat::Text) | None => OutputFormat::Text,
+    })
+}
+
+/// Runs the analysis of the command and renders its result.
+fn run_command(
+    graph: &DependencyGraph,
+    args: &Args,
+    config: &Config,
+    format: OutputFormat,
+) -> anyhow::Result<String> {
+    match &args.command {
+        Commands::Analyze => run_analyze(graph, format, &args.root)
+            .with_context(|| format!("Error processing directory '{}'", args.root)),
+        Commands::Impact {
+            module_name,
+            include_type_only,
+        } => run_impact_analysis(graph, format, module_name, *include_type_only)
+            .context("Error running impact analysis"),
         Commands::Dependencies { module_name } => {
-            match run_dependencies_analysis(dir_path, &module_name) {
-                Ok(()) => {}
-                Err(e) => {
-                    eprintln!("Error running dependencies analysis: {}", e);
-                }
-            }
+            run_dependencies_analysis(graph, format, module_name)
+                .context("Error running dependencies analysis")
+        }
+        Commands::Usages {
+            symbol_name,
+            include_type_only,
+        } => run_usages_analysis(graph, format, symbol_name, *include_type_only)
+            .context("Error running usages analysis"),
+        Commands::Changeset {
+            module_name,
+            scope,
+            include_type_only,
+        } => run_changeset_analysis(
+            graph,
+            format,
+            module_name,
+            scope,
+            *include_type_only,
+            &config.changeset.risk_thresholds,
+        )
+        .context("Error running changeset analysis"),
+        Commands::Cycles { include_type_only } => {
+            run_cycles_analysis(graph, format, *include_type_only)
+                .context("Error running cycles analysis")
+        }
+        Commands::Diagnose => run_diagnose_analysis(graph, format, &config.diagnose)
+            .context("Error running diagnose analysis"),
+        Commands::Pressure => {
+            run_pressure_analysis(graph, format).context("Error running pressure analysis")
         }
-        Commands::Changeset { module_name, scope } => {
-            match run_changeset_analysis(dir_path, &module_name, &scope) {
-                Ok(()) => {}
+        Commands::Instability => {
+            run_instability_analysis(graph, format).context("Error running instability analysis")
+        }
+        Commands::External { group_paths } => {
+            // Mappings given on the command line are matched first
+            let group_paths = [group_paths.clone(), config.group_mappings()].concat();
+            run_external_analysis(graph, format, &group_paths)
+                .context("Error running external analysis")
+        }
+        Commands::Agent => Ok(String::new()), // Handled before building the graph
+    }
+}
+
+/// Output of a run in watch mode, compared with the next run.
+struct WatchedOutput {
+    /// Rendered result, with the diagnostics in text format
+    output: String,
+    /// Cycles found by the `cycles` command, reported as introduced or resolved
+    cycles: Option<Vec<Cycle>>,
+}
+
+fn render_watched(graph: &DependencyGraph, args: &Args, config: &Config) -> WatchedOutput {
+    let format = output_format(args, config);
+    let mut output =
+        run_command(graph, args, config, format).unwrap_or_else(|e| format!("{:#}\n", e));
+    if format == OutputFormat::Text {
+        output.push_str(&diagnostics_formatters::format_text(graph.diagnostics()));
+    }
+    let cycles = match args.command {
+        Commands::Cycles { include_type_only } => detect_cycles(graph, include_type_only)
+            .ok()
+            .map(|result| result.cycles),
+        _ => None,
+    };
+    WatchedOutput { output, cycles }
+}
+
+/// Keeps the graph up to date with the files under the root, printing what changed in the
+/// output of the command after every change. Text output is printed as a diff against the
+/// previous run, JSON output in full.
+fn run_watch(args: &Args, mut config: Config) -> anyhow::Result<()> {
+    let dir_path = Path::new(&args.root);
+    let mut live = LiveGraph::build(dir_path, graph_options(args, &config))?;
+    let mut previous = render_watched(live.graph(), args, &config);
+    print!("{}", previous.output);
+    eprintln!("Watching '{}' for changes (Ctrl-C to stop)", args.root);
+
+    // Set when an update failed, so the graph may be out of date or half patched
+    let mut stale = false;
+    watch::watch(dir_path, |changed| {
+        let project_changed = changed.iter().any(|path| watch::is_project_file(path));
+        if project_changed {
+            // The configuration, packages or ignored files may have changed
+            match Config::load(dir_path) {
+                Ok(reloaded) => config = reloaded,
                 Err(e) => {
-                    eprintln!("Error running changeset analysis: {}", e);
+                    eprintln!("Error: {:#}", e);
+                    return Ok(());
                 }
             }
+            init_parser(dir_path, &config);
         }
-        Commands::Cycles => match run_cycles_analysis(dir_path) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Error running cycles analysis: {}", e);
+        let updated = if project_changed || stale {
+            LiveGraph::build(dir_path, graph_options(args, &config)).map(|rebuilt| {
+                live = rebuilt;
+                Update::Rebuilt
+            })
+        } else {
+            live.update(changed)
+        };
+        // Errors are reported and the session goes on: the next change rebuilds the graph
+        let update = match updated {
+            Ok(update) => {
+                stale = false;
+                update
             }
-        },
-        Commands::Diagnose => match run_diagnose_analysis(dir_path) {
-            Ok(()) => {}
             Err(e) => {
-                eprintln!("Error running diagnose analysis: {}", e);
+                eprintln!("Error: {:#}", e);
+                stale = true;
+                return Ok(());
             }
-        },
-        Commands::Pressure => match run_pressure_analysis(dir_path) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Error running pressure analysis: {}", e);
-            }
-        },
-        Commands::Instability => match run_instability_analysis(dir_path) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Error running instability analysis: {}", e);
-            }
-        },
-        Commands::External => match run_external_analysis(dir_path) {
-            Ok(()) => {}
-            Err(e) => {
-                eprintln!("Error running external analysis: {}", e);
+        };
+        if update == Update::Unchanged {
+            return Ok(());
+        }
+
+        let current = render_watched(live.graph(), args, &config);
+        let paths: Vec<String> = changed
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        println!("\n=== Changed: {} ===", paths.join(", "));
+        if output_format(args, &config) == OutputFormat::Json {
+            print!("{}", current.output);
+        } else {
+            let cycle_changes = match (&previous.cycles, &current.cycles) {
+                (Some(previous), Some(current)) => CycleChanges::between(previous, current),
+                _ => CycleChanges::default(),
+            };
+            let line_changes = watch::diff_lines(&previous.output, &current.output);
+            if !cycle_changes.is_empty() {
+                print!("{}", cycle_changes.format_text());
+            } else if !line_changes.is_empty() {
+                print!("{}", line_changes);
+            } else {
+                println!("Output unchanged");
             }
-        },
-        Commands::Agent => {
-            print_agent_documentation();
         }
+        previous = current;
+        Ok(())
+    })
+}
+
+/// Renders a command result in the requested output format.
+fn render_result<T: Serialize>(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    result: &T,
+    format_text: fn(&T) -> String,
+) -> anyhow::Result<String> {
+    match format {
+        OutputFormat::Text => Ok(format_text(result)),
+        OutputFormat::Json => render_json(graph, result),
     }
 }
 
-fn run_impact_analysis(dir_path: &Path, module_name: &str) -> anyhow::Result<()> {
-    // Build the dependency graph
-    let graph = build_directory_dependency_graph(dir_path)?;
+/// Renders a command result as JSON, along with the diagnostics of the graph.
+fn render_json<T: Serialize>(graph: &DependencyGraph, result: &T) -> anyhow::Result<String> {
+    let output = serde_json::json!({
+        "result": result,
+        "diagnostics": graph.diagnostics(),
+    });
+    Ok(format!("{}\n", serde_json::to_string_pretty(&output)?))
+}
 
-    // Run impact analysis
-    let result = analyze_impact(&graph, module_name)?;
+/// Result of `analyze` for JSON output
+#[derive(Serialize)]
+struct AnalyzeResult {
+    packages: Vec<PackageInfo>,
+    modules: Vec<ModuleDependencies>,
+}
 
-    // Output results as text with prefix grouping
-    print!("{}", formatters::format_text_grouped(&result));
+/// A project module (or notebook) and the modules it depends on, for JSON output
+#[derive(Serialize)]
+struct ModuleDependencies {
+    module: String,
+    origin: ModuleOrigin,
+    /// True for PEP 420 namespace packages, which have no file of their own
+    namespace_package: bool,
+    dependencies: Vec<Dependency>,
+}
 
-    Ok(())
+#[derive(Serialize)]
+struct Dependency {
+    module: String,
+    #[serde(flatten)]
+    edge: DependencyEdge,
 }
 
-fn run_dependencies_analysis(dir_path: &Path, module_name: &str) -> anyhow::Result<()> {
-    // Build the dependency graph
-    let graph = build_directory_dependency_graph(dir_path)?;
+fn run_analyze(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    root: &str,
+) -> anyhow::Result<String> {
+    let packages = pyproject::get_package_info();
+    if format == OutputFormat::Text {
+        let mut output = format!("Analyzed directory: {}\n", root);
+        let detected: Vec<&str> = packages
+            .iter()
+            .filter(|package| package.auto_detected)
+            .map(|package| package.name.as_str())
+            .collect();
+        if !detected.is_empty() {
+            output.push_str(&format!(
+                "Auto-detected packages (none configured in pyproject.toml): {}\n",
+                detected.join(", ")
+            ));
+        }
+        output.push_str(&format!("{}\n", graph));
+        return Ok(output);
+    }
 
-    // Run dependencies analysis
-    let result = analyze_dependencies(&graph, module_name)?;
+    let mut modules = Vec::new();
+    for module in graph
+        .all_modules()
+        .filter(|module| module.origin != ModuleOrigin::External)
+    {
+        let dependencies = graph
+            .get_dependency_edges(module)?
+            .into_iter()
+            .map(|(module, edge)| Dependency { module, edge })
+            .collect();
+        modules.push(ModuleDependencies {
+            module: module.canonical_path.clone(),
+            origin: module.origin.clone(),
+            namespace_package: graph.is_namespace_package(&module.canonical_path),
+            dependencies,
+        });
+    }
+    modules.sort_by(|a, b| a.module.cmp(&b.module));
+
+    render_json(graph, &AnalyzeResult { packages, modules })
+}
+
+fn run_impact_analysis(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    module_name: &str,
+    include_type_only: bool,
+) -> anyhow::Result<String> {
+    // Run impact analysis
+    let result = analyze_impact(graph, module_name, include_type_only)?;
 
     // Output results as text with prefix grouping
-    print!("{}", dep_formatters::format_text_grouped(&result));
+    render_result(graph, format, &result, formatters::format_text_grouped)
+}
 
-    Ok(())
+fn run_dependencies_analysis(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    module_name: &str,
+) -> anyhow::Result<String> {
+    // Run dependencies analysis
+    let result = analyze_dependencies(graph, module_name)?;
+
+    // Output results as text with prefix grouping
+    render_result(graph, format, &result, dep_formatters::format_text_grouped)
 }
 
-fn run_cycles_analysis(dir_path: &Path) -> anyhow::Result<()> {
-    // Build the dependency graph
-    let graph = build_directory_dependency_graph(dir_path)?;
+fn run_usages_analysis(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    symbol_name: &str,
+    include_type_only: bool,
+) -> anyhow::Result<String> {
+    // Run usages analysis
+    let result = analyze_usages(graph, symbol_name, include_type_only)?;
 
+    // Output results as text
+    render_result(graph, format, &result, usages_formatters::format_text)
+}
+
+fn run_cycles_analysis(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    include_type_only: bool,
+) -> anyhow::Result<String> {
     // Run cycle detection
-    let result = detect_cycles(&graph)?;
+    let result = detect_cycles(graph, include_type_only)?;
 
     // Output results as text with prefix grouping
-    print!("{}", cycle_formatters::format_text_grouped(&result));
-
-    Ok(())
+    render_result(
+        graph,
+        format,
+        &result,
+        cycle_formatters::format_text_grouped,
+    )
 }
 
-fn run_pressure_analysis(dir_path: &Path) -> anyhow::Result<()> {
-    // Build the dependency graph
-    let graph = build_directory_dependency_graph(dir_path)?;
-
+fn run_pressure_analysis(graph: &DependencyGraph, format: OutputFormat) -> anyhow::Result<String> {
     // Run pressure analysis
-    let result = analyze_pressure(&graph)?;
+    let result = analyze_pressure(graph)?;
 
     // Output results as text
-    print!("{}", pressure_formatters::format_text(&result));
-
-    Ok(())
+    render_result(graph, format, &result, pressure_formatters::format_text)
 }
 
-fn run_instability_analysis(dir_path: &Path) -> anyhow::Result<()> {
-    // Build the dependency graph
-    let graph = build_directory_dependency_graph(dir_path)?;
-
+fn run_instability_analysis(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+) -> anyhow::Result<String> {
     // Run instability analysis
-    let result = analyze_instability(&graph)?;
+    let result = analyze_instability(graph)?;
 
     // Output results as text
-    print!("{}", instability_formatters::format_text(&result));
-
-    Ok(())
+    render_result(graph, format, &result, instability_formatters::format_text)
 }
 
-fn run_diagnose_analysis(dir_path: &Path) -> anyhow::Result<()> {
-    // Build the dependency graph
-    let graph = build_directory_dependency_graph(dir_path)?;
-
+fn run_diagnose_analysis(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    settings: &DiagnoseSettings,
+) -> anyhow::Result<String> {
     // Run diagnose analysis
-    let result = analyze_diagnose(&graph)?;
+    let result = analyze_diagnose_with_settings(graph, settings)?;
 
     // Output results as text
-    print!("{}", diagnose_formatters::format_text(&result));
-
-    Ok(())
+    render_result(graph, format, &result, diagnose_formatters::format_text)
 }
 
-fn run_changeset_analysis(dir_path: &Path, module_name: &str, scope: &str) -> anyhow::Result<()> {
-    // Build the dependency graph
-    let graph = build_directory_dependency_graph(dir_path)?;
-
+fn run_changeset_analysis(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    module_name: &str,
+    scope: &str,
+    include_type_only: bool,
+    thresholds: &RiskThresholds,
+) -> anyhow::Result<String> {
     // Parse scope
     let changeset_scope = ChangesetScope::from_str(scope);
 
     // Run changeset analysis
-    let result = analyze_changeset(&graph, module_name, changeset_scope)?;
+    let result = analyze_changeset_with_thresholds(
+        graph,
+        module_name,
+        changeset_scope,
+        include_type_only,
+        thresholds,
+    )?;
 
     // Output results as text with grouping
-    print!("{}", changeset_formatters::format_text_grouped(&result));
-
-    Ok(())
+    render_result(
+        graph,
+        format,
+        &result,
+        changeset_formatters::format_text_grouped,
+    )
 }
 
-fn run_external_analysis(dir_path: &Path) -> anyhow::Result<()> {
-    // Build the dependency graph
-    let graph = build_directory_dependency_graph(dir_path)?;
-
+fn run_external_analysis(
+    graph: &DependencyGraph,
+    format: OutputFormat,
+    group_paths: &[String],
+) -> anyhow::Result<String> {
     // Run external dependencies analysis
-    let result = analyze_external_dependencies(&graph)?;
+    let source_groups = SourceGroups::new(group_paths)?;
+    let result = analyze_external_dependencies_with_groups(graph, &source_groups)?;
 
     // Output results as text with grouping
-    print!("{}", external_formatters::format_text_grouped(&result));
-
-    Ok(())
+    render_result(
+        graph,
+        format,
+        &result,
+        external_formatters::format_text_grouped,
+    )
 }
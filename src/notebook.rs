@@ -0,0 +1,134 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+/// Jupyter notebook document (nbformat 4), reduced to what import extraction needs.
+#[derive(Debug, Deserialize)]
+struct Notebook {
+    #[serde(default)]
+    cells: Vec<NotebookCell>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NotebookCell {
+    cell_type: String,
+    #[serde(default)]
+    source: CellSource,
+}
+
+/// Cell source, stored either as a list of lines or as a single string.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum CellSource {
+    Lines(Vec<String>),
+    Text(String),
+}
+
+impl Default for CellSource {
+    fn default() -> Self {
+        CellSource::Text(String::new())
+    }
+}
+
+impl CellSource {
+    fn text(&self) -> String {
+        match self {
+            CellSource::Lines(lines) => lines.concat(),
+            CellSource::Text(text) => text.clone(),
+        }
+    }
+}
+
+/// Cell magics whose body is Python code (`%%time`, `%%capture output`). Cells starting
+/// with any other cell magic (`%%bash`, `%%html`, `%%writefile`) hold another language.
+const PYTHON_CELL_MAGICS: &[&str] = &[
+    "time", "timeit", "capture", "prun", "debug", "python", "python3",
+];
+
+/// Python code of a notebook code cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeCell {
+    /// 1-based position of the cell in the notebook, counting all cells
+    pub number: usize,
+    pub code: String,
+}
+
+/// Extracts the Python code of the code cells of a Jupyter notebook.
+///
+/// Cells starting with a cell magic of another language (`%%bash`, `%%html`) are skipped,
+/// and the magic line of Python cell magics (`%%time`) is dropped. Line magics
+/// (`%matplotlib inline`) and shell escapes (`!pip install ...`) are replaced by `pass`,
+/// so the code around them still parses and keeps its line numbers.
+pub fn extract_code_cells(notebook_json: &str) -> Result<Vec<CodeCell>> {
+    let notebook: Notebook = serde_json::from_str(notebook_json)
+        .map_err(|e| anyhow::anyhow!("Invalid notebook JSON: {}", e))?;
+
+    let mut cells = Vec::new();
+    for (index, cell) in notebook.cells.iter().enumerate() {
+        if cell.cell_type != "code" {
+            continue;
+        }
+        let source = cell.source.text();
+        if let Some(magic) = source.trim_start().strip_prefix("%%") {
+            let name = magic.split_whitespace().next().unwrap_or_default();
+            if !PYTHON_CELL_MAGICS.contains(&name) {
+                continue;
+            }
+        }
+
+        let mut code = String::new();
+        for line in source.lines() {
+            let statement = line.trim_start();
+            if statement.starts_with('%') || statement.starts_with('!') {
+                code.push_str(&line[..line.len() - statement.len()]);
+                code.push_str("pass");
+            } else {
+                code.push_str(line);
+            }
+            code.push('\n');
+        }
+        cells.push(CodeCell {
+            number: index + 1,
+            code,
+        });
+    }
+
+    Ok(cells)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_code_cells() {
+        let notebook = r##"{
+            "cells": [
+                {"cell_type": "markdown", "source": ["# Churn analysis\n", "import nothing\n"]},
+                {"cell_type": "code", "source": ["%matplotlib inline\n", "import pandas as pd\n", "!pip install requests"]},
+                {"cell_type": "code", "source": "%%time\nimport slow_module\n"},
+                {"cell_type": "code", "source": "%%bash\nimport this_is_shell\n"},
+                {"cell_type": "code", "source": ["for name in names:\n", "    !echo $name\n", "from myapp.models import User"]}
+            ],
+            "metadata": {},
+            "nbformat": 4,
+            "nbformat_minor": 5
+        }"##;
+
+        let cells = extract_code_cells(notebook).unwrap();
+        let numbers: Vec<usize> = cells.iter().map(|cell| cell.number).collect();
+        assert_eq!(numbers, vec![2, 3, 5]);
+        assert_eq!(cells[0].code, "pass\nimport pandas as pd\npass\n");
+        // The magic line of a Python cell magic is dropped, keeping the body
+        assert_eq!(cells[1].code, "pass\nimport slow_module\n");
+        assert_eq!(
+            cells[2].code,
+            "for name in names:\n    pass\nfrom myapp.models import User\n"
+        );
+
+        let deps = crate::imports::extract_module_deps(&cells[1].code, None).unwrap();
+        let names: Vec<&str> = deps.iter().map(|d| d.canonical_path.as_str()).collect();
+        assert_eq!(names, vec!["slow_module"]);
+
+        assert!(extract_code_cells("not a notebook").is_err());
+    }
+}
@@ -1,30 +1,40 @@
+use crate::declared::{DeclaredDependency, DependencyGroup};
+use crate::imports::call_argument;
+use crate::requirement::Requirement;
 use anyhow::Result;
+use rustpython_parser::ast::{Constant, Expr, ExprCall, Mod, Stmt};
+use rustpython_parser::{Mode, parse};
+use serde::Serialize;
 use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 thread_local! {
-    static PARSER: RefCell<Option<PyProjectParser>> = RefCell::new(None);
+    static PARSER: RefCell<Option<PyProjectParser>> = const { RefCell::new(None) };
 }
 
 /// Package information from pyproject.toml
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PackageInfo {
-    pub name: String,      // Python module name (e.g., "mymodule")
-    pub directory: String, // Filesystem directory (e.g., "MyModule/")
+    pub name: String,        // Python module name (e.g., "mymodule")
+    pub directory: String,   // Filesystem directory (e.g., "MyModule/")
+    pub auto_detected: bool, // Found on disk because no package is configured
 }
 
 /// Parser for pyproject.toml with project context
 #[derive(Clone)]
 pub struct PyProjectParser {
     project_root: PathBuf,
+    /// Directories searched for packages when none are configured
+    source_roots: Vec<String>,
     package_info: OnceLock<Vec<PackageInfo>>,
 }
 
 /// Filters out packages whose paths are contained within other packages' paths.
 /// If module A's path is contained within module B's path, module A is ignored.
 fn filter_contained_packages(mut packages: Vec<PackageInfo>) -> Vec<PackageInfo> {
-    packages.sort_by(|a, b| a.directory.len().cmp(&b.directory.len()));
+    packages.sort_by_key(|a| a.directory.len());
 
     let mut filtered = Vec::new();
 
@@ -50,19 +60,41 @@ impl PyProjectParser {
     pub fn new(project_root: &Path) -> Self {
         Self {
             project_root: project_root.to_path_buf(),
+            source_roots: vec![".".to_string(), "src".to_string()],
             package_info: OnceLock::new(),
         }
     }
 
-    fn load_package_info(&self) -> Result<Vec<PackageInfo>> {
-        let pyproject_path = self.project_root.join("pyproject.toml");
+    /// Searches these directories of the project instead of the root and `src/` when
+    /// discovering packages.
+    pub fn with_source_roots(mut self, source_roots: &[String]) -> Self {
+        self.source_roots = source_roots
+            .iter()
+            .map(|root| match root.trim_end_matches('/') {
+                "" => ".".to_string(),
+                root => root.to_string(),
+            })
+            .collect();
+        self
+    }
 
-        if !pyproject_path.exists() {
-            return Ok(Vec::new());
+    /// Returns the configured packages, or the packages found on disk if none are configured.
+    fn load_package_info(&self) -> Result<Vec<PackageInfo>> {
+        let packages = self.load_declared_packages()?;
+        if !packages.is_empty() {
+            return Ok(packages);
         }
+        Ok(self.discover_packages())
+    }
 
-        let content = std::fs::read_to_string(&pyproject_path)?;
-        let toml: toml::Value = toml::from_str(&content)?;
+    fn load_declared_packages(&self) -> Result<Vec<PackageInfo>> {
+        let pyproject_path = self.project_root.join("pyproject.toml");
+
+        let toml: toml::Value = if pyproject_path.exists() {
+            toml::from_str(&std::fs::read_to_string(&pyproject_path)?)?
+        } else {
+            toml::Value::Table(toml::Table::new())
+        };
 
         let mut packages = Vec::new();
 
@@ -80,54 +112,378 @@ impl PyProjectParser {
                         .unwrap_or(include)
                         .to_string();
 
+                    // Namespace package portions are included by path (`company/team_a`)
                     packages.push(PackageInfo {
-                        name: include.to_string(),
+                        name: include.trim_end_matches('/').replace('/', "."),
                         directory,
+                        auto_detected: false,
                     });
                 }
             }
         }
 
+        packages.extend(self.setuptools_packages(&toml));
+        packages.extend(self.hatch_packages(&toml));
+        packages.extend(self.flit_packages(&toml));
+        packages.extend(self.pdm_packages(&toml));
+
+        // Legacy setuptools projects configure their packages like `[tool.setuptools]`
+        for config in self.setup_configs()? {
+            packages.extend(self.setuptools_packages(&config.to_setuptools_toml()));
+        }
+
+        // The same package may be configured for several backends
+        sort_and_dedup_packages(&mut packages);
+
         Ok(filter_contained_packages(packages))
     }
 
+    /// Reads `[tool.setuptools] packages`, `py-modules` and `package-dir`, and
+    /// `[tool.setuptools.packages.find]`.
+    fn setuptools_packages(&self, toml: &toml::Value) -> Vec<PackageInfo> {
+        let Some(setuptools) = toml.get("tool").and_then(|t| t.get("setuptools")) else {
+            return Vec::new();
+        };
+
+        let package_dir = setuptools.get("package-dir").and_then(|d| d.as_table());
+        let directory_of = |name: &str| {
+            let path = name.replace('.', "/");
+            match package_dir {
+                Some(dirs) => match (dirs.get(name), dirs.get("")) {
+                    (Some(dir), _) => dir.as_str().unwrap_or(&path).to_string(),
+                    (None, Some(root)) => join_path(root.as_str().unwrap_or("."), &path),
+                    (None, None) => path,
+                },
+                None => path,
+            }
+        };
+
+        let mut packages = Vec::new();
+        match setuptools.get("packages") {
+            Some(toml::Value::Array(names)) => {
+                let names: Vec<&str> = names.iter().filter_map(|n| n.as_str()).collect();
+                for name in &names {
+                    // Subpackages are covered by their parent package
+                    if names
+                        .iter()
+                        .any(|parent| name.starts_with(&format!("{}.", parent)))
+                    {
+                        continue;
+                    }
+                    packages.push(PackageInfo {
+                        name: name.to_string(),
+                        directory: directory_of(name),
+                        auto_detected: false,
+                    });
+                }
+            }
+            Some(toml::Value::Table(table)) => {
+                if let Some(find) = table.get("find") {
+                    let strings = |key: &str| -> Vec<String> {
+                        find.get(key)
+                            .and_then(|v| v.as_array())
+                            .into_iter()
+                            .flatten()
+                            .filter_map(|v| v.as_str().map(str::to_string))
+                            .collect()
+                    };
+                    let mut roots = strings("where");
+                    if roots.is_empty() {
+                        roots.push(".".to_string());
+                    }
+                    let mut include = strings("include");
+                    if include.is_empty() {
+                        include.push("*".to_string());
+                    }
+                    let exclude = strings("exclude");
+                    let namespaces = find
+                        .get("namespaces")
+                        .and_then(|n| n.as_bool())
+                        .unwrap_or(true);
+
+                    for root in &roots {
+                        packages.extend(self.find_packages(root, namespaces).into_iter().filter(
+                            |package| {
+                                include
+                                    .iter()
+                                    .any(|p| matches_package_pattern(p, &package.name))
+                                    && !exclude.iter().any(|p| glob_match(p, &package.name))
+                            },
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+        for module in setuptools
+            .get("py-modules")
+            .and_then(|m| m.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|m| m.as_str())
+        {
+            packages.push(PackageInfo {
+                name: module.to_string(),
+                directory: directory_of(module),
+                auto_detected: false,
+            });
+        }
+        packages
+    }
+
+    /// Reads `packages` of `[tool.hatch.build.targets.wheel]` and `[tool.hatch.build]`.
+    fn hatch_packages(&self, toml: &toml::Value) -> Vec<PackageInfo> {
+        let Some(build) = toml
+            .get("tool")
+            .and_then(|t| t.get("hatch"))
+            .and_then(|h| h.get("build"))
+        else {
+            return Vec::new();
+        };
+        let wheel = build.get("targets").and_then(|t| t.get("wheel"));
+        [wheel, Some(build)]
+            .into_iter()
+            .flatten()
+            .filter_map(|config| config.get("packages").and_then(|p| p.as_array()))
+            .flatten()
+            .filter_map(|path| path.as_str())
+            .filter_map(package_from_path)
+            .collect()
+    }
+
+    /// Reads `[tool.flit.module] name` (dotted for a namespace package portion); flit looks
+    /// for the module in the project root or `src/`.
+    fn flit_packages(&self, toml: &toml::Value) -> Vec<PackageInfo> {
+        let Some(name) = toml
+            .get("tool")
+            .and_then(|t| t.get("flit"))
+            .and_then(|f| f.get("module"))
+            .and_then(|m| m.get("name"))
+            .and_then(|n| n.as_str())
+        else {
+            return Vec::new();
+        };
+
+        let path = name.replace('.', "/");
+        let in_src = self.project_root.join("src").join(&path);
+        let directory = if in_src.is_dir() || in_src.with_extension("py").is_file() {
+            join_path("src", &path)
+        } else {
+            path
+        };
+        vec![PackageInfo {
+            name: name.to_string(),
+            directory,
+            auto_detected: false,
+        }]
+    }
+
+    /// Reads `[tool.pdm.build] includes`, or the packages found in its `package-dir`.
+    fn pdm_packages(&self, toml: &toml::Value) -> Vec<PackageInfo> {
+        let Some(build) = toml
+            .get("tool")
+            .and_then(|t| t.get("pdm"))
+            .and_then(|p| p.get("build"))
+        else {
+            return Vec::new();
+        };
+
+        let includes: Vec<PackageInfo> = build
+            .get("includes")
+            .and_then(|i| i.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|path| path.as_str())
+            .filter_map(package_from_path)
+            .collect();
+        if !includes.is_empty() {
+            return includes;
+        }
+
+        match build.get("package-dir").and_then(|d| d.as_str()) {
+            Some(package_dir) => self.find_packages(package_dir, false),
+            None => Vec::new(),
+        }
+    }
+
+    /// Discovers the top-level packages and modules of a flat or src layout: directories
+    /// with an `__init__.py` and `.py` files, in the source roots (the project root and
+    /// `src/` by default).
+    fn discover_packages(&self) -> Vec<PackageInfo> {
+        let mut packages = Vec::new();
+        for root in &self.source_roots {
+            packages.extend(self.find_packages(root, false));
+            // `src/` is a source root of its own, not a namespace package
+            packages.extend(
+                self.find_namespace_portions(root)
+                    .into_iter()
+                    .filter(|package| {
+                        root != "."
+                            || !self
+                                .source_roots
+                                .iter()
+                                .any(|other| package.directory.starts_with(&format!("{}/", other)))
+                    }),
+            );
+            packages.extend(self.find_modules(root));
+        }
+        sort_and_dedup_packages(&mut packages);
+        for package in &mut packages {
+            package.auto_detected = true;
+        }
+        packages
+    }
+
+    /// Lists the regular packages nested in PEP 420 namespace packages (directories without
+    /// an `__init__.py`) of a directory of the project: `company/team_a/__init__.py` gives
+    /// `company.team_a`, so `company.team_b` from another distribution stays external.
+    fn find_namespace_portions(&self, root: &str) -> Vec<PackageInfo> {
+        let mut packages = Vec::new();
+        let mut namespaces = vec![(String::new(), root.to_string())];
+
+        while let Some((namespace, directory)) = namespaces.pop() {
+            let Ok(entries) = std::fs::read_dir(self.project_root.join(&directory)) else {
+                continue;
+            };
+            for path in entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+            {
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if !path.is_dir() || !is_identifier(name) {
+                    continue;
+                }
+                let dotted = if namespace.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}.{}", namespace, name)
+                };
+                let child_directory = join_path(&directory, name);
+                if !path.join("__init__.py").is_file() {
+                    namespaces.push((dotted, child_directory));
+                } else if !namespace.is_empty() {
+                    packages.push(PackageInfo {
+                        name: dotted,
+                        directory: child_directory,
+                        auto_detected: false,
+                    });
+                }
+            }
+        }
+        packages
+    }
+
+    /// Lists the top-level `.py` modules in a directory of the project.
+    fn find_modules(&self, root: &str) -> Vec<PackageInfo> {
+        let Ok(entries) = std::fs::read_dir(self.project_root.join(root)) else {
+            return Vec::new();
+        };
+
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "py"))
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_str()?.to_string();
+                (is_identifier(&name) && name != "__init__").then(|| PackageInfo {
+                    directory: join_path(root, &name),
+                    name,
+                    auto_detected: false,
+                })
+            })
+            .collect()
+    }
+
+    /// Lists the top-level packages in a directory of the project, like setuptools'
+    /// `find_packages`: directories with an `__init__.py`, or with any Python file when
+    /// namespace packages are allowed.
+    fn find_packages(&self, root: &str, namespaces: bool) -> Vec<PackageInfo> {
+        let Ok(entries) = std::fs::read_dir(self.project_root.join(root)) else {
+            return Vec::new();
+        };
+
+        let mut packages: Vec<PackageInfo> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .filter_map(|path| {
+                let name = path.file_name()?.to_str()?.to_string();
+                if !is_identifier(&name) {
+                    return None;
+                }
+                let is_package = path.join("__init__.py").is_file()
+                    || (namespaces && contains_python_files(&path));
+                is_package.then(|| PackageInfo {
+                    directory: join_path(root, &name),
+                    name,
+                    auto_detected: false,
+                })
+            })
+            .collect();
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+        packages
+    }
+
     pub fn get_package_info(&self) -> &Vec<PackageInfo> {
         self.package_info
             .get_or_init(|| self.load_package_info().unwrap_or_default())
     }
 
     pub fn is_internal_module(&self, module_name: &str) -> bool {
+        // Package names are dotted for namespace package portions (`company.team_a`)
         let packages = self.get_package_info();
-        let top_level = module_name.split('.').next().unwrap_or(module_name);
-        packages.iter().any(|pkg| pkg.name == top_level)
+        packages.iter().any(|pkg| {
+            module_name
+                .strip_prefix(&pkg.name)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
     }
 
     pub fn normalize_module_name(&self, module_name: &str) -> Result<String> {
         let packages = self.get_package_info();
+        let mut prefixed = None;
 
         for package in packages {
             let from_dotted = package.directory.trim_end_matches('/').replace('/', ".");
 
-            if module_name.starts_with(&format!("{}.", from_dotted)) {
-                if let Some(remainder) = module_name.strip_prefix(&format!("{}.", from_dotted)) {
-                    // Check if remainder already starts with the package name (common package/package/ structure)
-                    if remainder.starts_with(&format!("{}.", package.name)) {
-                        return Ok(remainder.to_string());
-                    } else if remainder == package.name {
-                        return Ok(package.name.clone());
-                    } else {
-                        return Ok(format!("{}.{}", package.name, remainder));
-                    }
-                } else if module_name == from_dotted {
+            if module_name == from_dotted {
+                return Ok(package.name.clone());
+            }
+            if let Some(remainder) = module_name.strip_prefix(&format!("{}.", from_dotted)) {
+                // Check if remainder already starts with the package name (common package/package/ structure)
+                if remainder.starts_with(&format!("{}.", package.name)) {
+                    return Ok(remainder.to_string());
+                } else if remainder == package.name {
                     return Ok(package.name.clone());
+                } else if prefixed.is_none() {
+                    // Several packages (or namespace portions) may share a source root
+                    prefixed = Some(format!("{}.{}", package.name, remainder));
                 }
             }
         }
 
-        Ok(module_name.to_string())
+        Ok(prefixed.unwrap_or_else(|| module_name.to_string()))
     }
 
     pub fn get_declared_dependencies(&self) -> Result<Vec<String>> {
+        let mut dependencies: Vec<String> = self
+            .get_grouped_dependencies()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+        Ok(dependencies)
+    }
+
+    /// Returns the dependencies declared in pyproject.toml with their group.
+    ///
+    /// Poetry groups and PEP 735 dependency groups are dev dependencies unless named
+    /// after tests; PEP 621 extras are runtime dependencies unless named after
+    /// development or tests.
+    pub fn get_grouped_dependencies(&self) -> Result<Vec<(String, DependencyGroup)>> {
         let pyproject_path = self.project_root.join("pyproject.toml");
 
         if !pyproject_path.exists() {
@@ -148,7 +504,34 @@ impl PyProjectParser {
         {
             for (dep_name, _dep_spec) in deps {
                 if dep_name != "python" {
-                    dependencies.push(normalize_dependency_name(dep_name));
+                    dependencies.push((
+                        normalize_dependency_name(dep_name),
+                        DependencyGroup::Runtime,
+                    ));
+                }
+            }
+        }
+
+        // Parse PEP 621 [project] dependencies and [project.optional-dependencies]
+        if let Some(project) = toml.get("project") {
+            let optional = project
+                .get("optional-dependencies")
+                .and_then(|o| o.as_table())
+                .into_iter()
+                .flatten()
+                .map(|(extra, requirements)| {
+                    let group =
+                        DependencyGroup::from_name(extra).unwrap_or(DependencyGroup::Runtime);
+                    (group, requirements)
+                });
+            let required = project
+                .get("dependencies")
+                .map(|requirements| (DependencyGroup::Runtime, requirements));
+            for (group, requirements) in required.into_iter().chain(optional) {
+                for requirement in requirements.as_array().into_iter().flatten() {
+                    if let Some(requirement) = requirement.as_str().and_then(Requirement::parse) {
+                        dependencies.push((normalize_dependency_name(&requirement.name), group));
+                    }
                 }
             }
         }
@@ -160,22 +543,156 @@ impl PyProjectParser {
             .and_then(|p| p.get("group"))
             .and_then(|g| g.as_table())
         {
-            for (_group_name, group_config) in groups {
+            for (group_name, group_config) in groups {
+                let group = DependencyGroup::from_name(group_name).unwrap_or(DependencyGroup::Dev);
                 if let Some(group_deps) =
                     group_config.get("dependencies").and_then(|d| d.as_table())
                 {
                     for (dep_name, _dep_spec) in group_deps {
-                        dependencies.push(normalize_dependency_name(dep_name));
+                        dependencies.push((normalize_dependency_name(dep_name), group));
                     }
                 }
             }
         }
 
-        dependencies.sort();
-        dependencies.dedup();
+        // Parse PEP 735 [dependency-groups]
+        if let Some(groups) = toml.get("dependency-groups").and_then(|g| g.as_table()) {
+            for group_name in groups.keys() {
+                let group = DependencyGroup::from_name(group_name).unwrap_or(DependencyGroup::Dev);
+                for requirement in
+                    dependency_group_requirements(groups, group_name, &mut HashSet::new())
+                {
+                    dependencies.push((normalize_dependency_name(&requirement.name), group));
+                }
+            }
+        }
+
+        Ok(dependencies)
+    }
+
+    /// Returns the dependencies declared by `setup.cfg` or `setup.py` with their group.
+    ///
+    /// `install_requires` are runtime dependencies; `extras_require` groups are runtime
+    /// dependencies unless named after development or tests.
+    pub fn get_setup_dependencies(
+        &self,
+        file_name: &str,
+    ) -> Result<Vec<(String, DependencyGroup)>> {
+        let Some(config) = self.read_setup_config(file_name)? else {
+            return Ok(Vec::new());
+        };
+
+        let extras = config.extras_require.iter().map(|(extra, requirements)| {
+            let group = DependencyGroup::from_name(extra).unwrap_or(DependencyGroup::Runtime);
+            (group, requirements)
+        });
+        let mut dependencies = Vec::new();
+        for (group, requirements) in
+            std::iter::once((DependencyGroup::Runtime, &config.install_requires)).chain(extras)
+        {
+            for requirement in requirements.iter().filter_map(|r| Requirement::parse(r)) {
+                dependencies.push((normalize_dependency_name(&requirement.name), group));
+            }
+        }
         Ok(dependencies)
     }
 
+    /// Reads the static setuptools configuration of `setup.cfg` and `setup.py`.
+    fn setup_configs(&self) -> Result<Vec<SetupConfig>> {
+        let mut configs = Vec::new();
+        for file_name in SETUP_FILES {
+            configs.extend(self.read_setup_config(file_name)?);
+        }
+        Ok(configs)
+    }
+
+    /// Reads `setup.cfg` or statically evaluates `setup.py`, without executing it.
+    ///
+    /// Returns `None` if the file does not exist, or if `setup.py` has no `setup(...)`
+    /// call or is not valid Python.
+    fn read_setup_config(&self, file_name: &str) -> Result<Option<SetupConfig>> {
+        let path = self.project_root.join(file_name);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        if file_name == "setup.cfg" {
+            let content = std::fs::read_to_string(&path)?;
+            return Ok(Some(SetupConfig::from_setup_cfg(&content)));
+        }
+        let source = crate::encoding::decode_source(&std::fs::read(&path)?)?;
+        Ok(SetupConfig::from_setup_py(&source))
+    }
+
+    /// Returns the dependencies declared by every dependency source of the project
+    /// (pyproject.toml, requirements files and Pipfile).
+    pub fn get_declared_dependency_sources(&self) -> Result<Vec<DeclaredDependency>> {
+        crate::declared::load_declared_dependencies(&self.project_root)
+    }
+
+    /// Returns the extras groups declared by the project, mapped to the packages they contain.
+    ///
+    /// Reads `[tool.poetry.extras]` and `[project.optional-dependencies]` of pyproject.toml,
+    /// and `extras_require` of setup.cfg and setup.py.
+    pub fn get_extras(&self) -> Result<BTreeMap<String, Vec<String>>> {
+        let pyproject_path = self.project_root.join("pyproject.toml");
+
+        let toml: toml::Value = if pyproject_path.exists() {
+            toml::from_str(&std::fs::read_to_string(&pyproject_path)?)?
+        } else {
+            toml::Value::Table(toml::Table::new())
+        };
+
+        let mut extras: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        // Parse [tool.poetry.extras]
+        if let Some(poetry_extras) = toml
+            .get("tool")
+            .and_then(|t| t.get("poetry"))
+            .and_then(|p| p.get("extras"))
+            .and_then(|e| e.as_table())
+        {
+            for (extra_name, packages) in poetry_extras {
+                let entry = extras.entry(extra_name.clone()).or_default();
+                for package in packages.as_array().into_iter().flatten() {
+                    if let Some(package) = package.as_str() {
+                        entry.push(normalize_dependency_name(package));
+                    }
+                }
+            }
+        }
+
+        // Parse [project.optional-dependencies]
+        if let Some(optional_deps) = toml
+            .get("project")
+            .and_then(|p| p.get("optional-dependencies"))
+            .and_then(|o| o.as_table())
+        {
+            for (extra_name, requirements) in optional_deps {
+                let entry = extras.entry(extra_name.clone()).or_default();
+                for requirement in requirements.as_array().into_iter().flatten() {
+                    if let Some(requirement) = requirement.as_str().and_then(Requirement::parse) {
+                        entry.push(normalize_dependency_name(&requirement.name));
+                    }
+                }
+            }
+        }
+
+        // Parse `extras_require` of setup.cfg and setup.py
+        for config in self.setup_configs()? {
+            for (extra_name, requirements) in &config.extras_require {
+                let entry = extras.entry(extra_name.clone()).or_default();
+                for requirement in requirements.iter().filter_map(|r| Requirement::parse(r)) {
+                    entry.push(normalize_dependency_name(&requirement.name));
+                }
+            }
+        }
+
+        for packages in extras.values_mut() {
+            packages.sort();
+            packages.dedup();
+        }
+        Ok(extras)
+    }
+
     pub fn get_used_externals(&self) -> Result<Vec<String>> {
         let used_externals_path = self.project_root.join(".used-externals.txt");
 
@@ -188,19 +705,19 @@ impl PyProjectParser {
 
         for line in content.lines() {
             let line = line.trim();
-            
+
             // Skip empty lines and comments
             if line.is_empty() || line.starts_with('#') {
                 continue;
             }
-            
+
             // Extract package name (handle inline comments)
             let package_name = if let Some(comment_pos) = line.find('#') {
                 line[..comment_pos].trim()
             } else {
                 line
             };
-            
+
             if !package_name.is_empty() {
                 externals.push(normalize_dependency_name(package_name));
             }
@@ -212,12 +729,461 @@ impl PyProjectParser {
     }
 }
 
+/// Legacy setuptools configuration files, read in this order.
+pub(crate) const SETUP_FILES: [&str; 2] = ["setup.cfg", "setup.py"];
+
+/// Returns true if a `setup.py` has a `setup(...)` call, so it is read as a dependency
+/// declaration rather than analyzed as a module of the project.
+pub(crate) fn is_setup_declaration(path: &Path) -> bool {
+    std::fs::read(path)
+        .ok()
+        .and_then(|bytes| crate::encoding::decode_source(&bytes).ok())
+        .is_some_and(|source| SetupConfig::from_setup_py(&source).is_some())
+}
+
+/// Static setuptools configuration of a legacy project: the `[options]` of `setup.cfg`,
+/// or the literal arguments of the `setup(...)` call of `setup.py`.
+#[derive(Debug, Default, PartialEq)]
+struct SetupConfig {
+    packages: Option<SetupPackages>,
+    package_dir: BTreeMap<String, String>,
+    py_modules: Vec<String>,
+    install_requires: Vec<String>,
+    extras_require: BTreeMap<String, Vec<String>>,
+}
+
+/// The `packages` option: listed names, or a `find_packages` search.
+#[derive(Debug, PartialEq)]
+enum SetupPackages {
+    List(Vec<String>),
+    Find {
+        roots: Vec<String>,
+        include: Vec<String>,
+        exclude: Vec<String>,
+        namespaces: bool,
+    },
+}
+
+impl SetupConfig {
+    /// Reads `[options]` (`packages`, `package_dir`, `py_modules`, `install_requires`),
+    /// `[options.packages.find]` and `[options.extras_require]` of a `setup.cfg`.
+    fn from_setup_cfg(content: &str) -> Self {
+        let sections = parse_ini(content);
+        let option = |section: &str, key: &str| {
+            sections
+                .get(section)
+                .and_then(|options| options.get(key))
+                .map(String::as_str)
+        };
+
+        let packages = option("options", "packages").map(|packages| match packages.trim() {
+            find @ ("find:" | "find_namespace:") => {
+                let find_list = |key| option("options.packages.find", key).map(cfg_list);
+                SetupPackages::Find {
+                    roots: find_list("where").unwrap_or_default(),
+                    include: find_list("include").unwrap_or_default(),
+                    exclude: find_list("exclude").unwrap_or_default(),
+                    namespaces: find == "find_namespace:",
+                }
+            }
+            names => SetupPackages::List(cfg_list(names)),
+        });
+
+        // `package_dir` maps packages to directories, one `name = dir` per line (`=src`)
+        let package_dir = option("options", "package_dir")
+            .into_iter()
+            .flat_map(str::lines)
+            .filter_map(|line| line.split_once('='))
+            .map(|(name, dir)| (name.trim().to_string(), dir.trim().to_string()))
+            .collect();
+
+        let extras_require = sections
+            .get("options.extras_require")
+            .into_iter()
+            .flatten()
+            .map(|(extra, requirements)| (extra.clone(), cfg_requirements(requirements)))
+            .collect();
+
+        Self {
+            packages,
+            package_dir,
+            py_modules: option("options", "py_modules")
+                .map(cfg_list)
+                .unwrap_or_default(),
+            install_requires: option("options", "install_requires")
+                .map(cfg_requirements)
+                .unwrap_or_default(),
+            extras_require,
+        }
+    }
+
+    /// Statically evaluates the `setup(...)` call of a `setup.py` without running it.
+    ///
+    /// Reads the literal `packages`, `package_dir`, `py_modules`, `install_requires` and
+    /// `extras_require` arguments, which may also be module-level variables assigned a
+    /// literal; `packages` may be a `find_packages(...)` or `find_namespace_packages(...)`
+    /// call. Other arguments are ignored.
+    fn from_setup_py(source: &str) -> Option<Self> {
+        let Ok(Mod::Module(module)) = parse(source, Mode::Module, "setup.py") else {
+            return None;
+        };
+
+        let mut variables = HashMap::new();
+        for stmt in &module.body {
+            if let Stmt::Assign(assign) = stmt
+                && let [Expr::Name(target)] = assign.targets.as_slice()
+            {
+                variables.insert(target.id.to_string(), &*assign.value);
+            }
+        }
+        fn resolve<'e>(variables: &HashMap<String, &'e Expr>, expr: &'e Expr) -> Option<&'e Expr> {
+            match expr {
+                Expr::Name(name) => variables.get(name.id.as_str()).copied(),
+                _ => Some(expr),
+            }
+        }
+
+        let call = find_setup_call(&module.body)?;
+        let mut config = Self::default();
+        for keyword in &call.keywords {
+            let (Some(arg), Some(value)) =
+                (keyword.arg.as_deref(), resolve(&variables, &keyword.value))
+            else {
+                continue;
+            };
+            match arg {
+                "packages" => config.packages = setup_py_packages(value),
+                "package_dir" => {
+                    config.package_dir = literal_dict(value)
+                        .into_iter()
+                        .filter_map(|(name, dir)| Some((name, literal_str(dir)?)))
+                        .collect();
+                }
+                "py_modules" => config.py_modules = literal_strings(value),
+                "install_requires" => config.install_requires = literal_strings(value),
+                "extras_require" => {
+                    config.extras_require = literal_dict(value)
+                        .into_iter()
+                        .map(|(extra, requirements)| {
+                            let requirements = resolve(&variables, requirements)
+                                .map(literal_strings)
+                                .unwrap_or_default();
+                            (extra, requirements)
+                        })
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        Some(config)
+    }
+
+    /// Converts the configuration into the equivalent `[tool.setuptools]` table.
+    fn to_setuptools_toml(&self) -> toml::Value {
+        let strings = |values: &[String]| {
+            toml::Value::Array(values.iter().cloned().map(toml::Value::String).collect())
+        };
+
+        let mut setuptools = toml::Table::new();
+        match &self.packages {
+            Some(SetupPackages::List(names)) => {
+                setuptools.insert("packages".to_string(), strings(names));
+            }
+            Some(SetupPackages::Find {
+                roots,
+                include,
+                exclude,
+                namespaces,
+            }) => {
+                let mut find = toml::Table::new();
+                find.insert("where".to_string(), strings(roots));
+                find.insert("include".to_string(), strings(include));
+                find.insert("exclude".to_string(), strings(exclude));
+                find.insert("namespaces".to_string(), toml::Value::Boolean(*namespaces));
+                let mut packages = toml::Table::new();
+                packages.insert("find".to_string(), toml::Value::Table(find));
+                setuptools.insert("packages".to_string(), toml::Value::Table(packages));
+            }
+            None => {}
+        }
+        let package_dir = self
+            .package_dir
+            .iter()
+            .map(|(name, dir)| (name.clone(), toml::Value::String(dir.clone())))
+            .collect();
+        setuptools.insert("package-dir".to_string(), toml::Value::Table(package_dir));
+        setuptools.insert("py-modules".to_string(), strings(&self.py_modules));
+
+        let mut tool = toml::Table::new();
+        tool.insert("setuptools".to_string(), toml::Value::Table(setuptools));
+        let mut root = toml::Table::new();
+        root.insert("tool".to_string(), toml::Value::Table(tool));
+        toml::Value::Table(root)
+    }
+}
+
+/// Parses an INI file into `section -> key -> value`. Indented lines continue the value
+/// of the previous key, and full-line `#` or `;` comments are skipped.
+fn parse_ini(content: &str) -> BTreeMap<String, BTreeMap<String, String>> {
+    let mut sections: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+    let mut section = String::new();
+    let mut key: Option<String> = None;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(['#', ';']) {
+            continue;
+        }
+        if line.starts_with(char::is_whitespace)
+            && let Some(key) = &key
+        {
+            let value = sections
+                .entry(section.clone())
+                .or_default()
+                .entry(key.clone())
+                .or_default();
+            value.push('\n');
+            value.push_str(trimmed);
+        } else if let Some(name) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            section = name.trim().to_string();
+            key = None;
+        } else if let Some(delimiter) = trimmed.find(['=', ':']) {
+            let name = trimmed[..delimiter].trim().to_string();
+            let value = trimmed[delimiter + 1..].trim().to_string();
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(name.clone(), value);
+            key = Some(name);
+        }
+    }
+    sections
+}
+
+/// Splits a `setup.cfg` list, separated by commas or newlines.
+fn cfg_list(value: &str) -> Vec<String> {
+    value
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Splits a `setup.cfg` list of requirements, one per line (specifiers contain commas).
+fn cfg_requirements(value: &str) -> Vec<String> {
+    value
+        .lines()
+        .map(|line| line.split(" #").next().unwrap_or(line).trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Finds the `setup(...)` or `setuptools.setup(...)` call among the statements of a
+/// module, including those under `if __name__ == "__main__":`.
+fn find_setup_call(body: &[Stmt]) -> Option<&ExprCall> {
+    body.iter().find_map(|stmt| match stmt {
+        Stmt::Expr(expr) => match &*expr.value {
+            Expr::Call(call) if is_named_call(call, &["setup"]) => Some(call),
+            _ => None,
+        },
+        Stmt::If(if_stmt) => {
+            find_setup_call(&if_stmt.body).or_else(|| find_setup_call(&if_stmt.orelse))
+        }
+        _ => None,
+    })
+}
+
+/// Returns true if a call is to one of the functions, by name or attribute
+/// (`setup(...)`, `setuptools.setup(...)`).
+fn is_named_call(call: &ExprCall, names: &[&str]) -> bool {
+    match &*call.func {
+        Expr::Name(name) => names.contains(&name.id.as_str()),
+        Expr::Attribute(attribute) => names.contains(&attribute.attr.as_str()),
+        _ => false,
+    }
+}
+
+/// Evaluates the `packages` argument of `setup.py`: a list of names or a
+/// `find_packages(where, exclude, include)` call.
+fn setup_py_packages(expr: &Expr) -> Option<SetupPackages> {
+    let Expr::Call(call) = expr else {
+        return Some(SetupPackages::List(literal_strings(expr)));
+    };
+    if !is_named_call(call, &["find_packages", "find_namespace_packages"]) {
+        return None;
+    }
+    let argument = |index, keyword| call_argument(call, index, keyword);
+    Some(SetupPackages::Find {
+        roots: argument(0, "where")
+            .and_then(literal_str)
+            .into_iter()
+            .collect(),
+        exclude: argument(1, "exclude")
+            .map(literal_strings)
+            .unwrap_or_default(),
+        include: argument(2, "include")
+            .map(literal_strings)
+            .unwrap_or_default(),
+        namespaces: is_named_call(call, &["find_namespace_packages"]),
+    })
+}
+
+fn literal_str(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Constant(constant) => match &constant.value {
+            Constant::Str(value) => Some(value.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Evaluates a list or tuple of string literals (non-literal items are skipped).
+fn literal_strings(expr: &Expr) -> Vec<String> {
+    let items = match expr {
+        Expr::List(list) => &list.elts,
+        Expr::Tuple(tuple) => &tuple.elts,
+        _ => return Vec::new(),
+    };
+    items.iter().filter_map(literal_str).collect()
+}
+
+/// Evaluates a dict literal with string keys (other entries are skipped).
+fn literal_dict(expr: &Expr) -> Vec<(String, &Expr)> {
+    let Expr::Dict(dict) = expr else {
+        return Vec::new();
+    };
+    dict.keys
+        .iter()
+        .zip(&dict.values)
+        .filter_map(|(key, value)| Some((literal_str(key.as_ref()?)?, value)))
+        .collect()
+}
+
+/// Reads the requirements of a PEP 735 dependency group, following its
+/// `{ include-group = "..." }` entries.
+fn dependency_group_requirements(
+    groups: &toml::map::Map<String, toml::Value>,
+    group_name: &str,
+    visited: &mut HashSet<String>,
+) -> Vec<Requirement> {
+    // Group names are compared normalized, and include cycles are broken
+    let normalized = normalize_dependency_name(group_name).replace('.', "-");
+    if !visited.insert(normalized.clone()) {
+        return Vec::new();
+    }
+    let Some(entries) = groups
+        .iter()
+        .find(|(name, _)| normalize_dependency_name(name).replace('.', "-") == normalized)
+        .and_then(|(_, entries)| entries.as_array())
+    else {
+        return Vec::new();
+    };
+
+    let mut requirements = Vec::new();
+    for entry in entries {
+        if let Some(requirement) = entry.as_str().and_then(Requirement::parse) {
+            requirements.push(requirement);
+        } else if let Some(included) = entry.get("include-group").and_then(|g| g.as_str()) {
+            requirements.extend(dependency_group_requirements(groups, included, visited));
+        }
+    }
+    requirements
+}
+
 /// Normalizes dependency name from complex dependency specifications
-fn normalize_dependency_name(dep_name: &str) -> String {
+pub(crate) fn normalize_dependency_name(dep_name: &str) -> String {
     // Handle underscores vs hyphens - convert to lowercase and use hyphens
     dep_name.to_lowercase().replace('_', "-")
 }
 
+/// Sorts packages by name, dropping duplicates of the same name and directory.
+///
+/// Portions of a namespace package in several source roots share a name and are kept.
+fn sort_and_dedup_packages(packages: &mut Vec<PackageInfo>) {
+    packages.sort_by(|a, b| (&a.name, &a.directory).cmp(&(&b.name, &b.directory)));
+    packages.dedup_by(|a, b| a.name == b.name && a.directory == b.directory);
+}
+
+/// Builds the package of a build backend path entry (`src/mypkg` or `mymodule.py`).
+fn package_from_path(path: &str) -> Option<PackageInfo> {
+    let path = path.trim_end_matches('/');
+    if path.contains(['*', '?', '[']) {
+        return None;
+    }
+    let directory = path.strip_suffix(".py").unwrap_or(path);
+    let name = directory.rsplit('/').next()?;
+    is_identifier(name).then(|| PackageInfo {
+        name: name.to_string(),
+        directory: directory.to_string(),
+        auto_detected: false,
+    })
+}
+
+/// Joins a project-relative directory and a name, treating "." and "" as the project root.
+fn join_path(root: &str, name: &str) -> String {
+    match root.trim_end_matches('/') {
+        "" | "." => name.to_string(),
+        root => format!("{}/{}", root, name),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn contains_python_files(directory: &Path) -> bool {
+    ignore::WalkBuilder::new(directory)
+        .standard_filters(false)
+        .build()
+        .filter_map(|entry| entry.ok())
+        .any(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+                && entry.path().extension().is_some_and(|ext| ext == "py")
+        })
+}
+
+/// Matches a setuptools include pattern against a top-level package. Patterns naming
+/// subpackages (`mypkg.*`) select their top-level package too.
+fn matches_package_pattern(pattern: &str, package: &str) -> bool {
+    glob_match(pattern, package)
+        || pattern
+            .strip_prefix(package)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Matches a name against a shell-style pattern with `*` and `?` wildcards.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 /// Initialize the thread-local parser with project root
 pub fn init(project_root: &Path) {
     PARSER.with(|parser| {
@@ -225,6 +1191,33 @@ pub fn init(project_root: &Path) {
     });
 }
 
+/// Initialize the thread-local parser with project root, discovering packages in the
+/// given source roots
+pub fn init_with_source_roots(project_root: &Path, source_roots: &[String]) {
+    PARSER.with(|parser| {
+        *parser.borrow_mut() =
+            Some(PyProjectParser::new(project_root).with_source_roots(source_roots));
+    });
+}
+
+/// Returns a copy of the thread-local parser with its packages loaded, to share the
+/// project context with worker threads.
+pub fn current() -> Option<PyProjectParser> {
+    PARSER.with(|parser| {
+        let parser = parser.borrow();
+        let p = parser.as_ref()?;
+        p.get_package_info();
+        Some(p.clone())
+    })
+}
+
+/// Installs a parser returned by [`current`] as the thread-local parser.
+pub fn install(parser: Option<PyProjectParser>) {
+    PARSER.with(|p| {
+        *p.borrow_mut() = parser;
+    });
+}
+
 #[cfg(test)]
 pub fn init_for_test(project_root: &Path) {
     init(project_root);
@@ -267,6 +1260,38 @@ pub fn get_declared_dependencies() -> Result<Vec<String>> {
     })
 }
 
+/// Returns the internal packages of the project (configured or auto-detected).
+pub fn get_package_info() -> Vec<PackageInfo> {
+    PARSER.with(|parser| {
+        if let Some(p) = parser.borrow().as_ref() {
+            p.get_package_info().clone()
+        } else {
+            Vec::new()
+        }
+    })
+}
+
+/// Returns the dependencies declared by every dependency source of the project.
+pub fn get_declared_dependency_sources() -> Result<Vec<DeclaredDependency>> {
+    PARSER.with(|parser| {
+        if let Some(p) = parser.borrow().as_ref() {
+            p.get_declared_dependency_sources()
+        } else {
+            Ok(Vec::new())
+        }
+    })
+}
+
+pub fn get_extras() -> Result<BTreeMap<String, Vec<String>>> {
+    PARSER.with(|parser| {
+        if let Some(p) = parser.borrow().as_ref() {
+            p.get_extras()
+        } else {
+            Ok(BTreeMap::new())
+        }
+    })
+}
+
 pub fn get_used_externals() -> Result<Vec<String>> {
     PARSER.with(|parser| {
         if let Some(p) = parser.borrow().as_ref() {
@@ -292,16 +1317,18 @@ pub fn compute_module_name(file_path: &Path, project_root: &Path) -> Result<Stri
 
     // Add all directory components from the relative path
     for component in relative_path.components() {
-        if let std::path::Component::Normal(name) = component {
-            if let Some(name_str) = name.to_str() {
-                if name_str.ends_with(".py") {
-                    let file_stem = name_str.strip_suffix(".py").unwrap();
-                    if file_stem != "__init__" {
-                        parts.push(file_stem.to_string());
-                    }
-                } else {
-                    parts.push(name_str.to_string());
+        if let std::path::Component::Normal(name) = component
+            && let Some(name_str) = name.to_str()
+        {
+            if let Some(file_stem) = name_str
+                .strip_suffix(".py")
+                .or_else(|| name_str.strip_suffix(".pyi"))
+            {
+                if file_stem != "__init__" {
+                    parts.push(file_stem.to_string());
                 }
+            } else {
+                parts.push(name_str.to_string());
             }
         }
     }
@@ -371,14 +1398,17 @@ packages = [
             PackageInfo {
                 name: "medcat".to_string(),
                 directory: "ehr_data_formatter/medcat/".to_string(),
+                auto_detected: false,
             },
             PackageInfo {
                 name: "ehr_data_formatter".to_string(),
                 directory: "ehr_data_formatter/".to_string(),
+                auto_detected: false,
             },
             PackageInfo {
                 name: "other".to_string(),
                 directory: "other/".to_string(),
+                auto_detected: false,
             },
         ];
 
@@ -458,11 +1488,443 @@ matplotlib = "^3.8.2"
         assert_eq!(deps.len(), 6);
     }
 
+    #[test]
+    fn test_get_declared_dependencies_pep621() {
+        let temp_dir = TempDir::new().unwrap();
+        let pyproject_content = r#"
+[project]
+name = "myapp"
+dependencies = [
+    "requests[socks]>=2.31,<3",
+    "Django (>=4.2)",
+    "typing_extensions; python_version < '3.11'",
+    "internal-lib @ git+https://example.com/internal-lib.git",
+]
+
+[project.optional-dependencies]
+postgres = ["psycopg[binary]>=3"]
+"#;
+        fs::write(temp_dir.path().join("pyproject.toml"), pyproject_content).unwrap();
+
+        let parser = PyProjectParser::new(temp_dir.path());
+        let deps = parser.get_declared_dependencies().unwrap();
+        assert_eq!(
+            deps,
+            vec![
+                "django",
+                "internal-lib",
+                "psycopg",
+                "requests",
+                "typing-extensions"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_grouped_dependencies_dependency_groups() {
+        let temp_dir = TempDir::new().unwrap();
+        let pyproject_content = r#"
+[project]
+dependencies = ["httpx"]
+
+[dependency-groups]
+Test = ["pytest>=8", "faker"]
+typing = ["mypy", { include-group = "test" }]
+lint = ["ruff", { include-group = "lint" }]
+"#;
+        fs::write(temp_dir.path().join("pyproject.toml"), pyproject_content).unwrap();
+
+        let parser = PyProjectParser::new(temp_dir.path());
+        let mut deps = parser.get_grouped_dependencies().unwrap();
+        deps.sort();
+        let deps: Vec<(&str, DependencyGroup)> = deps
+            .iter()
+            .map(|(name, group)| (name.as_str(), *group))
+            .collect();
+        assert_eq!(
+            deps,
+            vec![
+                ("faker", DependencyGroup::Dev),
+                ("faker", DependencyGroup::Test),
+                ("httpx", DependencyGroup::Runtime),
+                ("mypy", DependencyGroup::Dev),
+                ("pytest", DependencyGroup::Dev),
+                ("pytest", DependencyGroup::Test),
+                ("ruff", DependencyGroup::Dev),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_package_info_build_backends() {
+        // setuptools: package discovery in src/, with include/exclude patterns
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for dir in ["src/alpha/sub", "src/beta", "src/benchmarks", "src/data"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("src/alpha/__init__.py"), "").unwrap();
+        fs::write(root.join("src/alpha/sub/core.py"), "").unwrap();
+        fs::write(root.join("src/beta/module.py"), "").unwrap(); // Namespace package
+        fs::write(root.join("src/benchmarks/__init__.py"), "").unwrap();
+        fs::write(root.join("src/data/table.csv"), "").unwrap();
+        fs::write(
+            root.join("pyproject.toml"),
+            r#"
+[build-system]
+requires = ["setuptools>=61"]
+build-backend = "setuptools.build_meta"
+
+[tool.setuptools]
+py-modules = ["cli"]
+package-dir = { "" = "src" }
+
+[tool.setuptools.packages.find]
+where = ["src"]
+exclude = ["bench*"]
+"#,
+        )
+        .unwrap();
+
+        let parser = PyProjectParser::new(root);
+        let mut packages: Vec<(&str, &str)> = parser
+            .get_package_info()
+            .iter()
+            .map(|p| (p.name.as_str(), p.directory.as_str()))
+            .collect();
+        packages.sort();
+        assert_eq!(
+            packages,
+            vec![
+                ("alpha", "src/alpha"),
+                ("beta", "src/beta"),
+                ("cli", "src/cli")
+            ]
+        );
+        assert_eq!(
+            parser.normalize_module_name("src.alpha.sub.core").unwrap(),
+            "alpha.sub.core"
+        );
+        assert_eq!(parser.normalize_module_name("src.alpha").unwrap(), "alpha");
+        assert_eq!(parser.normalize_module_name("src.cli").unwrap(), "cli");
+
+        // hatchling, flit and pdm name their packages explicitly
+        let backends = [
+            (
+                "[tool.hatch.build.targets.wheel]\npackages = [\"src/gamma\"]\n",
+                ("gamma", "src/gamma"),
+            ),
+            (
+                "[tool.flit.module]\nname = \"delta\"\n",
+                ("delta", "src/delta"),
+            ),
+            (
+                "[tool.pdm.build]\nincludes = [\"lib/epsilon\", \"*.md\"]\n",
+                ("epsilon", "lib/epsilon"),
+            ),
+            (
+                "[tool.setuptools]\npackages = [\"zeta\", \"zeta.api\"]\n",
+                ("zeta", "zeta"),
+            ),
+        ];
+        for (config, expected) in backends {
+            let temp_dir = TempDir::new().unwrap();
+            fs::create_dir_all(temp_dir.path().join("src/delta")).unwrap();
+            fs::write(temp_dir.path().join("pyproject.toml"), config).unwrap();
+
+            let parser = PyProjectParser::new(temp_dir.path());
+            let packages: Vec<(&str, &str)> = parser
+                .get_package_info()
+                .iter()
+                .map(|p| (p.name.as_str(), p.directory.as_str()))
+                .collect();
+            assert_eq!(packages, vec![expected], "{}", config);
+            assert!(parser.is_internal_module(&format!("{}.models", expected.0)));
+        }
+    }
+
+    #[test]
+    fn test_discover_packages_without_configuration() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for dir in ["pkg", "docs", "src/core"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("pkg/__init__.py"), "").unwrap();
+        fs::write(root.join("docs/conf.py"), "").unwrap(); // Not a package
+        fs::write(root.join("src/core/__init__.py"), "").unwrap();
+        fs::write(root.join("manage.py"), "").unwrap();
+        fs::write(root.join("pyproject.toml"), "[project]\nname = \"demo\"\n").unwrap();
+
+        let parser = PyProjectParser::new(root);
+        let packages: Vec<(&str, &str, bool)> = parser
+            .get_package_info()
+            .iter()
+            .map(|p| (p.name.as_str(), p.directory.as_str(), p.auto_detected))
+            .collect();
+        assert_eq!(
+            packages,
+            vec![
+                ("core", "src/core", true),
+                ("manage", "manage", true),
+                ("pkg", "pkg", true),
+            ]
+        );
+        assert!(parser.is_internal_module("core.models"));
+        assert!(!parser.is_internal_module("docs"));
+        assert_eq!(
+            parser.normalize_module_name("src.core.models").unwrap(),
+            "core.models"
+        );
+
+        // Configured source roots replace the root and `src/`
+        let parser = PyProjectParser::new(root).with_source_roots(&["src/".to_string()]);
+        let packages: Vec<&str> = parser
+            .get_package_info()
+            .iter()
+            .map(|p| p.directory.as_str())
+            .collect();
+        assert_eq!(packages, vec!["src/core"]);
+
+        // Configured packages take precedence over discovery
+        fs::write(
+            root.join("pyproject.toml"),
+            "[tool.poetry]\npackages = [{ include = \"pkg\" }]\n",
+        )
+        .unwrap();
+        let parser = PyProjectParser::new(root);
+        let packages = parser.get_package_info();
+        assert_eq!(packages.len(), 1);
+        assert!(!packages[0].auto_detected);
+    }
+
+    #[test]
+    fn test_setup_cfg() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("src/legacy_app")).unwrap();
+        fs::create_dir_all(root.join("src/legacy_tests")).unwrap();
+        fs::write(root.join("src/legacy_app/__init__.py"), "").unwrap();
+        fs::write(root.join("src/legacy_tests/__init__.py"), "").unwrap();
+        fs::write(
+            root.join("setup.cfg"),
+            r#"
+[metadata]
+name = legacy-app
+
+[options]
+package_dir =
+    =src
+packages = find:
+install_requires =
+    requests>=2.31,<3
+    PyYAML  # config files
+    importlib-metadata; python_version < "3.10"
+
+[options.packages.find]
+where = src
+exclude =
+    legacy_tests*
+
+[options.extras_require]
+fast = ujson
+testing =
+    pytest
+"#,
+        )
+        .unwrap();
+
+        let parser = PyProjectParser::new(root);
+        let packages: Vec<(&str, &str)> = parser
+            .get_package_info()
+            .iter()
+            .map(|p| (p.name.as_str(), p.directory.as_str()))
+            .collect();
+        assert_eq!(packages, vec![("legacy_app", "src/legacy_app")]);
+
+        assert_eq!(
+            parser.get_setup_dependencies("setup.cfg").unwrap(),
+            vec![
+                ("requests".to_string(), DependencyGroup::Runtime),
+                ("pyyaml".to_string(), DependencyGroup::Runtime),
+                ("importlib-metadata".to_string(), DependencyGroup::Runtime),
+                ("ujson".to_string(), DependencyGroup::Runtime),
+                ("pytest".to_string(), DependencyGroup::Test),
+            ]
+        );
+        let extras = parser.get_extras().unwrap();
+        assert_eq!(extras.get("fast"), Some(&vec!["ujson".to_string()]));
+    }
+
+    #[test]
+    fn test_setup_py() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("lib/old_pkg")).unwrap();
+        fs::write(root.join("lib/old_pkg/__init__.py"), "").unwrap();
+        fs::write(
+            root.join("setup.py"),
+            r#"
+import os
+from setuptools import setup
+
+REQUIREMENTS = ["numpy>=1.24", "scikit_learn"]
+
+if __name__ == "__main__":
+    setup(
+        name="old-pkg",
+        version=os.environ.get("VERSION", "0.1"),
+        packages=["old_pkg", "old_pkg.sub"],
+        package_dir={"": "lib"},
+        py_modules=("tool",),
+        install_requires=REQUIREMENTS,
+        extras_require={"dev": ["black"], "plots": ["matplotlib"]},
+    )
+"#,
+        )
+        .unwrap();
+
+        let parser = PyProjectParser::new(root);
+        let packages: Vec<(&str, &str)> = parser
+            .get_package_info()
+            .iter()
+            .map(|p| (p.name.as_str(), p.directory.as_str()))
+            .collect();
+        assert_eq!(
+            packages,
+            vec![("tool", "lib/tool"), ("old_pkg", "lib/old_pkg")]
+        );
+        assert_eq!(
+            parser.normalize_module_name("lib.old_pkg.core").unwrap(),
+            "old_pkg.core"
+        );
+
+        assert_eq!(
+            parser.get_setup_dependencies("setup.py").unwrap(),
+            vec![
+                ("numpy".to_string(), DependencyGroup::Runtime),
+                ("scikit-learn".to_string(), DependencyGroup::Runtime),
+                ("black".to_string(), DependencyGroup::Dev),
+                ("matplotlib".to_string(), DependencyGroup::Runtime),
+            ]
+        );
+
+        // `find_packages` calls, and files that cannot be evaluated statically
+        let config = SetupConfig::from_setup_py(
+            "import setuptools\nsetuptools.setup(packages=setuptools.find_packages(where='src', exclude=['tests']))\n",
+        )
+        .unwrap();
+        assert_eq!(
+            config.packages,
+            Some(SetupPackages::Find {
+                roots: vec!["src".to_string()],
+                include: Vec::new(),
+                exclude: vec!["tests".to_string()],
+                namespaces: false,
+            })
+        );
+        assert_eq!(SetupConfig::from_setup_py("print('no setup call')\n"), None);
+        assert_eq!(SetupConfig::from_setup_py("setup(\n"), None);
+    }
+
+    #[test]
+    fn test_namespace_package_portions() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for dir in [
+            "company/team_a",
+            "src/company/team_b",
+            "src/company/team_b/sub",
+        ] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join("company/team_a/__init__.py"), "").unwrap();
+        fs::write(root.join("src/company/team_b/__init__.py"), "").unwrap();
+        fs::write(root.join("src/company/team_b/sub/__init__.py"), "").unwrap();
+
+        // Discovered portions from several source roots share the `company` namespace
+        let parser = PyProjectParser::new(root);
+        let packages: Vec<(&str, &str)> = parser
+            .get_package_info()
+            .iter()
+            .map(|p| (p.name.as_str(), p.directory.as_str()))
+            .collect();
+        assert_eq!(
+            packages,
+            vec![
+                ("company.team_a", "company/team_a"),
+                ("company.team_b", "src/company/team_b"),
+            ]
+        );
+        assert!(parser.is_internal_module("company.team_a.models"));
+        assert!(parser.is_internal_module("company.team_b"));
+        assert!(!parser.is_internal_module("company.team_c"));
+        assert!(!parser.is_internal_module("company"));
+        assert_eq!(
+            parser
+                .normalize_module_name("src.company.team_b.sub.api")
+                .unwrap(),
+            "company.team_b.sub.api"
+        );
+
+        // Configured portions sharing a source root
+        fs::write(
+            root.join("pyproject.toml"),
+            r#"
+[tool.poetry]
+packages = [
+    { include = "company/team_a", from = "lib" },
+    { include = "company/team_b", from = "lib" },
+]
+"#,
+        )
+        .unwrap();
+        let parser = PyProjectParser::new(root);
+        assert_eq!(parser.get_package_info().len(), 2);
+        assert_eq!(
+            parser
+                .normalize_module_name("lib.company.team_b.api")
+                .unwrap(),
+            "company.team_b.api"
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("bench*", "benchmarks"));
+        assert!(glob_match("t?sts", "tests"));
+        assert!(!glob_match("bench*", "mybench"));
+        assert!(matches_package_pattern("mypkg.*", "mypkg"));
+        assert!(!matches_package_pattern("mypkg.*", "mypkg2"));
+    }
+
+    #[test]
+    fn test_get_extras() {
+        let temp_dir = TempDir::new().unwrap();
+        let pyproject_content = r#"
+[project.optional-dependencies]
+fast = ["ujson>=5.0", "orjson ; python_version >= '3.8'"]
+yaml = ["PyYAML[libyaml]~=6.0"]
+
+[tool.poetry.extras]
+fast = ["uvloop"]
+"#;
+        fs::write(temp_dir.path().join("pyproject.toml"), pyproject_content).unwrap();
+
+        let parser = PyProjectParser::new(temp_dir.path());
+        let extras = parser.get_extras().unwrap();
+
+        assert_eq!(extras.len(), 2);
+        assert_eq!(extras["fast"], vec!["orjson", "ujson", "uvloop"]);
+        assert_eq!(extras["yaml"], vec!["pyyaml"]);
+    }
+
     #[test]
     fn test_get_used_externals_empty_file() {
         let temp_dir = TempDir::new().unwrap();
         let parser = PyProjectParser::new(temp_dir.path());
-        
+
         // No .used-externals.txt file should return empty vec
         let externals = parser.get_used_externals().unwrap();
         assert!(externals.is_empty());
@@ -486,7 +1948,11 @@ pytest-asyncio
 
 Django_REST_Framework  # Should be normalized to django-rest-framework
 "#;
-        fs::write(temp_dir.path().join(".used-externals.txt"), used_externals_content).unwrap();
+        fs::write(
+            temp_dir.path().join(".used-externals.txt"),
+            used_externals_content,
+        )
+        .unwrap();
 
         let parser = PyProjectParser::new(temp_dir.path());
         let externals = parser.get_used_externals().unwrap();
@@ -498,7 +1964,7 @@ Django_REST_Framework  # Should be normalized to django-rest-framework
         assert!(externals.contains(&"sqlalchemy".to_string()));
         assert!(externals.contains(&"pytest-asyncio".to_string()));
         assert!(externals.contains(&"django-rest-framework".to_string()));
-        
+
         // Should be sorted
         assert_eq!(externals[0], "django-rest-framework");
         assert_eq!(externals[1], "psycopg2-binary");
@@ -518,7 +1984,11 @@ redis
     matplotlib    # Comment at end
 
 "#;
-        fs::write(temp_dir.path().join(".used-externals.txt"), used_externals_content).unwrap();
+        fs::write(
+            temp_dir.path().join(".used-externals.txt"),
+            used_externals_content,
+        )
+        .unwrap();
 
         let parser = PyProjectParser::new(temp_dir.path());
         let externals = parser.get_used_externals().unwrap();
@@ -538,7 +2008,11 @@ NumPy  # Should normalize to same as above
 NUMPY  # Should normalize to same as above
 requests
 "#;
-        fs::write(temp_dir.path().join(".used-externals.txt"), used_externals_content).unwrap();
+        fs::write(
+            temp_dir.path().join(".used-externals.txt"),
+            used_externals_content,
+        )
+        .unwrap();
 
         let parser = PyProjectParser::new(temp_dir.path());
         let externals = parser.get_used_externals().unwrap();
@@ -0,0 +1,112 @@
+/// A PEP 508 dependency specifier, such as `requests[socks]>=2.31; python_version < "3.13"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// Distribution name as written (e.g. "PyYAML")
+    pub name: String,
+    /// Extras requested from the distribution (e.g. ["socks"])
+    pub extras: Vec<String>,
+    /// Version specifier without parentheses (e.g. ">=2.31,<3")
+    pub specifier: Option<String>,
+    /// Direct reference of `name @ url` requirements
+    pub url: Option<String>,
+    /// Environment marker after the `;` (e.g. `python_version < "3.13"`)
+    pub marker: Option<String>,
+}
+
+impl Requirement {
+    /// Parses a PEP 508 requirement string, returning `None` if it does not start with
+    /// a distribution name.
+    pub fn parse(requirement: &str) -> Option<Self> {
+        let (requirement, marker) = match requirement.split_once(';') {
+            Some((requirement, marker)) => (requirement, non_empty(marker)),
+            None => (requirement, None),
+        };
+
+        let requirement = requirement.trim();
+        let end = requirement
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(requirement.len());
+        let name = &requirement[..end];
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut rest = requirement[end..].trim_start();
+        let mut extras = Vec::new();
+        if let Some(bracketed) = rest.strip_prefix('[') {
+            let (list, after) = bracketed.split_once(']')?;
+            extras = list
+                .split(',')
+                .map(str::trim)
+                .filter(|extra| !extra.is_empty())
+                .map(str::to_string)
+                .collect();
+            rest = after.trim_start();
+        }
+
+        let (specifier, url) = match rest.strip_prefix('@') {
+            Some(url) => (None, non_empty(url)),
+            None => {
+                let specifier = rest
+                    .strip_prefix('(')
+                    .and_then(|inner| inner.strip_suffix(')'))
+                    .unwrap_or(rest);
+                (non_empty(specifier), None)
+            }
+        };
+
+        Some(Self {
+            name: name.to_string(),
+            extras,
+            specifier,
+            url,
+            marker,
+        })
+    }
+}
+
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requirement() {
+        let requirement =
+            Requirement::parse(r#"requests [socks, security] >=2.31,<3 ; python_version < "3.13""#)
+                .unwrap();
+        assert_eq!(requirement.name, "requests");
+        assert_eq!(requirement.extras, vec!["socks", "security"]);
+        assert_eq!(requirement.specifier.as_deref(), Some(">=2.31,<3"));
+        assert_eq!(requirement.url, None);
+        assert_eq!(
+            requirement.marker.as_deref(),
+            Some(r#"python_version < "3.13""#)
+        );
+
+        let requirement = Requirement::parse("PyYAML (~=6.0)").unwrap();
+        assert_eq!(requirement.name, "PyYAML");
+        assert!(requirement.extras.is_empty());
+        assert_eq!(requirement.specifier.as_deref(), Some("~=6.0"));
+
+        let requirement =
+            Requirement::parse("my_lib @ https://example.com/my_lib-1.0.tar.gz").unwrap();
+        assert_eq!(requirement.name, "my_lib");
+        assert_eq!(
+            requirement.url.as_deref(),
+            Some("https://example.com/my_lib-1.0.tar.gz")
+        );
+        assert_eq!(requirement.specifier, None);
+
+        assert_eq!(
+            Requirement::parse("zope.interface").unwrap().name,
+            "zope.interface"
+        );
+        assert_eq!(Requirement::parse(">=1.0"), None);
+        assert_eq!(Requirement::parse("broken[extra"), None);
+    }
+}
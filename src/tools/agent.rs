@@ -42,16 +42,33 @@ Target Analysis Commands:
                          Example: pydep-mapper dependencies api.views
                          Output: All imports used by api.views (internal + external)
 
+  usages SYMBOL        → Find modules importing a top-level function, class or constant
+                         Example: pydep-mapper usages billing.charge_card
+                         Output: Importing modules with file:line (imports via package facades included)
+                         Use: Check a public API can be removed or renamed safely
+
 Global Options:
   --root DIR           → Analyze specific directory (default: current dir)
                          Example: pydep-mapper --root /path/to/project pressure
+  --trace-reexports    → Route `from pkg import Name` to the module defining Name when
+                         pkg/__init__.py re-exports it (`from .x import Name`, `__all__`)
+                         Example: pydep-mapper --trace-reexports impact myapp.models.user
+  --format json        → Print the result and diagnostics as JSON
+                         Example: pydep-mapper impact auth.models --format json
+  --strict             → Exit with status 1 if any file or import could not be analyzed
 
 Changeset Scope Options:
   --scope affected     → Show only what breaks if module changes
   --scope dependencies → Show only what module depends on
   --scope both         → Show both (default)
 
+Type-Only Imports (impact, changeset, cycles):
+  --include-type-only  → Also follow imports under `if TYPE_CHECKING:` and in string annotations
+                         Type-only cycles are flagged as harmless at runtime
+
 Output Format: Hierarchical text with submodule counts, excludes test modules
+Diagnostics: Files that fail to parse or read and unresolvable relative imports are listed
+             in a final "=== Diagnostics ===" section (the graph may miss their imports)
 "#
     );
 }
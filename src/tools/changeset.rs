@@ -1,10 +1,11 @@
-use crate::graph::DependencyGraph;
+use crate::graph::{DependencyGraph, DependencyType, SourceLocation};
 use crate::imports::{ModuleIdentifier, ModuleOrigin};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
 /// Scope of changeset analysis
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum ChangesetScope {
     /// Show what would be affected if the module changes
     Affected,
@@ -15,6 +16,7 @@ pub enum ChangesetScope {
 }
 
 impl ChangesetScope {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s.to_lowercase().as_str() {
             "affected" => ChangesetScope::Affected,
@@ -26,7 +28,7 @@ impl ChangesetScope {
 }
 
 /// Risk level for modules in a changeset
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 pub enum RiskLevel {
     /// Low risk - few dependents, well isolated
     Low,
@@ -38,18 +40,67 @@ pub enum RiskLevel {
     Critical,
 }
 
+/// Minimum dependent counts of the medium, high and critical risk levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskThresholds {
+    pub medium: usize,
+    pub high: usize,
+    pub critical: usize,
+}
+
+impl Default for RiskThresholds {
+    fn default() -> Self {
+        Self {
+            medium: 3,
+            high: 11,
+            critical: 51,
+        }
+    }
+}
+
+impl RiskThresholds {
+    /// Checks that the levels are in increasing order.
+    pub fn validate(&self) -> Result<()> {
+        if self.medium > self.high || self.high > self.critical {
+            return Err(anyhow::anyhow!(
+                "Risk thresholds must be increasing (medium <= high <= critical), got {}, {} and {}",
+                self.medium,
+                self.high,
+                self.critical
+            ));
+        }
+        Ok(())
+    }
+
+    /// Assesses the risk level of a module from its number of dependents.
+    pub fn risk_level(&self, dependent_count: usize) -> RiskLevel {
+        if dependent_count >= self.critical {
+            RiskLevel::Critical
+        } else if dependent_count >= self.high {
+            RiskLevel::High
+        } else if dependent_count >= self.medium {
+            RiskLevel::Medium
+        } else {
+            RiskLevel::Low
+        }
+    }
+}
+
 /// A module in the changeset with its risk assessment
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ChangesetModule {
     pub module_name: String,
     pub risk_level: RiskLevel,
     pub dependent_count: usize,
     pub dependency_depth: usize,
     pub is_external: bool,
+    /// Import statements linking the module to the target
+    pub import_locations: Vec<SourceLocation>,
 }
 
 /// Raw data from changeset analysis
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ChangesetResult {
     /// Target module being analyzed
     pub target_module: String,
@@ -68,10 +119,30 @@ pub struct ChangesetResult {
 }
 
 /// Performs changeset analysis on a module
+///
+/// Type-only imports are ignored unless `include_type_only` is set.
 pub fn analyze_changeset(
     graph: &DependencyGraph,
     module_name: &str,
     scope: ChangesetScope,
+    include_type_only: bool,
+) -> Result<ChangesetResult> {
+    analyze_changeset_with_thresholds(
+        graph,
+        module_name,
+        scope,
+        include_type_only,
+        &RiskThresholds::default(),
+    )
+}
+
+/// Performs changeset analysis on a module, assessing risk levels with custom thresholds
+pub fn analyze_changeset_with_thresholds(
+    graph: &DependencyGraph,
+    module_name: &str,
+    scope: ChangesetScope,
+    include_type_only: bool,
+    thresholds: &RiskThresholds,
 ) -> Result<ChangesetResult> {
     // Find the target module
     let target_module_id = find_module_by_name(graph, module_name)?;
@@ -81,12 +152,14 @@ pub fn analyze_changeset(
 
     // Analyze affected modules (what breaks if target changes)
     if matches!(scope, ChangesetScope::Affected | ChangesetScope::Both) {
-        affected_modules = analyze_affected_modules(graph, &target_module_id)?;
+        affected_modules =
+            analyze_affected_modules(graph, &target_module_id, include_type_only, thresholds)?;
     }
 
     // Analyze dependencies (what target needs)
     if matches!(scope, ChangesetScope::Dependencies | ChangesetScope::Both) {
-        dependency_modules = analyze_dependency_modules(graph, &target_module_id)?;
+        dependency_modules =
+            analyze_dependency_modules(graph, &target_module_id, include_type_only, thresholds)?;
     }
 
     // Generate test execution order (three-tier: dependencies → target → affected)
@@ -130,9 +203,11 @@ fn find_module_by_name(graph: &DependencyGraph, module_name: &str) -> Result<Mod
 fn analyze_affected_modules(
     graph: &DependencyGraph,
     target_module: &ModuleIdentifier,
+    include_type_only: bool,
+    thresholds: &RiskThresholds,
 ) -> Result<Vec<ChangesetModule>> {
     // Use import-only traversal to get modules that directly import the target
-    let mut affected_module_names = get_import_dependents(graph, target_module)?;
+    let mut affected_module_names = get_import_dependents(graph, target_module, include_type_only)?;
 
     // Filter out test modules
     affected_module_names
@@ -143,14 +218,16 @@ fn analyze_affected_modules(
 
     // Get dependent counts for risk assessment
     let dependent_counts = calculate_dependent_counts(graph)?;
+    let mut import_locations = graph.get_transitive_dependent_locations(target_module)?;
 
     let mut modules = Vec::new();
 
     for module_name in affected_module_names {
         let dependent_count = dependent_counts.get(&module_name).unwrap_or(&0);
-        let risk_level = assess_risk_level(*dependent_count, 0);
+        let risk_level = thresholds.risk_level(*dependent_count);
 
         modules.push(ChangesetModule {
+            import_locations: import_locations.remove(&module_name).unwrap_or_default(),
             module_name,
             risk_level,
             dependent_count: *dependent_count,
@@ -169,9 +246,12 @@ fn analyze_affected_modules(
 fn analyze_dependency_modules(
     graph: &DependencyGraph,
     target_module: &ModuleIdentifier,
+    include_type_only: bool,
+    thresholds: &RiskThresholds,
 ) -> Result<Vec<ChangesetModule>> {
     // Use import-only traversal to get modules that target directly imports
-    let mut dependency_module_names = get_import_dependencies(graph, target_module)?;
+    let mut dependency_module_names =
+        get_import_dependencies(graph, target_module, include_type_only)?;
 
     // Filter out test modules
     dependency_module_names
@@ -182,14 +262,16 @@ fn analyze_dependency_modules(
 
     // Get dependent counts for risk assessment
     let dependent_counts = calculate_dependent_counts(graph)?;
+    let mut import_locations = graph.get_transitive_dependency_locations(target_module)?;
 
     let mut modules = Vec::new();
 
     for module_name in dependency_module_names {
         let dependent_count = dependent_counts.get(&module_name).unwrap_or(&0);
-        let risk_level = assess_risk_level(*dependent_count, 0);
+        let risk_level = thresholds.risk_level(*dependent_count);
 
         modules.push(ChangesetModule {
+            import_locations: import_locations.remove(&module_name).unwrap_or_default(),
             module_name,
             risk_level,
             dependent_count: *dependent_count,
@@ -216,16 +298,6 @@ fn calculate_dependent_counts(graph: &DependencyGraph) -> Result<HashMap<String,
     Ok(counts)
 }
 
-/// Assess risk level based on dependent count and other factors
-fn assess_risk_level(dependent_count: usize, _dependency_depth: usize) -> RiskLevel {
-    match dependent_count {
-        0..=2 => RiskLevel::Low,
-        3..=10 => RiskLevel::Medium,
-        11..=50 => RiskLevel::High,
-        _ => RiskLevel::Critical,
-    }
-}
-
 /// Check if a module is external
 fn is_external_module(graph: &DependencyGraph, module_name: &str) -> bool {
     for module in graph.all_modules() {
@@ -236,10 +308,20 @@ fn is_external_module(graph: &DependencyGraph, module_name: &str) -> bool {
     false
 }
 
+/// Check if a relationship is an import that the analysis should follow
+fn is_followed_import(dep_type: &DependencyType, include_type_only: bool) -> bool {
+    match dep_type {
+        DependencyType::Imports | DependencyType::DynamicImports => true,
+        DependencyType::TypeOnlyImports => include_type_only,
+        _ => false,
+    }
+}
+
 /// Get modules that directly import the target module (import-only, no containment)
 fn get_import_dependents(
     graph: &DependencyGraph,
     target_module: &ModuleIdentifier,
+    include_type_only: bool,
 ) -> Result<Vec<String>> {
     let mut dependents = Vec::new();
 
@@ -249,7 +331,7 @@ fn get_import_dependents(
 
         for (dep_module, dep_type) in dependencies {
             // Only follow Imports edges, ignore containment relationships
-            if dep_type == crate::graph::DependencyType::Imports
+            if is_followed_import(&dep_type, include_type_only)
                 && dep_module == target_module.canonical_path
             {
                 dependents.push(module.canonical_path.clone());
@@ -265,6 +347,7 @@ fn get_import_dependents(
 fn get_import_dependencies(
     graph: &DependencyGraph,
     target_module: &ModuleIdentifier,
+    include_type_only: bool,
 ) -> Result<Vec<String>> {
     let dependencies = graph.get_dependencies_with_types(target_module)?;
 
@@ -272,7 +355,7 @@ fn get_import_dependencies(
     let import_deps: Vec<String> = dependencies
         .into_iter()
         .filter_map(|(dep_module, dep_type)| {
-            if dep_type == crate::graph::DependencyType::Imports {
+            if is_followed_import(&dep_type, include_type_only) {
                 Some(dep_module)
             } else {
                 None
@@ -332,6 +415,7 @@ fn generate_test_order(
 /// Formatters for changeset results
 pub mod formatters {
     use super::{ChangesetResult, RiskLevel};
+    use crate::tools::common::formatters as common_formatters;
 
     /// Formats results as human-readable text
     pub fn format_text_grouped(result: &ChangesetResult) -> String {
@@ -441,8 +525,10 @@ pub mod formatters {
 
                 for module in risk_modules {
                     output.push_str(&format!(
-                        "  • {} ({} dependents)\n",
-                        module.module_name, module.dependent_count
+                        "  • {} ({} dependents){}\n",
+                        module.module_name,
+                        module.dependent_count,
+                        common_formatters::format_locations(Some(&module.import_locations))
                     ));
                 }
                 output.push('\n');
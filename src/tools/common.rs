@@ -1,6 +1,12 @@
-use crate::graph::DependencyType;
+use crate::graph::{DependencyType, SourceLocation};
 use std::collections::HashMap;
 
+/// A deduplicated module entry: (module_path, dependency_type, represented_module_count)
+pub type GroupedModule = (String, DependencyType, usize);
+
+/// Import statement locations keyed by the module at the other end of the import
+pub type ImportLocations = HashMap<String, Vec<SourceLocation>>;
+
 /// Deduplicates a list of modules by removing children when their parent is present,
 /// and tracks how many original modules each deduplicated entry represents.
 pub fn filter_hierarchical(
@@ -60,12 +66,33 @@ pub fn filter_hierarchical(
 
 /// Common formatting functionality for hierarchical module display
 pub mod formatters {
-    use crate::graph::DependencyType;
+    use super::ImportLocations;
+    use crate::graph::{DependencyType, SourceLocation};
     use std::collections::HashMap;
 
     // Constants for formatting
     const INDENT: &str = "  ";
     const DOT_SEPARATOR: &str = ".";
+    const MAX_LOCATIONS_SHOWN: usize = 3;
+
+    /// Formats import locations as a suffix: " (a/b.py:3, a/c.py:12, +2 more)".
+    ///
+    /// Returns an empty string when there are no locations.
+    pub fn format_locations(locations: Option<&Vec<SourceLocation>>) -> String {
+        let Some(locations) = locations.filter(|l| !l.is_empty()) else {
+            return String::new();
+        };
+
+        let mut shown: Vec<String> = locations
+            .iter()
+            .take(MAX_LOCATIONS_SHOWN)
+            .map(|location| location.to_string())
+            .collect();
+        if locations.len() > MAX_LOCATIONS_SHOWN {
+            shown.push(format!("+{} more", locations.len() - MAX_LOCATIONS_SHOWN));
+        }
+        format!(" ({})", shown.join(", "))
+    }
 
     /// Calculates prefix counts for hierarchical grouping
     pub fn calculate_prefix_counts(
@@ -108,7 +135,13 @@ pub mod formatters {
     }
 
     /// Main function for formatting modules with hierarchical grouping
-    pub fn format_grouped_modules(modules: &[(String, DependencyType, usize)]) -> String {
+    ///
+    /// Modules that stand for themselves only (no grouped submodules) are followed by
+    /// their import locations, if any.
+    pub fn format_grouped_modules(
+        modules: &[(String, DependencyType, usize)],
+        locations: &ImportLocations,
+    ) -> String {
         let mut output = String::new();
         let mut current_prefix: Vec<String> = Vec::new();
         let prefix_counts = calculate_prefix_counts(modules);
@@ -144,7 +177,16 @@ pub mod formatters {
                     prefix_counts.get(&current_path).copied().filter(|&c| c > 1)
                 };
 
-                output.push_str(&format_segment(i, segment, segment_count, is_root_segment));
+                let mut line = format_segment(i, segment, segment_count, is_root_segment);
+                if is_final_segment && *count <= 1 {
+                    let suffix = format_locations(locations.get(module_path));
+                    if !suffix.is_empty() {
+                        line.pop(); // Trailing newline
+                        line.push_str(&suffix);
+                        line.push('\n');
+                    }
+                }
+                output.push_str(&line);
             }
 
             current_prefix = segments;
@@ -1,19 +1,37 @@
-use crate::graph::{DependencyGraph, DependencyType};
+use crate::graph::{DependencyGraph, DependencyType, SourceLocation};
 use crate::imports::ModuleIdentifier;
 use anyhow::{Context, Result, anyhow};
 use petgraph::graph::NodeIndex;
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
 
 /// Represents a detected circular dependency cycle
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
 pub struct Cycle {
     /// The modules in the cycle, in order (without repeating the first at the end)
     pub modules: Vec<String>,
+    /// True if the cycle only exists through type-only imports (harmless at runtime)
+    pub type_only: bool,
+    /// For each step `modules[i] → modules[i + 1]`, the import statement creating it
+    pub locations: Vec<Option<SourceLocation>>,
 }
 
 impl Cycle {
     pub fn new(modules: Vec<String>) -> Self {
-        Self { modules }
+        Self {
+            modules,
+            type_only: false,
+            locations: Vec::new(),
+        }
+    }
+
+    /// Iterates over the steps of the cycle: (importer, imported, import location).
+    pub fn steps(&self) -> impl Iterator<Item = (&str, &str, Option<&SourceLocation>)> {
+        self.modules.iter().enumerate().map(|(i, from)| {
+            let to = &self.modules[(i + 1) % self.modules.len()];
+            let location = self.locations.get(i).and_then(Option::as_ref);
+            (from.as_str(), to.as_str(), location)
+        })
     }
 
     /// "a → b → c → a"
@@ -28,7 +46,7 @@ impl Cycle {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct CycleResult {
     pub cycles: Vec<Cycle>,
 }
@@ -41,11 +59,18 @@ impl CycleResult {
     pub fn cycle_count(&self) -> usize {
         self.cycles.len()
     }
+
+    pub fn type_only_count(&self) -> usize {
+        self.cycles.iter().filter(|cycle| cycle.type_only).count()
+    }
 }
 
 /// Detect circular import dependencies using transitive dependency propagation.
 /// If a.x imports b.y, this creates a module-level dependency a -> b.
-pub fn detect_cycles(graph: &DependencyGraph) -> Result<CycleResult> {
+///
+/// Only runtime imports are followed unless `include_type_only` is set, in which case
+/// cycles that need at least one type-only import are reported too and flagged as such.
+pub fn detect_cycles(graph: &DependencyGraph, include_type_only: bool) -> Result<CycleResult> {
     // 1) Build node <-> module maps once.
     let mut module_to_node: HashMap<String, NodeIndex> = HashMap::new();
     let mut node_to_module: HashMap<NodeIndex, String> = HashMap::new();
@@ -58,8 +83,10 @@ pub fn detect_cycles(graph: &DependencyGraph) -> Result<CycleResult> {
         node_to_module.insert(idx, module.canonical_path.clone());
     }
 
-    // 2) Build adjacency with transitive dependencies (propagated through submodules).
-    let mut adj: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+    // 2) Build adjacency with transitive dependencies (propagated through submodules),
+    //    once for runtime imports only and once including type-only imports.
+    let mut runtime_adj: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
+    let mut full_adj: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
     for (module_name, &src) in module_to_node.iter() {
         let module_id = find_module_by_name_cached(graph, module_name)
             .with_context(|| format!("Module '{}' not found", module_name))?;
@@ -67,42 +94,128 @@ pub fn detect_cycles(graph: &DependencyGraph) -> Result<CycleResult> {
             .get_transitive_dependencies_with_types(&module_id)
             .with_context(|| format!("Failed to get transitive deps for '{}'", module_name))?;
 
-        let import_targets = deps
-            .into_iter()
-            .filter(|(_, ty)| *ty == DependencyType::Imports)
-            .filter_map(|(dep_name, _)| module_to_node.get(&dep_name).copied())
-            .collect::<Vec<_>>();
+        for (dep_name, ty) in deps {
+            let Some(&target) = module_to_node.get(&dep_name) else {
+                continue;
+            };
+            match ty {
+                DependencyType::Imports | DependencyType::DynamicImports => {
+                    runtime_adj.entry(src).or_default().push(target);
+                    full_adj.entry(src).or_default().push(target);
+                }
+                DependencyType::TypeOnlyImports => {
+                    full_adj.entry(src).or_default().push(target);
+                }
+                _ => {}
+            }
+        }
+    }
 
-        adj.entry(src).or_default().extend(import_targets);
+    // Cycles are enumerated in module name order, so the same graph gives the same cycles
+    for targets in runtime_adj.values_mut().chain(full_adj.values_mut()) {
+        targets.sort_by_key(|target| &node_to_module[target]);
+        targets.dedup();
     }
 
-    // 3) DFS with explicit recursion stack to find back-edges -> cycles.
+    // 3) Find runtime cycles first, then cycles that only exist through type-only imports.
+    //    A set of canonicalized cycle signatures deduplicates across both passes.
+    let mut seen: HashSet<Vec<String>> = HashSet::new();
+    let mut out = find_cycles(&runtime_adj, &node_to_module, &mut seen)?;
+
+    if include_type_only {
+        // The runtime pass does not enumerate every cycle, so this pass may also find
+        // runtime ones: a cycle is type-only when one of its steps has no runtime import.
+        for mut cycle in find_cycles(&full_adj, &node_to_module, &mut seen)? {
+            cycle.type_only = !is_runtime_cycle(&cycle.modules, &runtime_adj, &module_to_node);
+            out.push(cycle);
+        }
+    }
+
+    // 4) Attach the location of the import statement behind each step of each cycle.
+    let mut locations_cache = HashMap::new();
+    for cycle in &mut out {
+        cycle.locations = locate_cycle_steps(graph, &cycle.modules, &mut locations_cache)?;
+    }
+
+    Ok(CycleResult::new(out))
+}
+
+/// Returns true if every step `a → b` of a cycle is a runtime import.
+fn is_runtime_cycle(
+    modules: &[String],
+    runtime_adj: &HashMap<NodeIndex, Vec<NodeIndex>>,
+    module_to_node: &HashMap<String, NodeIndex>,
+) -> bool {
+    modules.iter().enumerate().all(|(i, from)| {
+        let to = &modules[(i + 1) % modules.len()];
+        let (Some(from), Some(to)) = (module_to_node.get(from), module_to_node.get(to)) else {
+            return false;
+        };
+        runtime_adj
+            .get(from)
+            .is_some_and(|targets| targets.contains(to))
+    })
+}
+
+/// Finds, for each step `a → b` of a cycle, the first import of `b` by `a` or its submodules.
+fn locate_cycle_steps(
+    graph: &DependencyGraph,
+    modules: &[String],
+    cache: &mut HashMap<String, HashMap<String, Vec<SourceLocation>>>,
+) -> Result<Vec<Option<SourceLocation>>> {
+    let mut locations = Vec::with_capacity(modules.len());
+
+    for (i, from) in modules.iter().enumerate() {
+        let to = &modules[(i + 1) % modules.len()];
+        if !cache.contains_key(from) {
+            let module_id = find_module_by_name_cached(graph, from)?;
+            let from_locations = graph.get_transitive_dependency_locations(&module_id)?;
+            cache.insert(from.clone(), from_locations);
+        }
+        let location = cache
+            .get(from)
+            .and_then(|from_locations| from_locations.get(to))
+            .and_then(|to_locations| to_locations.first())
+            .cloned();
+        locations.push(location);
+    }
+
+    Ok(locations)
+}
+
+/// DFS with explicit recursion stack to find back-edges -> cycles.
+/// Cycles whose signature is already in `seen` are skipped.
+fn find_cycles(
+    adj: &HashMap<NodeIndex, Vec<NodeIndex>>,
+    node_to_module: &HashMap<NodeIndex, String>,
+    seen: &mut HashSet<Vec<String>>,
+) -> Result<Vec<Cycle>> {
     let mut visited: HashSet<NodeIndex> = HashSet::new();
     let mut stack: Vec<NodeIndex> = Vec::new();
     let mut on_stack: HashSet<NodeIndex> = HashSet::new();
-
-    // Use a set of canonicalized cycle signatures to deduplicate.
-    let mut seen: HashSet<Vec<String>> = HashSet::new();
     let mut out: Vec<Cycle> = Vec::new();
 
-    for &start in node_to_module.keys() {
+    let mut starts: Vec<(&NodeIndex, &String)> = node_to_module.iter().collect();
+    starts.sort_by_key(|(_, module)| *module);
+    for (&start, _) in starts {
         if !visited.contains(&start) {
             dfs_cycles(
                 start,
-                &adj,
+                adj,
                 &mut visited,
                 &mut stack,
                 &mut on_stack,
-                &node_to_module,
-                &mut seen,
+                node_to_module,
+                seen,
                 &mut out,
             )?;
         }
     }
 
-    Ok(CycleResult::new(out))
+    Ok(out)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn dfs_cycles(
     node: NodeIndex,
     adj: &HashMap<NodeIndex, Vec<NodeIndex>>,
@@ -146,10 +259,11 @@ fn dfs_cycles(
     Ok(())
 }
 
-/// Normalize a cycle to a canonical representation:
-/// - rotate so the lexicographically smallest string is first
-/// - pick the lexicographically smaller between forward and reversed cycles
-fn normalize_cycle(names: &mut Vec<String>) {
+/// Normalize a cycle to a canonical representation by rotating it so the
+/// lexicographically smallest string is first.
+///
+/// The direction is kept: each step of the cycle is an actual import.
+fn normalize_cycle(names: &mut [String]) {
     if names.is_empty() {
         return;
     }
@@ -161,20 +275,6 @@ fn normalize_cycle(names: &mut Vec<String>) {
         .min_by(|(_, a), (_, b)| a.cmp(b))
         .unwrap();
     names.rotate_left(min_i);
-
-    // Compare with reversed-rotated form to ensure unique direction
-    let mut rev = names.clone();
-    rev.reverse();
-    let (min_i_rev, _) = rev
-        .iter()
-        .enumerate()
-        .min_by(|(_, a), (_, b)| a.cmp(b))
-        .unwrap();
-    rev.rotate_left(min_i_rev);
-
-    if rev < *names {
-        *names = rev;
-    }
 }
 
 /// Fast path using the already-built maps; avoids scanning all modules repeatedly.
@@ -201,13 +301,160 @@ pub mod formatters {
 
         output.push_str("Circular dependencies found:\n");
         for (i, cycle) in result.cycles.iter().enumerate() {
-            output.push_str(&format!("Cycle {}: {}\n", i + 1, cycle.format_cycle()));
+            let marker = if cycle.type_only { " (type-only)" } else { "" };
+            output.push_str(&format!(
+                "Cycle {}: {}{}\n",
+                i + 1,
+                cycle.format_cycle(),
+                marker
+            ));
+            for (from, to, location) in cycle.steps() {
+                if let Some(location) = location {
+                    output.push_str(&format!("  {} → {} at {}\n", from, to, location));
+                }
+            }
         }
         output.push_str(&format!(
-            "Total: {} cycle{}\n",
+            "Total: {} cycle{}",
             result.cycle_count(),
             if result.cycle_count() == 1 { "" } else { "s" }
         ));
+        if result.type_only_count() > 0 {
+            output.push_str(&format!(
+                " ({} type-only, harmless at runtime)",
+                result.type_only_count()
+            ));
+        }
+        output.push('\n');
         output
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imports::ModuleOrigin;
+
+    fn create_test_module_id(name: &str) -> ModuleIdentifier {
+        ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_type_only_cycles() {
+        let mut graph = DependencyGraph::new();
+
+        let a = create_test_module_id("a");
+        let b = create_test_module_id("b");
+        let c = create_test_module_id("c");
+        let d = create_test_module_id("d");
+        for module in [&a, &b, &c, &d] {
+            graph.add_module(module.clone());
+        }
+
+        // a <-> b is a runtime cycle, c <-> d only closes through a type-only import
+        graph
+            .add_dependency(&a, &b, DependencyType::Imports)
+            .unwrap();
+        graph
+            .add_dependency(&b, &a, DependencyType::Imports)
+            .unwrap();
+        graph
+            .add_dependency(&c, &d, DependencyType::Imports)
+            .unwrap();
+        graph
+            .add_dependency(&d, &c, DependencyType::TypeOnlyImports)
+            .unwrap();
+
+        let runtime_only = detect_cycles(&graph, false).unwrap();
+        assert_eq!(runtime_only.cycle_count(), 1);
+        assert_eq!(runtime_only.cycles[0].modules, vec!["a", "b"]);
+        assert!(!runtime_only.cycles[0].type_only);
+
+        let with_type_only = detect_cycles(&graph, true).unwrap();
+        assert_eq!(with_type_only.cycle_count(), 2);
+        assert_eq!(with_type_only.type_only_count(), 1);
+        let type_only_cycle = with_type_only
+            .cycles
+            .iter()
+            .find(|cycle| cycle.type_only)
+            .unwrap();
+        assert_eq!(type_only_cycle.modules, vec!["c", "d"]);
+
+        let formatted = formatters::format_text_grouped(&with_type_only);
+        assert!(formatted.contains("c → d → c (type-only)"));
+        assert!(formatted.contains("Total: 2 cycles (1 type-only, harmless at runtime)"));
+    }
+
+    #[test]
+    fn test_runtime_cycles_found_with_type_only_imports() {
+        let mut graph = DependencyGraph::new();
+        let [a, b, c, d] = ["pkg.a", "pkg.b", "pkg.c", "pkg.d"].map(create_test_module_id);
+        for module in [&a, &b, &c, &d] {
+            graph.add_module(module.clone());
+        }
+        for (from, to) in [(&a, &b), (&a, &c), (&b, &a), (&c, &b)] {
+            graph
+                .add_dependency(from, to, DependencyType::Imports)
+                .unwrap();
+        }
+        graph
+            .add_dependency(&d, &c, DependencyType::TypeOnlyImports)
+            .unwrap();
+
+        // Cycles of runtime imports are never type-only, whichever pass finds them
+        for _ in 0..30 {
+            let result = detect_cycles(&graph, true).unwrap();
+            assert!(result.cycle_count() > 0);
+            assert_eq!(result.type_only_count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_cycle_step_locations() {
+        use crate::graph::DependencyEdge;
+
+        let mut graph = DependencyGraph::new();
+
+        let a = create_test_module_id("a");
+        let b = create_test_module_id("b");
+        let c = create_test_module_id("c");
+        for module in [&a, &b, &c] {
+            graph.add_module(module.clone());
+        }
+
+        let import_at = |path: &str, line: usize| DependencyEdge {
+            location: Some(SourceLocation {
+                path: path.to_string(),
+                cell: None,
+                line,
+                column: 1,
+            }),
+            ..DependencyEdge::new(DependencyType::Imports)
+        };
+
+        // a → c → b → a: the reported order must follow the import direction
+        graph.add_edge(&a, &c, import_at("a.py", 3)).unwrap();
+        graph.add_edge(&c, &b, import_at("c.py", 7)).unwrap();
+        graph.add_edge(&b, &a, import_at("b.py", 1)).unwrap();
+
+        let result = detect_cycles(&graph, false).unwrap();
+        assert_eq!(result.cycle_count(), 1);
+
+        let cycle = &result.cycles[0];
+        assert_eq!(cycle.modules, vec!["a", "c", "b"]);
+        let steps: Vec<String> = cycle
+            .steps()
+            .map(|(from, to, location)| format!("{} → {} at {}", from, to, location.unwrap()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec!["a → c at a.py:3", "c → b at c.py:7", "b → a at b.py:1"]
+        );
+
+        let formatted = formatters::format_text_grouped(&result);
+        assert!(formatted.contains("Cycle 1: a → c → b → a\n  a → c at a.py:3\n"));
+    }
+}
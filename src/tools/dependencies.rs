@@ -1,10 +1,11 @@
 use crate::graph::{DependencyGraph, DependencyType};
 use crate::imports::{ModuleIdentifier, ModuleOrigin};
-use crate::tools::common;
+use crate::tools::common::{self, GroupedModule, ImportLocations};
 use anyhow::Result;
+use serde::Serialize;
 
 /// Result of dependency analysis for a module
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DependencyAnalysisResult {
     /// The module that was analyzed
     pub target_module: String,
@@ -14,12 +15,14 @@ pub struct DependencyAnalysisResult {
     pub internal_dependencies: Vec<(String, DependencyType, usize)>,
     /// Total count of dependencies
     pub total_dependency_count: usize,
+    /// Where the module (or one of its submodules) imports each dependency
+    pub import_locations: ImportLocations,
 }
 
 pub fn get_dependencies_analysis(
     graph: &DependencyGraph,
     module_id: &ModuleIdentifier,
-) -> Result<(Vec<String>, Vec<(String, DependencyType, usize)>, usize)> {
+) -> Result<(Vec<String>, Vec<GroupedModule>, usize)> {
     // Collect dependencies of the module and of all its descendants.
     let mut all_dependencies = graph.get_transitive_dependencies_with_types(module_id)?;
 
@@ -71,12 +74,14 @@ pub fn analyze_dependencies(
     // Get dependencies analysis from the graph
     let (external_dependencies, internal_dependencies, total_count) =
         get_dependencies_analysis(graph, target_module)?;
+    let import_locations = graph.get_transitive_dependency_locations(target_module)?;
 
     Ok(DependencyAnalysisResult {
         target_module: target_module.canonical_path.clone(),
         external_dependencies,
         internal_dependencies,
         total_dependency_count: total_count,
+        import_locations,
     })
 }
 
@@ -98,7 +103,12 @@ pub mod formatters {
             if !result.external_dependencies.is_empty() {
                 output.push_str("External packages:\n");
                 for dep in &result.external_dependencies {
-                    output.push_str(&format!("  {}\n", dep));
+                    let locations = result.import_locations.get(dep);
+                    output.push_str(&format!(
+                        "  {}{}\n",
+                        dep,
+                        common_formatters::format_locations(locations)
+                    ));
                 }
             }
 
@@ -112,7 +122,12 @@ pub mod formatters {
                     if *count > 1 {
                         output.push_str(&format!("  ({} submodules) {}\n", count, module));
                     } else {
-                        output.push_str(&format!("  {}\n", module));
+                        let locations = result.import_locations.get(module);
+                        output.push_str(&format!(
+                            "  {}{}\n",
+                            module,
+                            common_formatters::format_locations(locations)
+                        ));
                     }
                 }
             }
@@ -139,7 +154,12 @@ pub mod formatters {
             if !result.external_dependencies.is_empty() {
                 output.push_str("External packages:\n");
                 for dep in &result.external_dependencies {
-                    output.push_str(&format!("  {}\n", dep));
+                    let locations = result.import_locations.get(dep);
+                    output.push_str(&format!(
+                        "  {}{}\n",
+                        dep,
+                        common_formatters::format_locations(locations)
+                    ));
                 }
             }
 
@@ -151,6 +171,7 @@ pub mod formatters {
                 output.push_str("Internal modules:\n");
                 output.push_str(&common_formatters::format_grouped_modules(
                     &result.internal_dependencies,
+                    &result.import_locations,
                 ));
             }
         }
@@ -236,6 +257,7 @@ mod tests {
                 ("api".to_string(), DependencyType::Imports, 3),
             ],
             total_dependency_count: 4,
+            import_locations: Default::default(),
         };
 
         let formatted = formatters::format_text(&result);
@@ -257,6 +279,7 @@ mod tests {
             external_dependencies: vec![],
             internal_dependencies: vec![],
             total_dependency_count: 0,
+            import_locations: Default::default(),
         };
 
         let formatted = formatters::format_text(&result);
@@ -1,24 +1,112 @@
 use crate::graph::DependencyGraph;
+use crate::tools::common::ImportLocations;
 use crate::tools::cycles::{Cycle, detect_cycles};
 use crate::tools::external::analyze_external_dependencies;
 use crate::tools::instability::analyze_instability;
 use crate::tools::pressure::analyze_pressure;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Limits of the diagnose report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DiagnoseSettings {
+    /// Dependent counts above which modules are reported as pressure points, by level
+    pub pressure_levels: (usize, usize, usize),
+    /// Number of longest cycles listed
+    pub top_cycles: usize,
+    pub score_weights: ScoreWeights,
+}
+
+impl Default for DiagnoseSettings {
+    fn default() -> Self {
+        Self {
+            pressure_levels: (10, 50, 100),
+            top_cycles: 5,
+            score_weights: ScoreWeights::default(),
+        }
+    }
+}
+
+impl DiagnoseSettings {
+    /// Checks that the pressure levels are in increasing order and that the score weights
+    /// are non-negative, with at least one of them positive.
+    pub fn validate(&self) -> Result<()> {
+        let (low, mid, high) = self.pressure_levels;
+        if low > mid || mid > high {
+            return Err(anyhow::anyhow!(
+                "Pressure levels must be increasing, got [{}, {}, {}]",
+                low,
+                mid,
+                high
+            ));
+        }
+        let weights = self.score_weights.values();
+        if weights
+            .iter()
+            .any(|weight| !weight.is_finite() || *weight < 0.0)
+            || weights.iter().sum::<f64>() == 0.0
+        {
+            return Err(anyhow::anyhow!(
+                "Score weights must be non-negative with at least one positive, got {:?}",
+                weights
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Relative weights of the problems making up the health score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoreWeights {
+    /// Share of modules in a circular dependency
+    pub cycles: f64,
+    /// Average instability
+    pub instability: f64,
+    /// Share of modules over the lowest pressure level
+    pub pressure: f64,
+    /// Share of external dependencies that are undeclared or unused
+    pub external: f64,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            cycles: 1.0,
+            instability: 1.0,
+            pressure: 1.0,
+            external: 1.0,
+        }
+    }
+}
+
+impl ScoreWeights {
+    fn values(&self) -> [f64; 4] {
+        [self.cycles, self.instability, self.pressure, self.external]
+    }
+}
 
 /// Raw data from diagnose analysis - no display logic
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DiagnoseResult {
     /// Total number of modules analyzed
     pub total_modules: usize,
+    /// Health score from 0 to 100: 100 minus the weighted average of the share of the
+    /// codebase affected by each problem
+    pub health_score: f64,
     /// Number of circular dependencies found
     pub cycle_count: usize,
-    /// Top 5 longest cycles (sorted by length descending)
+    /// Longest cycles (sorted by length descending)
     pub top_cycles: Vec<Cycle>,
     /// Average instability score across all modules
     pub avg_instability: f64,
     /// Instability quantiles (10%, 50%, 90%)
     pub instability_quantiles: (f64, f64, f64),
-    /// Number of modules by pressure levels (>10, >50, >100 dependents)
+    /// Dependent counts of the pressure levels (10, 50 and 100 by default)
+    pub pressure_thresholds: (usize, usize, usize),
+    /// Number of modules by pressure levels (more dependents than each threshold)
     pub pressure_levels: (usize, usize, usize),
     /// Number of external dependencies
     pub external_dependency_count: usize,
@@ -26,21 +114,34 @@ pub struct DiagnoseResult {
     pub undeclared_dependencies: Vec<String>,
     /// External dependencies declared in pyproject.toml but not used in code
     pub unused_dependencies: Vec<String>,
+    /// Where each external dependency is imported
+    pub external_import_locations: ImportLocations,
 }
 
 /// Performs comprehensive diagnosis of the codebase
 pub fn analyze_diagnose(graph: &DependencyGraph) -> Result<DiagnoseResult> {
+    analyze_diagnose_with_settings(graph, &DiagnoseSettings::default())
+}
+
+/// Performs comprehensive diagnosis of the codebase with custom report limits
+pub fn analyze_diagnose_with_settings(
+    graph: &DependencyGraph,
+    settings: &DiagnoseSettings,
+) -> Result<DiagnoseResult> {
     // Get basic graph metrics
     let total_modules = graph.all_modules().count();
 
     // Run existing analyses
-    let cycles_result = detect_cycles(graph)?;
+    let cycles_result = detect_cycles(graph, false)?;
     let cycle_count = cycles_result.cycles.len();
 
-    // Get top 5 longest cycles (sorted by length descending)
+    // Get the longest cycles (sorted by length descending)
     let mut cycles_by_length = cycles_result.cycles.clone();
-    cycles_by_length.sort_by(|a, b| b.modules.len().cmp(&a.modules.len()));
-    let top_cycles = cycles_by_length.into_iter().take(5).collect();
+    cycles_by_length.sort_by_key(|cycle| std::cmp::Reverse(cycle.modules.len()));
+    let top_cycles = cycles_by_length
+        .into_iter()
+        .take(settings.top_cycles)
+        .collect();
 
     let instability_result = analyze_instability(graph)?;
     let avg_instability = if instability_result.instability_modules.is_empty() {
@@ -56,23 +157,20 @@ pub fn analyze_diagnose(graph: &DependencyGraph) -> Result<DiagnoseResult> {
 
     let pressure_result = analyze_pressure(graph)?;
 
-    // Calculate pressure levels (>10, >50, >100 dependents)
-    let pressure_over_10 = pressure_result
-        .pressure_modules
-        .iter()
-        .filter(|(_, count)| *count > 10)
-        .count();
-    let pressure_over_50 = pressure_result
-        .pressure_modules
-        .iter()
-        .filter(|(_, count)| *count > 50)
-        .count();
-    let pressure_over_100 = pressure_result
-        .pressure_modules
-        .iter()
-        .filter(|(_, count)| *count > 100)
-        .count();
-    let pressure_levels = (pressure_over_10, pressure_over_50, pressure_over_100);
+    // Calculate pressure levels (more dependents than each threshold)
+    let pressure_thresholds = settings.pressure_levels;
+    let count_over = |threshold: usize| {
+        pressure_result
+            .pressure_modules
+            .iter()
+            .filter(|(_, count)| *count > threshold)
+            .count()
+    };
+    let pressure_levels = (
+        count_over(pressure_thresholds.0),
+        count_over(pressure_thresholds.1),
+        count_over(pressure_thresholds.2),
+    );
 
     // Calculate instability quantiles (10%, 50%, 90%)
     let instability_quantiles =
@@ -81,19 +179,62 @@ pub fn analyze_diagnose(graph: &DependencyGraph) -> Result<DiagnoseResult> {
     let external_result = analyze_external_dependencies(graph)?;
     let external_dependency_count = external_result.frequency_analysis.len();
 
+    let share = |count: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            (count as f64 / total as f64).min(1.0)
+        }
+    };
+    let modules_in_cycles: HashSet<&String> = cycles_result
+        .cycles
+        .iter()
+        .flat_map(|cycle| &cycle.modules)
+        .collect();
+    let external_issues =
+        external_result.undeclared_dependencies.len() + external_result.unused_dependencies.len();
+    let health_score = health_score(
+        [
+            share(modules_in_cycles.len(), total_modules),
+            avg_instability,
+            share(pressure_levels.0, total_modules),
+            share(
+                external_issues,
+                external_dependency_count + external_result.unused_dependencies.len(),
+            ),
+        ],
+        &settings.score_weights,
+    );
+
     Ok(DiagnoseResult {
         total_modules,
+        health_score,
         cycle_count,
         top_cycles,
         avg_instability,
         instability_quantiles,
+        pressure_thresholds,
         pressure_levels,
         external_dependency_count,
         undeclared_dependencies: external_result.undeclared_dependencies,
         unused_dependencies: external_result.unused_dependencies,
+        external_import_locations: external_result.import_locations,
     })
 }
 
+/// Scores problems from 0 to 100, given the share of the codebase affected by each
+/// (cycles, instability, pressure and external dependencies, between 0 and 1).
+fn health_score(shares: [f64; 4], weights: &ScoreWeights) -> f64 {
+    let weights = weights.values();
+    let penalty: f64 = shares
+        .iter()
+        .zip(weights)
+        .map(|(share, weight)| share * weight)
+        .sum::<f64>()
+        / weights.iter().sum::<f64>();
+    100.0 * (1.0 - penalty)
+}
+
 /// Calculate instability quantiles (10%, 50%, 90%)
 fn calculate_instability_quantiles(instability_modules: &[(String, f64)]) -> (f64, f64, f64) {
     if instability_modules.is_empty() {
@@ -123,11 +264,13 @@ fn calculate_instability_quantiles(instability_modules: &[(String, f64)]) -> (f6
 /// Formatters for diagnose results
 pub mod formatters {
     use super::{Cycle, DiagnoseResult};
+    use crate::tools::common::ImportLocations;
 
     /// Formats results as human-readable text
     pub fn format_text(result: &DiagnoseResult) -> String {
         let (q10, q50, q90) = result.instability_quantiles;
-        let (pressure_10, pressure_50, pressure_100) = result.pressure_levels;
+        let (threshold_low, threshold_mid, threshold_high) = result.pressure_thresholds;
+        let (pressure_low, pressure_mid, pressure_high) = result.pressure_levels;
 
         format!(
             "=============================\n\
@@ -135,6 +278,7 @@ pub mod formatters {
              =============================\n\n\
              OVERVIEW\n\
              -----------\n\
+             Health Score: {:.0}/100\n\
              Total Modules: {}\n\
              External Dependencies: {}\n\n\
              CIRCULAR DEPENDENCIES\n\
@@ -162,9 +306,9 @@ pub mod formatters {
              > High fan-in modules are single points of failure; changes there \n\
              have a wide blast radius and slow delivery.\n\
              --\n\
-             Modules with >10 dependents: {}\n\
-             Modules with >50 dependents: {}\n\
-             Modules with >100 dependents: {}\n\
+             Modules with >{} dependents: {}\n\
+             Modules with >{} dependents: {}\n\
+             Modules with >{} dependents: {}\n\
              {}\n\n\
              EXTERNAL DEPENDENCIES\n\
              ---------------------\n\
@@ -175,6 +319,7 @@ pub mod formatters {
              Undeclared (used but not in pyproject.toml): {}\n\
              Unused (in pyproject.toml but not used): {}\n\
              {}\n\n",
+            result.health_score,
             result.total_modules,
             result.external_dependency_count,
             result.cycle_count,
@@ -193,10 +338,13 @@ pub mod formatters {
             } else {
                 ""
             },
-            pressure_10,
-            pressure_50,
-            pressure_100,
-            if pressure_10 > 0 {
+            threshold_low,
+            pressure_low,
+            threshold_mid,
+            pressure_mid,
+            threshold_high,
+            pressure_high,
+            if pressure_low > 0 {
                 "⚠️ High-pressure modules found - consider splitting large dependencies"
             } else {
                 ""
@@ -204,7 +352,11 @@ pub mod formatters {
             result.external_dependency_count,
             result.undeclared_dependencies.len(),
             result.unused_dependencies.len(),
-            format_external_issues(&result.undeclared_dependencies, &result.unused_dependencies)
+            format_external_issues(
+                &result.undeclared_dependencies,
+                &result.unused_dependencies,
+                &result.external_import_locations
+            )
         )
     }
 
@@ -223,6 +375,16 @@ pub mod formatters {
                 cycle_str,
                 cycle.modules.len()
             ));
+            let locations: Vec<String> = cycle
+                .steps()
+                .filter_map(|(_, _, location)| location.map(|l| l.to_string()))
+                .collect();
+            if !locations.is_empty() {
+                output.push_str(&format!(
+                    "     at {}\n",
+                    truncate_string(&locations.join(", "), 120)
+                ));
+            }
         }
         output
     }
@@ -237,11 +399,29 @@ pub mod formatters {
     }
 
     /// Format external dependency issues for display
-    fn format_external_issues(undeclared: &[String], unused: &[String]) -> String {
+    ///
+    /// Undeclared dependencies are shown with their first import location.
+    fn format_external_issues(
+        undeclared: &[String],
+        unused: &[String],
+        import_locations: &ImportLocations,
+    ) -> String {
         let mut issues = Vec::new();
 
         if !undeclared.is_empty() {
-            let undeclared_str = truncate_string(&undeclared.join(", "), 120);
+            let undeclared_with_locations: Vec<String> = undeclared
+                .iter()
+                .map(|dep| {
+                    match import_locations
+                        .get(dep)
+                        .and_then(|locations| locations.first())
+                    {
+                        Some(location) => format!("{} ({})", dep, location),
+                        None => dep.clone(),
+                    }
+                })
+                .collect();
+            let undeclared_str = truncate_string(&undeclared_with_locations.join(", "), 120);
             issues.push(format!("Undeclared: {}", undeclared_str));
         }
 
@@ -264,3 +444,34 @@ pub mod formatters {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_score() {
+        let weights = ScoreWeights::default();
+        assert_eq!(health_score([0.0; 4], &weights), 100.0);
+        assert_eq!(health_score([1.0; 4], &weights), 0.0);
+        assert_eq!(health_score([0.5, 0.0, 0.0, 0.5], &weights), 75.0);
+
+        // Problems without weight do not count
+        let cycles_only = ScoreWeights {
+            cycles: 2.0,
+            instability: 0.0,
+            pressure: 0.0,
+            external: 0.0,
+        };
+        assert_eq!(health_score([0.5, 1.0, 1.0, 1.0], &cycles_only), 50.0);
+
+        let settings = DiagnoseSettings {
+            score_weights: ScoreWeights {
+                cycles: 0.0,
+                ..cycles_only
+            },
+            ..DiagnoseSettings::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
@@ -1,38 +1,116 @@
-use crate::graph::DependencyGraph;
-use crate::imports::ModuleOrigin;
+use crate::declared::{DeclaredDependency, DependencyGroup, SourceGroups};
+use crate::graph::{DependencyGraph, DependencyType, SourceLocation};
+use crate::imports::{ModuleOrigin, external_package_root};
 use crate::pyproject;
+use crate::tools::common::ImportLocations;
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::{HashMap, HashSet};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::sync::OnceLock;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ExternalAnalysisResult {
     pub frequency_analysis: Vec<DependencyUsage>,
     pub summary: ExternalDependencySummary,
     pub undeclared_dependencies: Vec<String>,
     pub unused_dependencies: Vec<String>,
+    pub optional_dependencies: Vec<OptionalDependency>,
+    /// Gaps of each dependency source (pyproject.toml, requirements files, Pipfile), per group
+    pub declared_sources: Vec<DeclaredSourceGaps>,
     pub declared_externals_count: usize,
+    /// Packages imported by code that none of their declaring groups is installed for
+    pub misplaced_dependencies: Vec<MisplacedDependency>,
+    /// Undeclared packages that are only installed as requirements of declared ones
+    pub transitive_dependencies: Vec<TransitiveDependency>,
+    /// Where each used package is imported, keyed by package name (as reported in the gaps)
+    pub import_locations: ImportLocations,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct DependencyUsage {
     pub package_name: String,
     pub usage_count: usize,
     pub used_by_modules: Vec<String>,
+    /// True if every import of the package is guarded by `try/except ImportError`
+    pub optional: bool,
+    /// True if the package is only imported by Jupyter notebooks
+    pub notebook_only: bool,
+    /// Import statements of the package in the analyzed code
+    pub locations: Vec<SourceLocation>,
+    /// Import statements that must succeed at runtime: neither guarded by
+    /// `try/except ImportError` nor type-only
+    #[serde(skip)]
+    pub required_locations: Vec<SourceLocation>,
 }
 
-#[derive(Debug)]
+/// A package that is only imported behind `try/except ImportError`
+#[derive(Debug, Serialize)]
+pub struct OptionalDependency {
+    pub package_name: String,
+    pub declared: bool,
+    /// Extras groups listing the package in pyproject.toml
+    pub extras: Vec<String>,
+}
+
+/// A package only declared in groups that are not installed for some code importing
+/// it, such as a dev dependency imported by production code
+#[derive(Debug, Serialize)]
+pub struct MisplacedDependency {
+    pub package_name: String,
+    /// Groups declaring the package
+    pub declared_groups: Vec<DependencyGroup>,
+    /// Groups of the code importing the package without having it installed
+    pub code_groups: Vec<DependencyGroup>,
+    /// Imports of the package from that code
+    pub locations: Vec<SourceLocation>,
+}
+
+/// An undeclared package that the lockfile installs as a requirement of declared
+/// dependencies, so that imports of it only work by accident
+#[derive(Debug, Serialize)]
+pub struct TransitiveDependency {
+    pub package_name: String,
+    /// Resolved version in the lockfile
+    pub version: Option<String>,
+    /// Declared dependencies requiring the package, directly or transitively
+    pub required_by: Vec<String>,
+    /// Shortest requirement chain from a declared dependency to the package
+    pub chain: Vec<String>,
+}
+
+/// The dependencies one source declares for one group, and those not used in code
+#[derive(Debug, Serialize)]
+pub struct DeclaredSourceGaps {
+    /// File declaring the dependencies, relative to the project root
+    pub source: String,
+    pub group: DependencyGroup,
+    pub declared_count: usize,
+    pub unused_dependencies: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
 pub struct ExternalDependencySummary {
     pub total_used_packages: usize,
 }
 
 pub fn analyze_external_dependencies(graph: &DependencyGraph) -> Result<ExternalAnalysisResult> {
+    analyze_external_dependencies_with_groups(graph, &SourceGroups::default())
+}
+
+/// Analyzes external dependencies, telling the group of the importing code from its path
+/// with the given mapping.
+pub fn analyze_external_dependencies_with_groups(
+    graph: &DependencyGraph,
+    source_groups: &SourceGroups,
+) -> Result<ExternalAnalysisResult> {
     let used_externals = pyproject::get_used_externals()?;
     let frequency_analysis = collect_package_usage(graph, &used_externals)?;
-    let declared_deps = pyproject::get_declared_dependencies()?;
-    let (undeclared_dependencies, unused_dependencies) =
-        analyze_dependency_gaps(&frequency_analysis, &declared_deps)?;
+    let declared = pyproject::get_declared_dependency_sources()?;
+    let extras = pyproject::get_extras()?;
+    let gaps = analyze_dependency_gaps(&frequency_analysis, &declared, &extras, source_groups)?;
+    let transitive_dependencies =
+        find_transitive_dependencies(graph, &gaps.undeclared_dependencies, &declared);
 
     let summary = ExternalDependencySummary {
         total_used_packages: frequency_analysis.len(),
@@ -41,21 +119,37 @@ pub fn analyze_external_dependencies(graph: &DependencyGraph) -> Result<External
     Ok(ExternalAnalysisResult {
         frequency_analysis,
         summary,
-        undeclared_dependencies,
-        unused_dependencies,
+        undeclared_dependencies: gaps.undeclared_dependencies,
+        unused_dependencies: gaps.unused_dependencies,
+        optional_dependencies: gaps.optional_dependencies,
+        misplaced_dependencies: gaps.misplaced_dependencies,
+        declared_sources: gaps.declared_sources,
         declared_externals_count: used_externals.len(),
+        transitive_dependencies,
+        import_locations: gaps.import_locations,
     })
 }
 
 /// Collect usage statistics for external packages across internal modules
-fn collect_package_usage(graph: &DependencyGraph, used_externals: &[String]) -> Result<Vec<DependencyUsage>> {
+fn collect_package_usage(
+    graph: &DependencyGraph,
+    used_externals: &[String],
+) -> Result<Vec<DependencyUsage>> {
     let stdlib_modules = get_python_standard_library_modules();
     let mut package_usage: HashMap<String, Vec<String>> = HashMap::new();
+    // Packages imported at least once without an ImportError guard
+    let mut required_packages: HashSet<String> = HashSet::new();
+    // Packages used outside of notebooks
+    let mut code_packages: HashSet<String> = HashSet::new();
+    let mut package_locations: HashMap<String, Vec<SourceLocation>> = HashMap::new();
+    let mut required_locations: HashMap<String, Vec<SourceLocation>> = HashMap::new();
 
     // Add manually declared external packages from .used-externals.txt
     for package_name in used_externals {
         // Skip Python standard library modules
         if !stdlib_modules.contains(package_name) {
+            required_packages.insert(package_name.clone());
+            code_packages.insert(package_name.clone());
             package_usage
                 .entry(package_name.clone())
                 .or_default()
@@ -63,12 +157,12 @@ fn collect_package_usage(graph: &DependencyGraph, used_externals: &[String]) ->
         }
     }
 
-    // Count usage of external packages across internal modules
+    // Count usage of external packages across internal modules and notebooks
     for module in graph.all_modules() {
-        if module.origin == ModuleOrigin::Internal {
-            let dependencies = graph.get_dependencies_with_types(module)?;
+        if module.origin != ModuleOrigin::External {
+            let dependencies = graph.get_dependency_edges(module)?;
 
-            for (dep_module, _dep_type) in dependencies {
+            for (dep_module, edge) in dependencies {
                 // Check if this dependency is external by looking for a module with External origin
                 if let Some(external_module) = graph
                     .all_modules()
@@ -82,6 +176,27 @@ fn collect_package_usage(graph: &DependencyGraph, used_externals: &[String]) ->
                         continue;
                     }
 
+                    if !edge.optional {
+                        required_packages.insert(package_name.clone());
+                    }
+                    if module.origin == ModuleOrigin::Internal {
+                        code_packages.insert(package_name.clone());
+                    }
+                    if let Some(location) = &edge.location
+                        && !edge.optional
+                        && edge.dependency_type != DependencyType::TypeOnlyImports
+                    {
+                        required_locations
+                            .entry(package_name.clone())
+                            .or_default()
+                            .push(location.clone());
+                    }
+                    if let Some(location) = edge.location {
+                        package_locations
+                            .entry(package_name.clone())
+                            .or_default()
+                            .push(location);
+                    }
                     package_usage
                         .entry(package_name)
                         .or_default()
@@ -98,7 +213,18 @@ fn collect_package_usage(graph: &DependencyGraph, used_externals: &[String]) ->
             used_by_modules.sort();
             used_by_modules.dedup();
 
+            let mut locations = package_locations.remove(&package_name).unwrap_or_default();
+            locations.sort();
+            locations.dedup();
+            let mut required = required_locations.remove(&package_name).unwrap_or_default();
+            required.sort();
+            required.dedup();
+
             DependencyUsage {
+                optional: !required_packages.contains(&package_name),
+                notebook_only: !code_packages.contains(&package_name),
+                locations,
+                required_locations: required,
                 package_name,
                 usage_count: used_by_modules.len(),
                 used_by_modules,
@@ -116,39 +242,237 @@ fn collect_package_usage(graph: &DependencyGraph, used_externals: &[String]) ->
     Ok(frequency_analysis)
 }
 
-/// Compare used packages against declared dependencies to find gaps
+/// Differences between the packages used in code and the declared dependencies
+struct DependencyGaps {
+    undeclared_dependencies: Vec<String>,
+    unused_dependencies: Vec<String>,
+    optional_dependencies: Vec<OptionalDependency>,
+    misplaced_dependencies: Vec<MisplacedDependency>,
+    declared_sources: Vec<DeclaredSourceGaps>,
+    /// Import locations keyed by resolved package name
+    import_locations: ImportLocations,
+}
+
+/// Compare used packages against declared dependencies to find gaps.
+///
+/// Packages that are only imported behind `try/except ImportError` are reported
+/// as optional (with their extras groups) rather than as undeclared. Declared packages
+/// imported by code their groups are not installed for are reported as misplaced.
 fn analyze_dependency_gaps(
     frequency_analysis: &[DependencyUsage],
-    declared_deps: &[String],
-) -> Result<(Vec<String>, Vec<String>)> {
-    let declared_deps_set: HashSet<&str> = declared_deps.iter().map(String::as_str).collect();
-
-    // Pre-fetch all package mappings once
-    let mapping = build_complete_mapping(declared_deps)?;
+    declared: &[DeclaredDependency],
+    extras: &BTreeMap<String, Vec<String>>,
+    source_groups: &SourceGroups,
+) -> Result<DependencyGaps> {
+    let declared_deps_set: HashSet<&str> = declared.iter().map(|dep| dep.name.as_str()).collect();
+
+    // Pre-fetch all package mappings once, including packages only listed in extras
+    let mut known_packages: Vec<String> = declared_deps_set
+        .iter()
+        .map(|dep| dep.to_string())
+        .collect();
+    known_packages.extend(extras.values().flatten().cloned());
+    known_packages.sort();
+    known_packages.dedup();
+    let mapping = build_complete_mapping(&known_packages)?;
 
     // Resolve import names to package names using pre-built mapping
     let resolved_used_deps: HashSet<String> = frequency_analysis
         .iter()
         .map(|dep| resolve_import_to_package_name(&mapping, &dep.package_name))
         .collect();
+    let required_deps: HashSet<String> = frequency_analysis
+        .iter()
+        .filter(|dep| !dep.optional)
+        .map(|dep| resolve_import_to_package_name(&mapping, &dep.package_name))
+        .collect();
+
+    let mut import_locations: ImportLocations = HashMap::new();
+    let mut required_locations: ImportLocations = HashMap::new();
+    for dep in frequency_analysis {
+        let package_name = resolve_import_to_package_name(&mapping, &dep.package_name);
+        required_locations
+            .entry(package_name.clone())
+            .or_default()
+            .extend(dep.required_locations.iter().cloned());
+        import_locations
+            .entry(package_name)
+            .or_default()
+            .extend(dep.locations.iter().cloned());
+    }
+    for locations in import_locations
+        .values_mut()
+        .chain(required_locations.values_mut())
+    {
+        locations.sort();
+        locations.dedup();
+    }
 
     // Find undeclared dependencies (used but not declared in pyproject.toml)
-    let mut undeclared_dependencies: Vec<String> = resolved_used_deps
+    let mut undeclared_dependencies: Vec<String> = required_deps
         .iter()
         .filter(|dep| !declared_deps_set.contains(dep.as_str()))
         .cloned()
         .collect();
     undeclared_dependencies.sort();
 
+    // Find optional dependencies (only imported behind an ImportError guard)
+    let mut optional_dependencies: Vec<OptionalDependency> = resolved_used_deps
+        .iter()
+        .filter(|dep| !required_deps.contains(*dep))
+        .map(|dep| OptionalDependency {
+            package_name: dep.clone(),
+            declared: declared_deps_set.contains(dep.as_str()),
+            extras: extras
+                .iter()
+                .filter(|(_, packages)| packages.contains(dep))
+                .map(|(extra_name, _)| extra_name.clone())
+                .collect(),
+        })
+        .collect();
+    optional_dependencies.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+
+    // Find misplaced dependencies (imported by code none of their groups is installed for)
+    let mut declared_groups: BTreeMap<&str, BTreeSet<DependencyGroup>> = BTreeMap::new();
+    for dep in declared {
+        declared_groups
+            .entry(dep.name.as_str())
+            .or_default()
+            .insert(dep.group);
+    }
+    // Guarded and type-only imports are fine in code the package may not be installed for
+    let mut misplaced_dependencies: Vec<MisplacedDependency> = required_locations
+        .iter()
+        .filter_map(|(package_name, locations)| {
+            let groups = declared_groups.get(package_name.as_str())?;
+            let mut code_groups = BTreeSet::new();
+            let misplaced: Vec<SourceLocation> = locations
+                .iter()
+                .filter(|location| {
+                    let code_group = source_groups.group_of(&location.path);
+                    let available = groups.iter().any(|group| group.is_available_to(code_group));
+                    if !available {
+                        code_groups.insert(code_group);
+                    }
+                    !available
+                })
+                .cloned()
+                .collect();
+            (!misplaced.is_empty()).then(|| MisplacedDependency {
+                package_name: package_name.clone(),
+                declared_groups: groups.iter().copied().collect(),
+                code_groups: code_groups.into_iter().collect(),
+                locations: misplaced,
+            })
+        })
+        .collect();
+    misplaced_dependencies.sort_by(|a, b| a.package_name.cmp(&b.package_name));
+
+    // Type stubs distributions are used when the package they provide types for is
+    let used_distributions: HashSet<String> = resolved_used_deps
+        .iter()
+        .map(String::as_str)
+        .chain(
+            frequency_analysis
+                .iter()
+                .map(|dep| dep.package_name.as_str()),
+        )
+        .map(normalize_distribution_name)
+        .collect();
+
     // Find unused dependencies (declared but not used)
+    let is_unused = |dep: &str| {
+        !resolved_used_deps.contains(dep)
+            && !stubbed_distribution(dep).is_some_and(|stubbed| {
+                used_distributions.contains(&normalize_distribution_name(stubbed))
+            })
+    };
     let mut unused_dependencies: Vec<String> = declared_deps_set
         .iter()
-        .filter(|dep| !resolved_used_deps.contains(**dep))
+        .filter(|dep| is_unused(dep))
         .map(|s| s.to_string())
         .collect();
     unused_dependencies.sort();
 
-    Ok((undeclared_dependencies, unused_dependencies))
+    // The same gaps for each source and group (`declared` is sorted by name)
+    let mut per_source: BTreeMap<(&str, DependencyGroup), Vec<&str>> = BTreeMap::new();
+    for dep in declared {
+        per_source
+            .entry((dep.source.as_str(), dep.group))
+            .or_default()
+            .push(dep.name.as_str());
+    }
+    let declared_sources = per_source
+        .into_iter()
+        .map(|((source, group), deps)| DeclaredSourceGaps {
+            source: source.to_string(),
+            group,
+            declared_count: deps.len(),
+            unused_dependencies: deps
+                .into_iter()
+                .filter(|dep| is_unused(dep))
+                .map(str::to_string)
+                .collect(),
+        })
+        .collect();
+
+    Ok(DependencyGaps {
+        undeclared_dependencies,
+        unused_dependencies,
+        optional_dependencies,
+        misplaced_dependencies,
+        declared_sources,
+        import_locations,
+    })
+}
+
+/// Finds the undeclared packages that locked declared dependencies require, with the
+/// shortest requirement chain bringing each one in.
+fn find_transitive_dependencies(
+    graph: &DependencyGraph,
+    undeclared_dependencies: &[String],
+    declared: &[DeclaredDependency],
+) -> Vec<TransitiveDependency> {
+    let mut declared_names: Vec<String> = declared
+        .iter()
+        .map(|dep| pyproject::normalize_dependency_name(&dep.name))
+        .collect();
+    declared_names.sort();
+    declared_names.dedup();
+
+    undeclared_dependencies
+        .iter()
+        .filter_map(|dep| {
+            let package_name = pyproject::normalize_dependency_name(dep);
+            let chains: Vec<Vec<String>> = declared_names
+                .iter()
+                .filter_map(|declared| graph.get_requirement_chain(declared, &package_name))
+                .collect();
+            // Shortest chain, the first declared dependency on ties
+            let chain = chains.iter().min_by_key(|chain| chain.len())?.clone();
+            Some(TransitiveDependency {
+                version: graph.get_package_version(&package_name).map(str::to_string),
+                required_by: chains.into_iter().map(|chain| chain[0].clone()).collect(),
+                chain,
+                package_name: dep.clone(),
+            })
+        })
+        .collect()
+}
+
+/// Returns the package a type stubs distribution provides types for
+/// (`types-requests` -> `requests`, `pandas-stubs` -> `pandas`).
+fn stubbed_distribution(package_name: &str) -> Option<&str> {
+    package_name
+        .strip_prefix("types-")
+        .or_else(|| package_name.strip_suffix("-stubs"))
+        .filter(|stubbed| !stubbed.is_empty())
+}
+
+/// Normalizes a distribution name for comparison (case-insensitive, `-`, `_` and `.`
+/// are equivalent).
+fn normalize_distribution_name(package_name: &str) -> String {
+    package_name.to_lowercase().replace(['_', '.'], "-")
 }
 
 /// Cached Python standard library modules
@@ -172,7 +496,6 @@ fn get_python_standard_library_modules() -> &'static HashSet<String> {
                         .map(|line| line.trim().to_string())
                         .filter(|line| !line.is_empty())
                         .collect();
-
                     if !result.is_empty() {
                         return result;
                     }
@@ -180,21 +503,17 @@ fn get_python_standard_library_modules() -> &'static HashSet<String> {
                 _ => continue, // Try next command
             }
         }
-
         // If both fail, return empty set and warn user
         println!("Warning: Could not detect Python stdlib modules. Install Python or ensure it's in PATH.");
         HashSet::new()
     })
 }
 
-/// Extracts the root package name from a module path.
-/// Examples: numpy.testing.utils -> numpy, scipy.stats -> scipy
+/// Extracts the root package name from a module path, keeping namespace package portions.
+/// Examples: numpy.testing.utils -> numpy, scipy.stats -> scipy,
+/// google.cloud.storage.blob -> google.cloud.storage
 fn extract_root_package_name(module_path: &str) -> String {
-    module_path
-        .split('.')
-        .next()
-        .unwrap_or(module_path)
-        .to_string()
+    external_package_root(module_path).to_string()
 }
 
 /// Package import mapping with static fallback and API results
@@ -217,12 +536,12 @@ impl PackageImportMapping {
     /// Resolve import name to package name using pre-built mapping
     fn resolve_import_to_package(&self, import_name: &str) -> String {
         let normalized_name = import_name.to_lowercase();
-        
+
         // Check static mappings first (case-insensitive)
         if let Some(package_name) = self.static_mappings.get(&normalized_name) {
             return package_name.clone();
         }
-        
+
         // Also check original case for exact matches
         if let Some(package_name) = self.static_mappings.get(import_name) {
             return package_name.clone();
@@ -232,7 +551,7 @@ impl PackageImportMapping {
         if let Some(package_name) = self.api_mappings.get(&normalized_name) {
             return package_name.clone();
         }
-        
+
         // Also check original case for exact matches
         if let Some(package_name) = self.api_mappings.get(import_name) {
             return package_name.clone();
@@ -246,7 +565,8 @@ impl PackageImportMapping {
     fn add_mapping(&mut self, import_name: String, package_name: String) {
         // Store both original case and lowercase for case-insensitive lookup
         let normalized_name = import_name.to_lowercase();
-        self.api_mappings.insert(import_name.clone(), package_name.clone());
+        self.api_mappings
+            .insert(import_name.clone(), package_name.clone());
         if normalized_name != import_name {
             self.api_mappings.insert(normalized_name, package_name);
         }
@@ -264,7 +584,9 @@ fn build_complete_mapping(declared_packages: &[String]) -> Result<PackageImportM
     let pb = ProgressBar::new(declared_packages.len() as u64);
     pb.set_style(
         ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>7}/{len:7} {msg}")
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos:>7}/{len:7} {msg}",
+            )
             .unwrap()
             .progress_chars("##-"),
     );
@@ -366,6 +688,7 @@ fn query_pypi_for_imports(package_name: &str) -> Result<Vec<String>> {
 
 pub mod formatters {
     use super::*;
+    use crate::tools::common::formatters::format_locations;
 
     pub fn format_text_grouped(result: &ExternalAnalysisResult) -> String {
         let mut output = String::new();
@@ -433,7 +756,7 @@ pub mod formatters {
             "Total external packages used: {}\n",
             result.summary.total_used_packages
         ));
-        
+
         if result.declared_externals_count > 0 {
             output.push_str(&format!(
                 "Manually declared externals: {}\n",
@@ -444,21 +767,126 @@ pub mod formatters {
         // Add undeclared dependencies section
         if !result.undeclared_dependencies.is_empty() {
             output.push_str("\n=== Undeclared Dependencies ===\n");
-            output.push_str("(Used in code but not declared in pyproject.toml)\n");
+            output.push_str("(Used in code but not declared in any dependency source)\n");
             for dep in &result.undeclared_dependencies {
-                output.push_str(&format!("  {}\n", dep));
+                let locations = result.import_locations.get(dep);
+                output.push_str(&format!("  {}{}\n", dep, format_locations(locations)));
+            }
+        }
+
+        // Add misplaced dependencies section
+        if !result.misplaced_dependencies.is_empty() {
+            output.push_str("\n=== Misplaced Dependencies ===\n");
+            output.push_str(
+                "(Imported by code that none of their dependency groups is installed for)\n",
+            );
+            for dep in &result.misplaced_dependencies {
+                let join = |groups: &[DependencyGroup]| {
+                    let names: Vec<String> = groups.iter().map(|g| g.to_string()).collect();
+                    names.join(", ")
+                };
+                output.push_str(&format!(
+                    "  {} (declared in {}, imported by {} code){}\n",
+                    dep.package_name,
+                    join(&dep.declared_groups),
+                    join(&dep.code_groups),
+                    format_locations(Some(&dep.locations))
+                ));
+            }
+        }
+
+        // Add transitive-only dependencies section
+        if !result.transitive_dependencies.is_empty() {
+            output.push_str("\n=== Transitive-only Dependencies ===\n");
+            output.push_str(
+                "(Undeclared, only installed as requirements of declared dependencies)\n",
+            );
+            for dep in &result.transitive_dependencies {
+                let version = dep
+                    .version
+                    .as_ref()
+                    .map(|version| format!(" {}", version))
+                    .unwrap_or_default();
+                let locations = result.import_locations.get(&dep.package_name);
+                output.push_str(&format!(
+                    "  {}{} via {}{}\n",
+                    dep.package_name,
+                    version,
+                    dep.chain.join(" -> "),
+                    format_locations(locations)
+                ));
+            }
+        }
+
+        // Add notebook-only dependencies section
+        let notebook_only: Vec<_> = result
+            .frequency_analysis
+            .iter()
+            .filter(|dep| dep.notebook_only)
+            .collect();
+        if !notebook_only.is_empty() {
+            output.push_str("\n=== Notebook-only Dependencies ===\n");
+            output.push_str("(Only imported by Jupyter notebooks)\n");
+            for dep in notebook_only {
+                output.push_str(&format!(
+                    "  {}{}\n",
+                    dep.package_name,
+                    format_locations(Some(&dep.locations))
+                ));
+            }
+        }
+
+        // Add optional dependencies section
+        if !result.optional_dependencies.is_empty() {
+            output.push_str("\n=== Optional Dependencies ===\n");
+            output.push_str("(Only imported behind try/except ImportError)\n");
+            for dep in &result.optional_dependencies {
+                let status = if !dep.extras.is_empty() {
+                    format!("extras: {}", dep.extras.join(", "))
+                } else if dep.declared {
+                    "declared, not in any extras group".to_string()
+                } else {
+                    "undeclared, not in any extras group".to_string()
+                };
+                let locations = result.import_locations.get(&dep.package_name);
+                output.push_str(&format!(
+                    "  {} ({}){}\n",
+                    dep.package_name,
+                    status,
+                    format_locations(locations)
+                ));
             }
         }
 
         // Add unused dependencies section
         if !result.unused_dependencies.is_empty() {
             output.push_str("\n=== Unused Dependencies ===\n");
-            output.push_str("(Declared in pyproject.toml but not used in code)\n");
+            output.push_str("(Declared but not used in code)\n");
             for dep in &result.unused_dependencies {
                 output.push_str(&format!("  {}\n", dep));
             }
         }
 
+        // Add per-source section
+        if !result.declared_sources.is_empty() {
+            output.push_str("\n=== Dependency Sources ===\n");
+            for source in &result.declared_sources {
+                let unused = if source.unused_dependencies.is_empty() {
+                    String::new()
+                } else {
+                    format!(": {}", source.unused_dependencies.join(", "))
+                };
+                output.push_str(&format!(
+                    "  {} [{}]: {} declared, {} unused{}\n",
+                    source.source,
+                    source.group,
+                    source.declared_count,
+                    source.unused_dependencies.len(),
+                    unused
+                ));
+            }
+        }
+
         // Add diff summary
         if !result.undeclared_dependencies.is_empty() || !result.unused_dependencies.is_empty() {
             output.push_str("\n=== Dependency Sync Status ===\n");
@@ -472,7 +900,7 @@ pub mod formatters {
             ));
         } else {
             output.push_str("\n=== Dependency Sync Status ===\n");
-            output.push_str("✓ All used dependencies are properly declared in pyproject.toml\n");
+            output.push_str("✓ All used dependencies are properly declared\n");
             output.push_str("✓ No unused dependencies found\n");
         }
 
@@ -484,7 +912,6 @@ pub mod formatters {
 mod tests {
     use super::*;
     use crate::graph::DependencyGraph;
-    use crate::graph::DependencyType;
     use crate::imports::{ModuleIdentifier, ModuleOrigin};
 
     fn create_test_module_id(name: &str, origin: ModuleOrigin) -> ModuleIdentifier {
@@ -500,6 +927,10 @@ mod tests {
         assert_eq!(extract_root_package_name("numpy.testing"), "numpy");
         assert_eq!(extract_root_package_name("numpy.testing.utils"), "numpy");
         assert_eq!(extract_root_package_name("scipy.stats"), "scipy");
+        assert_eq!(
+            extract_root_package_name("google.cloud.storage.blob"),
+            "google.cloud.storage"
+        );
     }
 
     #[test]
@@ -640,7 +1071,11 @@ wheel
 redis
 tensorflow  # This one won't be used in code
 "#;
-        fs::write(temp_dir.path().join(".used-externals.txt"), used_externals_content).unwrap();
+        fs::write(
+            temp_dir.path().join(".used-externals.txt"),
+            used_externals_content,
+        )
+        .unwrap();
 
         // Initialize pyproject parser with temp directory
         init_for_test(temp_dir.path());
@@ -701,7 +1136,11 @@ tensorflow  # This one won't be used in code
             .find(|dep| dep.package_name == "setuptools")
             .unwrap();
         assert_eq!(setuptools_usage.usage_count, 1); // Only "(declared)"
-        assert!(setuptools_usage.used_by_modules.contains(&"(declared)".to_string()));
+        assert!(
+            setuptools_usage
+                .used_by_modules
+                .contains(&"(declared)".to_string())
+        );
 
         let numpy_usage = result
             .frequency_analysis
@@ -709,7 +1148,11 @@ tensorflow  # This one won't be used in code
             .find(|dep| dep.package_name == "numpy")
             .unwrap();
         assert_eq!(numpy_usage.usage_count, 1); // Only actual code usage
-        assert!(!numpy_usage.used_by_modules.contains(&"(declared)".to_string()));
+        assert!(
+            !numpy_usage
+                .used_by_modules
+                .contains(&"(declared)".to_string())
+        );
     }
 
     #[test]
@@ -801,6 +1244,455 @@ pytest = "^7.3.1"
         assert_eq!(result.unused_dependencies.len(), 3);
     }
 
+    #[test]
+    fn test_declared_sources_gaps() {
+        use crate::pyproject::{init_for_test, reset_for_test};
+        use std::fs;
+        use tempfile::TempDir;
+
+        reset_for_test();
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("requirements.txt"),
+            "numpy>=1.24\npandas\n-c constraints.txt\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("requirements-dev.txt"),
+            "-r requirements.txt\npytest\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("Pipfile"),
+            "[packages]\nrequests = \"*\"\n\n[dev-packages]\nblack = \"*\"\n",
+        )
+        .unwrap();
+        init_for_test(temp_dir.path());
+
+        let mut graph = DependencyGraph::new();
+        let internal1 = create_test_module_id("myapp.main", ModuleOrigin::Internal);
+        let numpy_id = create_test_module_id("numpy", ModuleOrigin::External);
+        let requests_id = create_test_module_id("requests", ModuleOrigin::External);
+        for module in [&internal1, &numpy_id, &requests_id] {
+            graph.add_module(module.clone());
+        }
+        graph
+            .add_dependency(&internal1, &numpy_id, DependencyType::Imports)
+            .unwrap();
+        graph
+            .add_dependency(&internal1, &requests_id, DependencyType::Imports)
+            .unwrap();
+
+        let result = analyze_external_dependencies(&graph).unwrap();
+
+        assert!(result.undeclared_dependencies.is_empty());
+        assert_eq!(
+            result.unused_dependencies,
+            vec!["black", "pandas", "pytest"]
+        );
+
+        let sources: Vec<(&str, DependencyGroup, usize, Vec<&str>)> = result
+            .declared_sources
+            .iter()
+            .map(|source| {
+                (
+                    source.source.as_str(),
+                    source.group,
+                    source.declared_count,
+                    source
+                        .unused_dependencies
+                        .iter()
+                        .map(String::as_str)
+                        .collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            sources,
+            vec![
+                ("Pipfile", DependencyGroup::Runtime, 1, vec![]),
+                ("Pipfile", DependencyGroup::Dev, 1, vec!["black"]),
+                (
+                    "requirements-dev.txt",
+                    DependencyGroup::Dev,
+                    1,
+                    vec!["pytest"]
+                ),
+                (
+                    "requirements.txt",
+                    DependencyGroup::Runtime,
+                    2,
+                    vec!["pandas"]
+                ),
+            ]
+        );
+
+        let formatted = formatters::format_text_grouped(&result);
+        assert!(formatted.contains("  requirements.txt [runtime]: 2 declared, 1 unused: pandas\n"));
+
+        reset_for_test();
+    }
+
+    #[test]
+    fn test_misplaced_dependencies() {
+        use crate::graph::{DependencyEdge, SourceLocation};
+        use crate::pyproject::{init_for_test, reset_for_test};
+        use std::fs;
+        use tempfile::TempDir;
+
+        reset_for_test();
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("pyproject.toml"),
+            r#"
+[project]
+dependencies = ["httpx"]
+
+[dependency-groups]
+dev = ["pytest", "ipython"]
+"#,
+        )
+        .unwrap();
+        init_for_test(temp_dir.path());
+
+        let mut graph = DependencyGraph::new();
+        let imports = [
+            (
+                "myapp.db",
+                "myapp/db.py",
+                "pytest",
+                false,
+                DependencyType::Imports,
+            ),
+            (
+                "myapp.db",
+                "myapp/db.py",
+                "httpx",
+                false,
+                DependencyType::Imports,
+            ),
+            (
+                "myapp.shell",
+                "myapp/shell.py",
+                "IPython",
+                true,
+                DependencyType::Imports,
+            ),
+            (
+                "myapp.core",
+                "myapp/core.py",
+                "pytest",
+                true,
+                DependencyType::Imports,
+            ),
+            (
+                "myapp.core",
+                "myapp/core.py",
+                "IPython",
+                false,
+                DependencyType::TypeOnlyImports,
+            ),
+            (
+                "tests.test_db",
+                "tests/test_db.py",
+                "pytest",
+                false,
+                DependencyType::Imports,
+            ),
+            (
+                "scripts.seed",
+                "scripts/seed.py",
+                "pytest",
+                false,
+                DependencyType::Imports,
+            ),
+        ];
+        for (module, path, package, optional, dependency_type) in imports {
+            let module_id = create_test_module_id(module, ModuleOrigin::Internal);
+            let package_id = create_test_module_id(package, ModuleOrigin::External);
+            graph.add_module(module_id.clone());
+            graph.add_module(package_id.clone());
+            let edge = DependencyEdge {
+                optional,
+                location: Some(SourceLocation {
+                    path: path.to_string(),
+                    cell: None,
+                    line: 1,
+                    column: 0,
+                }),
+                ..DependencyEdge::new(dependency_type)
+            };
+            graph.add_edge(&module_id, &package_id, edge).unwrap();
+        }
+
+        let source_groups = SourceGroups::new(&["scripts/**=dev".to_string()]).unwrap();
+        let result = analyze_external_dependencies_with_groups(&graph, &source_groups).unwrap();
+
+        // Test code may use dev dependencies, and guarded or type-only imports are fine
+        // anywhere, even when the package is also imported unguarded elsewhere
+        assert_eq!(result.misplaced_dependencies.len(), 1);
+        let misplaced = &result.misplaced_dependencies[0];
+        assert_eq!(misplaced.package_name, "pytest");
+        assert_eq!(misplaced.declared_groups, vec![DependencyGroup::Dev]);
+        assert_eq!(misplaced.code_groups, vec![DependencyGroup::Runtime]);
+        let paths: Vec<&str> = misplaced
+            .locations
+            .iter()
+            .map(|location| location.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["myapp/db.py"]);
+
+        let formatted = formatters::format_text_grouped(&result);
+        assert!(
+            formatted
+                .contains("  pytest (declared in dev, imported by runtime code) (myapp/db.py:1)\n")
+        );
+
+        reset_for_test();
+    }
+
+    #[test]
+    fn test_transitive_dependencies() {
+        use crate::pyproject::{init_for_test, reset_for_test};
+        use std::fs;
+        use tempfile::TempDir;
+
+        reset_for_test();
+
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("requirements.txt"), "pandas\n").unwrap();
+        init_for_test(temp_dir.path());
+
+        let mut graph = DependencyGraph::new();
+        let internal1 = create_test_module_id("myapp.main", ModuleOrigin::Internal);
+        let pandas_id = create_test_module_id("pandas", ModuleOrigin::External);
+        let dateutil_id = create_test_module_id("dateutil.parser", ModuleOrigin::External);
+        let yaml_id = create_test_module_id("yaml", ModuleOrigin::External);
+        for module in [&internal1, &pandas_id, &dateutil_id, &yaml_id] {
+            graph.add_module(module.clone());
+            if module != &internal1 {
+                graph
+                    .add_dependency(&internal1, module, DependencyType::Imports)
+                    .unwrap();
+            }
+        }
+        graph.add_package("pandas", Some("2.2.2"));
+        graph.add_package("python-dateutil", Some("2.9.0"));
+        graph
+            .add_package_requirement("pandas", "python-dateutil")
+            .unwrap();
+
+        let result = analyze_external_dependencies(&graph).unwrap();
+
+        // Transitive-only packages are still undeclared
+        assert_eq!(
+            result.undeclared_dependencies,
+            vec!["python-dateutil", "pyyaml"]
+        );
+        assert_eq!(result.transitive_dependencies.len(), 1);
+        let transitive = &result.transitive_dependencies[0];
+        assert_eq!(transitive.package_name, "python-dateutil");
+        assert_eq!(transitive.version.as_deref(), Some("2.9.0"));
+        assert_eq!(transitive.required_by, vec!["pandas"]);
+        assert_eq!(transitive.chain, vec!["pandas", "python-dateutil"]);
+
+        let formatted = formatters::format_text_grouped(&result);
+        assert!(formatted.contains("  python-dateutil 2.9.0 via pandas -> python-dateutil\n"));
+
+        reset_for_test();
+    }
+
+    #[test]
+    fn test_notebook_only_dependencies() {
+        use crate::graph::{DependencyEdge, SourceLocation};
+        use crate::pyproject::{init_for_test, reset_for_test};
+        use tempfile::TempDir;
+
+        reset_for_test();
+        let temp_dir = TempDir::new().unwrap();
+        init_for_test(temp_dir.path());
+
+        let mut graph = DependencyGraph::new();
+        let internal1 = create_test_module_id("myapp.main", ModuleOrigin::Internal);
+        let notebook = create_test_module_id("notebooks/eda.ipynb", ModuleOrigin::Notebook);
+        let numpy_id = create_test_module_id("numpy", ModuleOrigin::External);
+        let seaborn_id = create_test_module_id("seaborn", ModuleOrigin::External);
+        for module in [&internal1, &notebook, &numpy_id, &seaborn_id] {
+            graph.add_module(module.clone());
+        }
+        graph
+            .add_dependency(&internal1, &numpy_id, DependencyType::Imports)
+            .unwrap();
+        graph
+            .add_dependency(&notebook, &numpy_id, DependencyType::Imports)
+            .unwrap();
+        let seaborn_edge = DependencyEdge {
+            location: Some(SourceLocation {
+                path: "notebooks/eda.ipynb".to_string(),
+                cell: Some(3),
+                line: 2,
+                column: 1,
+            }),
+            ..DependencyEdge::new(DependencyType::Imports)
+        };
+        graph
+            .add_edge(&notebook, &seaborn_id, seaborn_edge)
+            .unwrap();
+
+        let result = analyze_external_dependencies(&graph).unwrap();
+
+        let usage = |name: &str| {
+            result
+                .frequency_analysis
+                .iter()
+                .find(|dep| dep.package_name == name)
+                .unwrap()
+        };
+        assert_eq!(usage("numpy").usage_count, 2);
+        assert!(!usage("numpy").notebook_only);
+        assert!(usage("seaborn").notebook_only);
+
+        let formatted = formatters::format_text_grouped(&result);
+        assert!(formatted.contains(
+            "=== Notebook-only Dependencies ===\n(Only imported by Jupyter notebooks)\n  seaborn (notebooks/eda.ipynb:cell 3:2)\n"
+        ));
+    }
+
+    #[test]
+    fn test_type_stubs_dependencies() {
+        use crate::pyproject::{init_for_test, reset_for_test};
+        use std::fs;
+        use tempfile::TempDir;
+
+        reset_for_test();
+
+        let temp_dir = TempDir::new().unwrap();
+        let pyproject_content = r#"
+[tool.poetry.dependencies]
+python = ">=3.10,<3.11"
+requests = "^2.31"
+pandas = "^2.0.3"
+
+[tool.poetry.group.dev.dependencies]
+types-requests = "^2.31"
+pandas-stubs = "^2.0"
+types-redis = "^4.6"
+"#;
+        fs::write(temp_dir.path().join("pyproject.toml"), pyproject_content).unwrap();
+        init_for_test(temp_dir.path());
+
+        let mut graph = DependencyGraph::new();
+        let internal1 = create_test_module_id("myapp.main", ModuleOrigin::Internal);
+        let requests_id = create_test_module_id("requests", ModuleOrigin::External);
+        let pandas_id = create_test_module_id("pandas", ModuleOrigin::External);
+        for module in [&internal1, &requests_id, &pandas_id] {
+            graph.add_module(module.clone());
+        }
+        graph
+            .add_dependency(&internal1, &requests_id, DependencyType::Imports)
+            .unwrap();
+        graph
+            .add_dependency(&internal1, &pandas_id, DependencyType::Imports)
+            .unwrap();
+
+        let result = analyze_external_dependencies(&graph).unwrap();
+
+        // Stubs of used packages are used, stubs of unused ones are not
+        assert_eq!(result.unused_dependencies, vec!["types-redis"]);
+        assert!(result.undeclared_dependencies.is_empty());
+
+        assert_eq!(stubbed_distribution("types-PyYAML"), Some("PyYAML"));
+        assert_eq!(stubbed_distribution("types-"), None);
+        assert_eq!(stubbed_distribution("requests"), None);
+    }
+
+    #[test]
+    fn test_optional_dependencies() {
+        use crate::graph::DependencyEdge;
+        use crate::imports::ImportScope;
+        use crate::pyproject::{init_for_test, reset_for_test};
+        use std::fs;
+        use tempfile::TempDir;
+
+        // Reset parser state to ensure clean test isolation
+        reset_for_test();
+
+        let temp_dir = TempDir::new().unwrap();
+        let pyproject_content = r#"
+[tool.poetry.dependencies]
+python = ">=3.10,<3.11"
+numpy = "^1.24.3"
+
+[project.optional-dependencies]
+fast = ["ujson>=5.0"]
+"#;
+        fs::write(temp_dir.path().join("pyproject.toml"), pyproject_content).unwrap();
+        init_for_test(temp_dir.path());
+
+        let mut graph = DependencyGraph::new();
+
+        let internal1 = create_test_module_id("myapp.main", ModuleOrigin::Internal);
+        let internal2 = create_test_module_id("myapp.utils", ModuleOrigin::Internal);
+        let numpy_id = create_test_module_id("numpy", ModuleOrigin::External);
+        let ujson_id = create_test_module_id("ujson", ModuleOrigin::External);
+        let redis_id = create_test_module_id("redis", ModuleOrigin::External);
+
+        for module in [&internal1, &internal2, &numpy_id, &ujson_id, &redis_id] {
+            graph.add_module(module.clone());
+        }
+
+        let optional_edge = DependencyEdge {
+            dependency_type: DependencyType::Imports,
+            scope: Some(ImportScope::Conditional),
+            optional: true,
+            location: None,
+            dynamic_package: None,
+            via: None,
+        };
+
+        // numpy is optional in one module but required in another
+        graph
+            .add_dependency(&internal1, &numpy_id, DependencyType::Imports)
+            .unwrap();
+        graph
+            .add_edge(&internal2, &numpy_id, optional_edge.clone())
+            .unwrap();
+        graph
+            .add_edge(&internal1, &ujson_id, optional_edge.clone())
+            .unwrap();
+        graph
+            .add_edge(&internal2, &redis_id, optional_edge)
+            .unwrap();
+
+        let result = analyze_external_dependencies(&graph).unwrap();
+
+        let numpy_usage = result
+            .frequency_analysis
+            .iter()
+            .find(|dep| dep.package_name == "numpy")
+            .unwrap();
+        assert!(!numpy_usage.optional);
+
+        // Optional-only packages are not reported as undeclared
+        assert!(result.undeclared_dependencies.is_empty());
+        assert!(result.unused_dependencies.is_empty());
+
+        assert_eq!(result.optional_dependencies.len(), 2);
+        let redis = &result.optional_dependencies[0];
+        assert_eq!(redis.package_name, "redis");
+        assert!(!redis.declared);
+        assert!(redis.extras.is_empty());
+        let ujson = &result.optional_dependencies[1];
+        assert_eq!(ujson.package_name, "ujson");
+        assert_eq!(ujson.extras, vec!["fast"]);
+
+        let formatted = formatters::format_text_grouped(&result);
+        assert!(formatted.contains("ujson (extras: fast)"));
+        assert!(formatted.contains("redis (undeclared, not in any extras group)"));
+    }
+
     #[test]
     fn test_get_python_standard_library_modules() {
         let stdlib_modules = get_python_standard_library_modules();
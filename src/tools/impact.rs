@@ -1,10 +1,11 @@
 use crate::graph::{DependencyGraph, DependencyType};
 use crate::imports::ModuleIdentifier;
-use crate::tools::common;
+use crate::tools::common::{self, GroupedModule, ImportLocations};
 use anyhow::Result;
+use serde::Serialize;
 
 /// Result of impact analysis for a module
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ImpactAnalysisResult {
     /// The module that was analyzed
     pub target_module: String,
@@ -12,18 +13,30 @@ pub struct ImpactAnalysisResult {
     pub affected_modules: Vec<(String, DependencyType, usize)>,
     /// Total count before deduplication
     pub total_affected_count: usize,
+    /// Jupyter notebooks affected by changes to the target (sorted by path)
+    pub affected_notebooks: Vec<String>,
+    /// Where each dependent imports the target (or one of its submodules)
+    pub import_locations: ImportLocations,
 }
 
 pub fn get_impact_analysis(
     graph: &DependencyGraph,
     module_id: &ModuleIdentifier,
-) -> Result<(Vec<(String, DependencyType, usize)>, usize)> {
+    include_type_only: bool,
+) -> Result<(Vec<GroupedModule>, usize)> {
     // Collect dependents of the module and of all its descendants.
     let mut affected_modules = graph.get_transitive_dependents_with_types(module_id)?;
 
-    // Filter out test modules
+    // Filter out modules that only depend on the target for type checking
+    if !include_type_only {
+        affected_modules.retain(|(_, dep_type)| *dep_type != DependencyType::TypeOnlyImports);
+    }
+
+    // Filter out test modules and notebooks
     affected_modules.retain(|(module_path, _)| {
-        !module_path.contains(".tests.") && !module_path.ends_with(".tests")
+        !module_path.contains(".tests.")
+            && !module_path.ends_with(".tests")
+            && !graph.is_notebook(module_path)
     });
 
     // Add parent modules if all their submodules are affected
@@ -94,7 +107,11 @@ fn find_parent_modules_with_all_children_affected(
 }
 
 /// Analyzes the impact of changes to the specified module
-pub fn analyze_impact(graph: &DependencyGraph, module_name: &str) -> Result<ImpactAnalysisResult> {
+pub fn analyze_impact(
+    graph: &DependencyGraph,
+    module_name: &str,
+    include_type_only: bool,
+) -> Result<ImpactAnalysisResult> {
     // Find the target module in the graph
     let target_module = graph
         .all_modules()
@@ -102,12 +119,28 @@ pub fn analyze_impact(graph: &DependencyGraph, module_name: &str) -> Result<Impa
         .ok_or_else(|| anyhow::anyhow!("Module '{}' not found in dependency graph", module_name))?;
 
     // Get impact analysis from the graph
-    let (affected_modules, total_count) = get_impact_analysis(graph, target_module)?;
+    let (affected_modules, total_count) =
+        get_impact_analysis(graph, target_module, include_type_only)?;
+    let import_locations = graph.get_transitive_dependent_locations(target_module)?;
+
+    let mut affected_notebooks: Vec<String> = graph
+        .get_transitive_dependents_with_types(target_module)?
+        .into_iter()
+        .filter(|(module_path, dep_type)| {
+            graph.is_notebook(module_path)
+                && (include_type_only || *dep_type != DependencyType::TypeOnlyImports)
+        })
+        .map(|(module_path, _)| module_path)
+        .collect();
+    affected_notebooks.sort();
+    affected_notebooks.dedup();
 
     Ok(ImpactAnalysisResult {
         target_module: target_module.canonical_path.clone(),
         affected_modules,
         total_affected_count: total_count,
+        affected_notebooks,
+        import_locations,
     })
 }
 
@@ -121,11 +154,33 @@ pub mod formatters {
     /// Common formatting structure for all output formats
     fn format_with_body(result: &ImpactAnalysisResult, body: String) -> String {
         format!(
-            "Modules depending on '{}':\n{}Total: {} modules impacted by {}\n",
-            result.target_module, body, result.total_affected_count, result.target_module
+            "Modules depending on '{}':\n{}{}Total: {} modules impacted by {}\n",
+            result.target_module,
+            body,
+            format_notebooks(result),
+            result.total_affected_count,
+            result.target_module
         )
     }
 
+    /// Formats the affected notebooks section (empty when no notebook is affected)
+    fn format_notebooks(result: &ImpactAnalysisResult) -> String {
+        if result.affected_notebooks.is_empty() {
+            return String::new();
+        }
+
+        let mut output = format!("Notebooks depending on '{}':\n", result.target_module);
+        for notebook in &result.affected_notebooks {
+            let locations = result.import_locations.get(notebook);
+            output.push_str(&format!(
+                "  {}{}\n",
+                notebook,
+                common_formatters::format_locations(locations)
+            ));
+        }
+        output
+    }
+
     /// Formats results as human-readable text
     pub fn format_text(result: &ImpactAnalysisResult) -> String {
         let body = if result.affected_modules.is_empty() {
@@ -136,7 +191,12 @@ pub mod formatters {
                 if *count > 1 {
                     output.push_str(&format!("({} submodules) {}\n", count, module));
                 } else {
-                    output.push_str(&format!("{}\n", module));
+                    let locations = result.import_locations.get(module);
+                    output.push_str(&format!(
+                        "{}{}\n",
+                        module,
+                        common_formatters::format_locations(locations)
+                    ));
                 }
             }
             output
@@ -150,7 +210,10 @@ pub mod formatters {
         let body = if result.affected_modules.is_empty() {
             format!("{}\n", NO_DEPENDENCIES_MSG)
         } else {
-            common_formatters::format_grouped_modules(&result.affected_modules)
+            common_formatters::format_grouped_modules(
+                &result.affected_modules,
+                &result.import_locations,
+            )
         };
 
         format_with_body(result, body)
@@ -192,7 +255,7 @@ mod tests {
             .unwrap();
 
         // Analyze impact of utils
-        let result = analyze_impact(&graph, "utils").unwrap();
+        let result = analyze_impact(&graph, "utils", false).unwrap();
 
         assert_eq!(result.target_module, "utils");
         assert_eq!(result.affected_modules.len(), 3);
@@ -209,6 +272,39 @@ mod tests {
         assert!(affected_names.contains(&&"tests.test_utils".to_string()));
     }
 
+    #[test]
+    fn test_impact_on_notebooks() {
+        let mut graph = DependencyGraph::new();
+
+        let main = create_test_module_id("main", ModuleOrigin::Internal);
+        let utils = create_test_module_id("utils", ModuleOrigin::Internal);
+        let notebook = create_test_module_id("notebooks/churn.ipynb", ModuleOrigin::Notebook);
+        for module in [&main, &utils, &notebook] {
+            graph.add_module(module.clone());
+        }
+        graph
+            .add_dependency(&main, &utils, DependencyType::Imports)
+            .unwrap();
+        graph
+            .add_dependency(&notebook, &utils, DependencyType::Imports)
+            .unwrap();
+
+        let result = analyze_impact(&graph, "utils", false).unwrap();
+
+        // Notebooks are reported apart from the modules
+        let affected_names: Vec<&str> = result
+            .affected_modules
+            .iter()
+            .map(|(name, _, _)| name.as_str())
+            .collect();
+        assert_eq!(affected_names, vec!["main", "utils"]);
+        assert_eq!(result.affected_notebooks, vec!["notebooks/churn.ipynb"]);
+
+        let formatted = formatters::format_text(&result);
+        assert!(formatted.contains("Notebooks depending on 'utils':\n  notebooks/churn.ipynb\n"));
+        assert!(formatted.contains("Total: 2 modules impacted by utils"));
+    }
+
     #[test]
     fn test_format_text() {
         let result = ImpactAnalysisResult {
@@ -218,6 +314,8 @@ mod tests {
                 ("api".to_string(), DependencyType::Imports, 3),
             ],
             total_affected_count: 4,
+            affected_notebooks: Vec::new(),
+            import_locations: Default::default(),
         };
 
         let formatted = formatters::format_text(&result);
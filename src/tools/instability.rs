@@ -2,9 +2,10 @@ use crate::graph::DependencyGraph;
 use crate::imports::ModuleOrigin;
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
 
 /// Result of instability analysis
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct InstabilityAnalysisResult {
     /// Modules with their instability scores (sorted by score descending)
     pub instability_modules: Vec<(String, f64)>,
@@ -8,3 +8,4 @@ pub mod external;
 pub mod impact;
 pub mod instability;
 pub mod pressure;
+pub mod usages;
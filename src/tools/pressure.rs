@@ -3,9 +3,10 @@ use crate::imports::ModuleOrigin;
 use crate::tools::impact::get_impact_analysis;
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
 
 /// Result of pressure points analysis
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct PressureAnalysisResult {
     /// Modules with their dependent counts (sorted by count descending)
     pub pressure_modules: Vec<(String, usize)>,
@@ -41,7 +42,7 @@ pub fn analyze_pressure(graph: &DependencyGraph) -> Result<PressureAnalysisResul
     for module in internal_modules {
         pb.set_message(format!("Analyzing {}", module.canonical_path));
 
-        let (affected_modules, _) = get_impact_analysis(graph, module)?;
+        let (affected_modules, _) = get_impact_analysis(graph, module, false)?;
         let dependent_count = affected_modules.len();
 
         // Only include modules that have more than 1 dependent (exclude self-only dependencies)
@@ -55,7 +56,7 @@ pub fn analyze_pressure(graph: &DependencyGraph) -> Result<PressureAnalysisResul
     pb.finish_with_message("Pressure analysis complete");
 
     // Sort by dependent count (descending) - highest pressure first
-    pressure_modules.sort_by(|a, b| b.1.cmp(&a.1));
+    pressure_modules.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
 
     Ok(PressureAnalysisResult { pressure_modules })
 }
@@ -0,0 +1,186 @@
+use crate::graph::{DependencyGraph, DependencyType, SourceLocation};
+use crate::imports::SymbolKind;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Result of usages analysis for a symbol
+#[derive(Debug, Serialize)]
+pub struct UsagesAnalysisResult {
+    /// The symbol that was analyzed
+    pub target_symbol: String,
+    /// Whether the symbol is a function, class or constant
+    pub kind: SymbolKind,
+    /// Modules importing the symbol (sorted by module path) with where they import it
+    pub users: Vec<(String, DependencyType, Vec<SourceLocation>)>,
+}
+
+/// Analyzes which modules import the specified symbol
+///
+/// The graph must be built with symbol nodes. Imports through package facades count
+/// as usages of the symbol they re-export.
+pub fn analyze_usages(
+    graph: &DependencyGraph,
+    symbol_name: &str,
+    include_type_only: bool,
+) -> Result<UsagesAnalysisResult> {
+    let kind = graph
+        .get_symbol_kind(symbol_name)
+        .ok_or_else(|| anyhow::anyhow!("Symbol '{}' not found in dependency graph", symbol_name))?;
+
+    let mut users: BTreeMap<String, (DependencyType, Vec<SourceLocation>)> = BTreeMap::new();
+    for (module, edge) in graph.get_symbol_dependents(symbol_name)? {
+        if !include_type_only && edge.dependency_type == DependencyType::TypeOnlyImports {
+            continue;
+        }
+        let (dependency_type, locations) = users
+            .entry(module)
+            .or_insert_with(|| (edge.dependency_type.clone(), Vec::new()));
+        // Prefer runtime relationships over type-only ones
+        if *dependency_type == DependencyType::TypeOnlyImports {
+            *dependency_type = edge.dependency_type.clone();
+        }
+        locations.extend(edge.location);
+    }
+
+    let users = users
+        .into_iter()
+        .map(|(module, (dependency_type, mut locations))| {
+            locations.sort();
+            locations.dedup();
+            (module, dependency_type, locations)
+        })
+        .collect();
+
+    Ok(UsagesAnalysisResult {
+        target_symbol: symbol_name.to_string(),
+        kind,
+        users,
+    })
+}
+
+/// Formats usages analysis results for display
+pub mod formatters {
+    use super::UsagesAnalysisResult;
+    use crate::graph::DependencyType;
+    use crate::tools::common::formatters as common_formatters;
+
+    const NO_USAGES_MSG: &str = "(no usages found)";
+
+    /// Formats results as human-readable text
+    pub fn format_text(result: &UsagesAnalysisResult) -> String {
+        let mut output = format!(
+            "Modules using '{}' ({:?}):\n",
+            result.target_symbol, result.kind
+        );
+
+        if result.users.is_empty() {
+            output.push_str(&format!("{}\n", NO_USAGES_MSG));
+        } else {
+            for (module, dependency_type, locations) in &result.users {
+                let type_only = if *dependency_type == DependencyType::TypeOnlyImports {
+                    " [type-only]"
+                } else {
+                    ""
+                };
+                output.push_str(&format!(
+                    "  {}{}{}\n",
+                    module,
+                    type_only,
+                    common_formatters::format_locations(Some(locations))
+                ));
+            }
+        }
+
+        output.push_str(&format!(
+            "Total: {} modules use {}\n",
+            result.users.len(),
+            result.target_symbol
+        ));
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::DependencyEdge;
+    use crate::imports::{ModuleIdentifier, ModuleOrigin};
+
+    fn create_test_module_id(name: &str) -> ModuleIdentifier {
+        ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: name.to_string(),
+        }
+    }
+
+    fn import_edge(dependency_type: DependencyType, path: &str, line: usize) -> DependencyEdge {
+        DependencyEdge {
+            location: Some(SourceLocation {
+                path: path.to_string(),
+                cell: None,
+                line,
+                column: 1,
+            }),
+            ..DependencyEdge::new(dependency_type)
+        }
+    }
+
+    #[test]
+    fn test_symbol_usages() {
+        let mut graph = DependencyGraph::new();
+        let billing = create_test_module_id("billing");
+        let api = create_test_module_id("api");
+        let types = create_test_module_id("types");
+        for module in [&billing, &api, &types] {
+            graph.add_module(module.clone());
+        }
+
+        graph
+            .add_symbol(&billing, "charge_card", SymbolKind::Function)
+            .unwrap();
+        graph
+            .add_symbol(&billing, "refund", SymbolKind::Function)
+            .unwrap();
+        graph
+            .add_symbol_import(
+                &api,
+                "billing.charge_card",
+                import_edge(DependencyType::Imports, "api.py", 3),
+            )
+            .unwrap();
+        graph
+            .add_symbol_import(
+                &types,
+                "billing.charge_card",
+                import_edge(DependencyType::TypeOnlyImports, "types.py", 7),
+            )
+            .unwrap();
+
+        let result = analyze_usages(&graph, "billing.charge_card", false).unwrap();
+        assert_eq!(result.kind, SymbolKind::Function);
+        assert_eq!(result.users.len(), 1);
+        assert_eq!(result.users[0].0, "api");
+
+        let result = analyze_usages(&graph, "billing.charge_card", true).unwrap();
+        let users: Vec<&str> = result.users.iter().map(|(m, _, _)| m.as_str()).collect();
+        assert_eq!(users, vec!["api", "types"]);
+
+        let formatted = formatters::format_text(&result);
+        assert!(formatted.contains("Modules using 'billing.charge_card' (Function):"));
+        assert!(formatted.contains("  api (api.py:3)"));
+        assert!(formatted.contains("  types [type-only] (types.py:7)"));
+        assert!(formatted.contains("Total: 2 modules use billing.charge_card"));
+
+        // Unused symbols have no users, unknown ones are errors
+        let result = analyze_usages(&graph, "billing.refund", false).unwrap();
+        assert!(result.users.is_empty());
+        assert!(analyze_usages(&graph, "billing.missing", false).is_err());
+
+        // Symbol nodes stay out of module-level queries
+        assert_eq!(graph.module_count(), 3);
+        assert!(graph.get_dependents(&billing).unwrap().is_empty());
+        assert!(graph.get_dependencies(&api).unwrap().is_empty());
+    }
+}
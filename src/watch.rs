@@ -0,0 +1,413 @@
+use crate::crawler::{
+    GraphOptions, analyze_python_directory_recursive_with_options,
+    build_directory_dependency_graph_with_options, is_python_file, is_stub_file, known_modules,
+    update_file_imports,
+};
+use crate::graph::DependencyGraph;
+use crate::lockfile::LOCKFILES;
+use crate::tools::cycles::Cycle;
+use anyhow::Result;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Time to wait for more changes after one is seen, so that a save touching several
+/// files is handled once.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Files of the project root configuring the analysis: a change reloads everything.
+const PROJECT_FILES: [&str; 6] = [
+    "pyproject.toml",
+    "setup.cfg",
+    "setup.py",
+    crate::config::CONFIG_FILE,
+    ".gitignore",
+    ".ignore",
+];
+
+/// Returns true if a path, relative to the project root, configures the analysis.
+pub fn is_project_file(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|name| PROJECT_FILES.contains(&name) || LOCKFILES.contains(&name))
+}
+
+/// How a [`LiveGraph`] handled a set of changed files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Update {
+    /// No analyzed file changed
+    Unchanged,
+    /// The imports of the changed files were replaced in place
+    Patched(usize),
+    /// The graph was built again (reusing the parse cache when enabled)
+    Rebuilt,
+}
+
+/// A dependency graph kept up to date with the files of a directory.
+pub struct LiveGraph {
+    dir_path: PathBuf,
+    options: GraphOptions,
+    graph: DependencyGraph,
+    /// Analyzed files, relative to the directory
+    files: HashSet<PathBuf>,
+    known_modules: HashSet<String>,
+}
+
+impl LiveGraph {
+    /// Builds the graph of a directory.
+    pub fn build(dir_path: &Path, options: GraphOptions) -> Result<Self> {
+        let mut live = Self {
+            dir_path: dir_path.to_path_buf(),
+            options,
+            graph: DependencyGraph::new(),
+            files: HashSet::new(),
+            known_modules: HashSet::new(),
+        };
+        live.rebuild()?;
+        Ok(live)
+    }
+
+    pub fn graph(&self) -> &DependencyGraph {
+        &self.graph
+    }
+
+    /// Updates the graph after files changed, given by their paths relative to the
+    /// directory.
+    ///
+    /// Edited files are re-parsed and their imports patched into the graph. Added or
+    /// removed files, stubs, and graphs with re-export tracing or symbols are rebuilt.
+    pub fn update(&mut self, changed: &[PathBuf]) -> Result<Update> {
+        let changed: Vec<&PathBuf> = changed.iter().filter(|path| is_python_file(path)).collect();
+        if changed.is_empty() {
+            return Ok(Update::Unchanged);
+        }
+
+        // Created or deleted files may be outside of the analyzed ones (ignored, excluded)
+        let exists = |path: &PathBuf| self.dir_path.join(path).is_file();
+        if changed
+            .iter()
+            .any(|path| self.files.contains(*path) != exists(path))
+            && self.discover()? != self.files
+        {
+            self.rebuild()?;
+            return Ok(Update::Rebuilt);
+        }
+
+        let changed: Vec<&PathBuf> = changed
+            .into_iter()
+            .filter(|path| self.files.contains(*path))
+            .collect();
+        if changed.is_empty() {
+            return Ok(Update::Unchanged);
+        }
+        let has_stub =
+            |path: &PathBuf| is_stub_file(path) || self.files.contains(&path.with_extension("pyi"));
+        if self.options.trace_reexports
+            || self.options.symbols
+            || changed.iter().any(|p| has_stub(p))
+        {
+            self.rebuild()?;
+            return Ok(Update::Rebuilt);
+        }
+
+        for path in &changed {
+            let file_path = self.dir_path.join(path);
+            if !update_file_imports(
+                &mut self.graph,
+                &self.dir_path,
+                &file_path,
+                &self.known_modules,
+            )? {
+                self.rebuild()?;
+                return Ok(Update::Rebuilt);
+            }
+        }
+        Ok(Update::Patched(changed.len()))
+    }
+
+    /// Builds the graph again from all the files of the directory.
+    pub fn rebuild(&mut self) -> Result<()> {
+        self.graph = build_directory_dependency_graph_with_options(&self.dir_path, &self.options)?;
+        self.files = self.discover()?;
+        let python_files: Vec<PathBuf> = self
+            .files
+            .iter()
+            .map(|path| self.dir_path.join(path))
+            .collect();
+        self.known_modules = known_modules(&self.dir_path, &python_files);
+        Ok(())
+    }
+
+    /// Lists the analyzed files of the directory, relative to it.
+    fn discover(&self) -> Result<HashSet<PathBuf>> {
+        Ok(analyze_python_directory_recursive_with_options(
+            &self.dir_path,
+            &self.options.discovery,
+        )?
+        .into_iter()
+        .map(|path| {
+            path.strip_prefix(&self.dir_path)
+                .map(Path::to_path_buf)
+                .unwrap_or(path)
+        })
+        .collect())
+    }
+}
+
+/// Watches a directory recursively and calls `on_change` with the paths changed in it,
+/// relative to the directory, until watching or `on_change` fails.
+pub fn watch(dir_path: &Path, mut on_change: impl FnMut(&[PathBuf]) -> Result<()>) -> Result<()> {
+    let root = dir_path.canonicalize()?;
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    while let Ok(event) = receiver.recv() {
+        let mut changed = BTreeSet::new();
+        let mut received = Some(event);
+        while let Some(result) = received {
+            let event = result?;
+            // Reading files, the analysis included, is not a change
+            if !matches!(event.kind, EventKind::Access(_)) {
+                changed.extend(
+                    event
+                        .paths
+                        .iter()
+                        .filter_map(|path| path.strip_prefix(&root).ok())
+                        .map(Path::to_path_buf),
+                );
+            }
+            received = receiver.recv_timeout(DEBOUNCE).ok();
+        }
+        if !changed.is_empty() {
+            on_change(&changed.into_iter().collect::<Vec<_>>())?;
+        }
+    }
+    Ok(())
+}
+
+/// Cycles that appeared or disappeared between two runs of the `cycles` command.
+#[derive(Debug, Default, PartialEq)]
+pub struct CycleChanges {
+    pub introduced: Vec<Cycle>,
+    pub resolved: Vec<Cycle>,
+}
+
+impl CycleChanges {
+    /// Compares the cycles of two runs; a cycle reported from another starting module is
+    /// the same cycle.
+    pub fn between(previous: &[Cycle], current: &[Cycle]) -> Self {
+        let previous_keys: HashSet<Vec<&str>> = previous.iter().map(cycle_key).collect();
+        let current_keys: HashSet<Vec<&str>> = current.iter().map(cycle_key).collect();
+        Self {
+            introduced: current
+                .iter()
+                .filter(|cycle| !previous_keys.contains(&cycle_key(cycle)))
+                .cloned()
+                .collect(),
+            resolved: previous
+                .iter()
+                .filter(|cycle| !current_keys.contains(&cycle_key(cycle)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.introduced.is_empty() && self.resolved.is_empty()
+    }
+
+    /// Formats the changes as human-readable text
+    pub fn format_text(&self) -> String {
+        let mut output = String::new();
+        for cycle in &self.introduced {
+            output.push_str(&format!("New cycle introduced: {}\n", cycle.format_cycle()));
+        }
+        for cycle in &self.resolved {
+            output.push_str(&format!("Cycle resolved: {}\n", cycle.format_cycle()));
+        }
+        output
+    }
+}
+
+/// The modules of a cycle, starting from the smallest one.
+fn cycle_key(cycle: &Cycle) -> Vec<&str> {
+    let start = (0..cycle.modules.len())
+        .min_by_key(|&i| &cycle.modules[i])
+        .unwrap_or(0);
+    cycle.modules[start..]
+        .iter()
+        .chain(&cycle.modules[..start])
+        .map(String::as_str)
+        .collect()
+}
+
+/// Lists the lines removed from (`- `) and added to (`+ `) the output of a command, in
+/// output order.
+pub fn diff_lines(previous: &str, current: &str) -> String {
+    let mut remaining: HashMap<&str, isize> = HashMap::new();
+    for line in current.lines() {
+        *remaining.entry(line).or_default() += 1;
+    }
+    let mut removed = Vec::new();
+    for line in previous.lines() {
+        let count = remaining.entry(line).or_default();
+        if *count > 0 {
+            *count -= 1;
+        } else {
+            removed.push(line);
+        }
+    }
+
+    let mut kept: HashMap<&str, isize> = HashMap::new();
+    for line in previous.lines() {
+        *kept.entry(line).or_default() += 1;
+    }
+    let mut added = Vec::new();
+    for line in current.lines() {
+        let count = kept.entry(line).or_default();
+        if *count > 0 {
+            *count -= 1;
+        } else {
+            added.push(line);
+        }
+    }
+
+    let mut output = String::new();
+    for line in removed {
+        output.push_str(&format!("- {}\n", line));
+    }
+    for line in added {
+        output.push_str(&format!("+ {}\n", line));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imports::{ModuleIdentifier, ModuleOrigin};
+    use crate::tools::cycles::detect_cycles;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn internal(name: &str) -> ModuleIdentifier {
+        ModuleIdentifier {
+            origin: ModuleOrigin::Internal,
+            canonical_path: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_live_graph_update() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("app")).unwrap();
+        fs::write(root.join("app/__init__.py"), "").unwrap();
+        fs::write(root.join("app/a.py"), "import os\nfrom app import b\n").unwrap();
+        fs::write(root.join("app/b.py"), "import json\n").unwrap();
+
+        let mut live = LiveGraph::build(root, GraphOptions::default()).unwrap();
+        let cycles = |live: &LiveGraph| detect_cycles(live.graph(), false).unwrap().cycles;
+        assert!(cycles(&live).is_empty());
+        assert_eq!(
+            live.update(&[PathBuf::from("README.md")]).unwrap(),
+            Update::Unchanged
+        );
+
+        // An edit importing a module of the graph is patched in place
+        fs::write(
+            root.join("app/b.py"),
+            "import json\nimport os\nfrom app import a\n",
+        )
+        .unwrap();
+        let update = live.update(&[PathBuf::from("app/b.py")]).unwrap();
+        assert_eq!(update, Update::Patched(1));
+        let mut dependencies = live.graph().get_dependencies(&internal("app.b")).unwrap();
+        dependencies.sort();
+        assert_eq!(dependencies, vec!["app", "app.a", "json", "os"]);
+        let introduced = CycleChanges::between(&[], &cycles(&live));
+        assert_eq!(introduced.introduced.len(), 1);
+
+        // Dropping the last import of a third-party module rebuilds the graph
+        fs::write(root.join("app/b.py"), "import os\n").unwrap();
+        let update = live.update(&[PathBuf::from("app/b.py")]).unwrap();
+        assert_eq!(update, Update::Rebuilt);
+        assert!(cycles(&live).is_empty());
+        assert!(
+            !live
+                .graph()
+                .all_modules()
+                .any(|m| m.canonical_path == "json")
+        );
+
+        // So does a new file
+        fs::write(root.join("app/c.py"), "from app import a\n").unwrap();
+        let update = live.update(&[PathBuf::from("app/c.py")]).unwrap();
+        assert_eq!(update, Update::Rebuilt);
+        assert!(live.graph().contains_module(&internal("app.c")));
+    }
+
+    #[test]
+    fn test_cycle_changes() {
+        let cycle = |modules: &[&str]| Cycle::new(modules.iter().map(|m| m.to_string()).collect());
+        let previous = vec![cycle(&["a", "b"]), cycle(&["c", "d", "e"])];
+        let current = vec![cycle(&["d", "e", "c"]), cycle(&["x", "y"])];
+
+        let changes = CycleChanges::between(&previous, &current);
+        assert_eq!(changes.introduced, vec![cycle(&["x", "y"])]);
+        assert_eq!(changes.resolved, vec![cycle(&["a", "b"])]);
+        assert_eq!(
+            changes.format_text(),
+            "New cycle introduced: x → y → x\nCycle resolved: a → b → a\n"
+        );
+        assert!(CycleChanges::between(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_cycle_changes_same_graph() {
+        use crate::graph::{DependencyGraph, DependencyType};
+
+        // Overlapping cycles, which a traversal may report from different starts
+        let mut graph = DependencyGraph::new();
+        let modules = ["pkg.a", "pkg.b", "pkg.c", "pkg.d", "pkg.e"].map(internal);
+        for module in &modules {
+            graph.add_module(module.clone());
+        }
+        let [a, b, c, d, e] = &modules;
+        for (from, to) in [
+            (a, b),
+            (a, c),
+            (b, a),
+            (c, b),
+            (b, d),
+            (d, e),
+            (e, c),
+            (e, a),
+        ] {
+            graph
+                .add_dependency(from, to, DependencyType::Imports)
+                .unwrap();
+        }
+
+        let first = detect_cycles(&graph, false).unwrap().cycles;
+        assert!(!first.is_empty());
+        for _ in 0..50 {
+            let current = detect_cycles(&graph, false).unwrap().cycles;
+            assert!(CycleChanges::between(&first, &current).is_empty());
+            assert_eq!(current, first);
+        }
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        let previous = "Count: 2\nfoo\nbar\nbar\n";
+        let current = "Count: 3\nbar\nfoo\nbaz\n";
+        assert_eq!(
+            diff_lines(previous, current),
+            "- Count: 2\n- bar\n+ Count: 3\n+ baz\n"
+        );
+        assert_eq!(diff_lines(current, current), "");
+    }
+}